@@ -0,0 +1,169 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use nai_tokenizers::glm45_template::{
+    Chat, ContentPart, ContextState, MAX_CHAT_PLACEHOLDERS, MAX_PART_PLACEHOLDERS, Message,
+    PlaceholderLimitError, PrefillType, ReasoningEnabled, ReasoningRetention, Version,
+    VisionConfig,
+};
+
+/// Sizes and frame counts cover the full `u32` range; anything over the placeholder
+/// limits has to fail with an error rather than allocate.
+#[derive(Arbitrary, Debug)]
+enum Part {
+    Text(String),
+    Image {
+        width: u32,
+        height: u32,
+    },
+    Video {
+        frames: u32,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl From<Part> for ContentPart {
+    fn from(part: Part) -> Self {
+        match part {
+            Part::Text(text) => ContentPart::Text { text },
+            Part::Image { width, height } => ContentPart::Image { width, height },
+            Part::Video {
+                frames,
+                width,
+                height,
+            } => ContentPart::Video {
+                frames,
+                width,
+                height,
+            },
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum FuzzMessage {
+    System(String),
+    User(String),
+    MultimodalUser(Vec<Part>),
+    Assistant {
+        content: String,
+        reasoning_content: Option<String>,
+    },
+}
+
+impl From<FuzzMessage> for Message {
+    fn from(message: FuzzMessage) -> Self {
+        match message {
+            FuzzMessage::System(content) => Message::System { content },
+            FuzzMessage::User(content) => Message::User { content },
+            FuzzMessage::MultimodalUser(parts) => Message::MultimodalUser {
+                content: parts.into_iter().map(|p| p.into()).collect(),
+            },
+            FuzzMessage::Assistant {
+                content,
+                reasoning_content,
+            } => Message::Assistant {
+                content,
+                reasoning_content,
+            },
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum Prefill {
+    None,
+    Canonical,
+    PartialReasoning(String),
+    FullReasoning {
+        reasoning_content: String,
+        content: String,
+    },
+}
+
+impl From<Prefill> for PrefillType {
+    fn from(prefill: Prefill) -> Self {
+        match prefill {
+            Prefill::None => PrefillType::None,
+            Prefill::Canonical => PrefillType::Canonical,
+            Prefill::PartialReasoning(reasoning_content) => {
+                PrefillType::PartialReasoning { reasoning_content }
+            }
+            Prefill::FullReasoning {
+                reasoning_content,
+                content,
+            } => PrefillType::FullReasoning {
+                reasoning_content,
+                content,
+            },
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum Retention {
+    StripPast,
+    SinceLastUser,
+    LastTurns(u8),
+    All,
+}
+
+impl From<Retention> for ReasoningRetention {
+    fn from(retention: Retention) -> Self {
+        match retention {
+            Retention::StripPast => ReasoningRetention::StripPast,
+            Retention::SinceLastUser => ReasoningRetention::SinceLastUser,
+            Retention::LastTurns(turns) => ReasoningRetention::LastTurns(turns.into()),
+            Retention::All => ReasoningRetention::All,
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    messages: Vec<FuzzMessage>,
+    prefill: Prefill,
+    reasoning_enabled: bool,
+    glm47: bool,
+    ignore_message_position: bool,
+    retention: Retention,
+    vision: (u32, u32, u32),
+}
+
+fuzz_target!(|input: Input| {
+    let (patch_size, spatial_merge_size, temporal_patch_size) = input.vision;
+    let vision = VisionConfig {
+        patch_size,
+        spatial_merge_size,
+        temporal_patch_size,
+    };
+
+    let reasoning = if input.reasoning_enabled {
+        ReasoningEnabled::Yes
+    } else {
+        ReasoningEnabled::No
+    };
+    let version = if input.glm47 {
+        Version::GLM47
+    } else {
+        Version::GLM456
+    };
+    let chat = Chat {
+        messages: input.messages.into_iter().map(|m| m.into()).collect(),
+    };
+    let rendered = ContextState::new_with_version(reasoning, version)
+        .with_vision_config(vision)
+        .with_reasoning_retention(input.retention.into())
+        .chat_with_options(&chat, input.prefill.into(), input.ignore_message_position);
+    match rendered {
+        Ok(rendered) => assert!(rendered.starts_with("[gMASK]<sop>")),
+        Err(PlaceholderLimitError::Part { placeholders, .. }) => {
+            assert!(placeholders > MAX_PART_PLACEHOLDERS)
+        }
+        Err(PlaceholderLimitError::Chat { placeholders, .. }) => {
+            assert!(placeholders > MAX_CHAT_PLACEHOLDERS)
+        }
+    }
+});
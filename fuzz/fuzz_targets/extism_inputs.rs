@@ -0,0 +1,43 @@
+#![no_main]
+
+//! Decodes arbitrary bytes as every Extism request type, the way the msgpack exports
+//! (and the v2 exports' JSON sniffing) do, and renders any chat that decodes.
+
+use libfuzzer_sys::fuzz_target;
+use nai_tokenizers::vocab::SearchQuery;
+use nai_tokenizers_extism_input::{
+    ChatTemplateInput, ChunkTextInput, DetokenizeInput, EncodeBatchInput, StopMatcherNewInput,
+    StopMatcherPushInput, TemplateDefaults, TokenizeBytesInput, TokenizeInput, TruncateInput,
+    VocabLookupInput,
+};
+use serde::de::DeserializeOwned;
+
+fn decode<T: DeserializeOwned>(data: &[u8]) -> Option<T> {
+    let msgpack = rmp_serde::from_slice(data).ok();
+    let json = serde_json::from_slice(data).ok();
+    msgpack.or(json)
+}
+
+/// Renders the way the plugin's `chat_template` export does with the default config.
+fn render(input: ChatTemplateInput) {
+    if let Ok(request) = input.resolve(&TemplateDefaults::default()) {
+        let _ = request.render();
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode::<TokenizeInput>(data);
+    let _ = decode::<TokenizeBytesInput>(data);
+    let _ = decode::<DetokenizeInput>(data);
+    let _ = decode::<StopMatcherNewInput>(data);
+    let _ = decode::<StopMatcherPushInput>(data);
+    let _ = decode::<VocabLookupInput>(data);
+    let _ = decode::<SearchQuery>(data);
+    let _ = decode::<ChunkTextInput>(data);
+    let _ = decode::<TruncateInput>(data);
+    let _ = decode::<EncodeBatchInput>(data);
+    let _ = decode::<u32>(data);
+    if let Some(input) = decode::<ChatTemplateInput>(data) {
+        render(input);
+    }
+});
@@ -0,0 +1,36 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use nai_tokenizers::glm45_tokenizer::{
+    SpecialTokens, detokenize, tokenize, tokenize_with_offsets, vocab_size,
+};
+
+#[derive(Arbitrary, Debug)]
+struct Input<'a> {
+    text: &'a str,
+    /// Arbitrary ids, mostly outside the vocabulary.
+    tokens: Vec<u32>,
+}
+
+fuzz_target!(|input: Input| {
+    // Byte-level BPE is lossless, special tokens included
+    let tokens = tokenize(input.text, SpecialTokens::Keep).unwrap();
+    assert!(tokens.iter().all(|&id| (id as usize) < vocab_size()));
+    assert_eq!(
+        detokenize(&tokens, SpecialTokens::Keep).unwrap(),
+        input.text
+    );
+
+    let (tokens, offsets) = tokenize_with_offsets(input.text, SpecialTokens::Ignore).unwrap();
+    assert_eq!(tokens.len(), offsets.len());
+    assert!(
+        offsets
+            .iter()
+            .all(|&(start, end)| start <= end && end <= input.text.len())
+    );
+
+    for mode in [SpecialTokens::Keep, SpecialTokens::Ignore] {
+        let _ = detokenize(&input.tokens, mode);
+    }
+});
@@ -0,0 +1,437 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use nai_tokenizers::chunking::{self, ChunkOptions, Chunker};
+use nai_tokenizers::compare::{self, CompareOptions};
+use nai_tokenizers::dataset::{self, PreprocessConfig, ShardFormat};
+use nai_tokenizers::glm45_tokenizer::GLM45_TOKENIZER;
+use nai_tokenizers::merge_trace::{self, MergeTrace, TraceOptions};
+use nai_tokenizers::pretokenization::{self, Explanation};
+use nai_tokenizers::stats::{self, InputFormat};
+
+#[derive(Parser)]
+#[command(
+    name = "nai-tokenizers",
+    about = "Native tooling for the GLM-4.5 tokenizer"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render and tokenize OpenAI-format JSONL chats into packed training shards
+    Preprocess(PreprocessArgs),
+    /// Report token statistics over text or JSONL files as JSON
+    Stats(StatsArgs),
+    /// Diff the bundled GLM-4.5 tokenizer against another tokenizer.json as JSON
+    Compare(CompareArgs),
+    /// Split documents into token-bounded chunks, written as JSONL
+    Chunk(ChunkArgs),
+    /// Show how text is split by the pre-tokenizer and what each split becomes
+    Explain(ExplainArgs),
+    /// Replay the BPE merges behind each split's tokens
+    Trace(TraceArgs),
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Npy,
+    Raw,
+}
+
+#[derive(clap::Args)]
+struct PreprocessArgs {
+    /// JSONL files with one `{"messages": [...]}` object per line
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Output directory; rerunning with the same directory resumes
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Tokens per packed block
+    #[arg(long, default_value_t = 4096)]
+    seq_len: usize,
+    /// Input documents per shard, also the unit of resumption
+    #[arg(long, default_value_t = 10_000)]
+    docs_per_shard: usize,
+    #[arg(long, value_enum, default_value_t = Format::Npy)]
+    format: Format,
+    /// Render chats with reasoning disabled
+    #[arg(long)]
+    no_reasoning: bool,
+    /// Strip reasoning from every assistant turn instead of keeping it
+    #[arg(long)]
+    strip_reasoning: bool,
+    /// Drop each shard's final partial block instead of padding it
+    #[arg(long)]
+    drop_last: bool,
+    /// Worker threads (defaults to the number of CPUs)
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StatsFormat {
+    /// Each file is one document
+    Text,
+    /// Each line is one document
+    Lines,
+    /// Each line is a JSON object holding the document in `--field`
+    Jsonl,
+}
+
+#[derive(clap::Args)]
+struct StatsArgs {
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    #[arg(long, value_enum, default_value_t = StatsFormat::Jsonl)]
+    format: StatsFormat,
+    /// JSON field holding the document text
+    #[arg(long, default_value = "text")]
+    field: String,
+    /// Number of most frequent tokens to report
+    #[arg(long, default_value_t = 50)]
+    top_k: usize,
+}
+
+#[derive(clap::Args)]
+struct CompareArgs {
+    /// tokenizer.json to compare the bundled tokenizer against
+    tokenizer: PathBuf,
+    /// Text or JSONL files to measure token count changes on
+    #[arg(long)]
+    corpus: Vec<PathBuf>,
+    #[arg(long, value_enum, default_value_t = StatsFormat::Jsonl)]
+    format: StatsFormat,
+    /// JSON field holding the document text
+    #[arg(long, default_value = "text")]
+    field: String,
+    /// Maximum entries listed per category
+    #[arg(long, default_value_t = 100)]
+    max_examples: usize,
+}
+
+#[derive(clap::Args)]
+struct ChunkArgs {
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Maximum tokens per chunk
+    #[arg(long, default_value_t = 512)]
+    max_tokens: usize,
+    /// Tokens repeated from the end of each chunk at the start of the next
+    #[arg(long, default_value_t = 0)]
+    overlap: usize,
+    #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+    format: StatsFormat,
+    /// JSON field holding the document text
+    #[arg(long, default_value = "text")]
+    field: String,
+}
+
+#[derive(clap::Args)]
+struct ExplainArgs {
+    /// Text to explain; read from stdin if omitted
+    text: Option<String>,
+    /// Print the full breakdown as JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args)]
+struct TraceArgs {
+    /// Text to trace; read from stdin if omitted
+    text: Option<String>,
+    /// Alternative tokenizations listed per split
+    #[arg(long, default_value_t = 5)]
+    alternatives: usize,
+    /// How many more tokens than BPE's result an alternative may use
+    #[arg(long, default_value_t = 1)]
+    max_extra_tokens: usize,
+    /// Print the full trace as JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+fn input_format(format: StatsFormat, field: String) -> InputFormat {
+    match format {
+        StatsFormat::Text => InputFormat::Text,
+        StatsFormat::Lines => InputFormat::Lines,
+        StatsFormat::Jsonl => InputFormat::Jsonl { field },
+    }
+}
+
+fn stats(args: StatsArgs) -> Result<()> {
+    let format = input_format(args.format, args.field);
+    let report = stats::corpus_stats(&args.inputs, &format, args.top_k)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+fn compare(args: CompareArgs) -> Result<()> {
+    let other = compare::load_tokenizer(&args.tokenizer)?;
+    let options = CompareOptions {
+        max_examples: args.max_examples,
+    };
+    let mut report = compare::compare(&GLM45_TOKENIZER, &other, &options)?;
+    if !args.corpus.is_empty() {
+        let format = input_format(args.format, args.field);
+        report.corpus = Some(compare::compare_corpus(
+            &GLM45_TOKENIZER,
+            &other,
+            &args.corpus,
+            &format,
+            args.max_examples,
+        )?);
+    }
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+struct ChunkRecord<'a> {
+    source: &'a Path,
+    /// Document index within the source: always 0 for `--format text`, the line's
+    /// document index otherwise.
+    document: u64,
+    #[serde(flatten)]
+    chunk: chunking::Chunk,
+}
+
+/// Streams a text file through the chunker without reading it into memory whole.
+fn chunk_file(
+    path: &Path,
+    options: ChunkOptions,
+    mut emit: impl FnMut(chunking::Chunk) -> Result<()>,
+) -> Result<()> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut chunker = Chunker::new(options)?;
+    let mut pending = Vec::new();
+    let mut buf = vec![0; 1 << 16];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        pending.extend_from_slice(&buf[..read]);
+        // Hold back a character split across reads.
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => anyhow::bail!("{} is not valid UTF-8: {}", path.display(), e),
+        };
+        let text = std::str::from_utf8(&pending[..valid]).unwrap();
+        for chunk in chunker.push(text)? {
+            emit(chunk)?;
+        }
+        pending.drain(..valid);
+    }
+    anyhow::ensure!(
+        pending.is_empty(),
+        "{} ends inside a UTF-8 character",
+        path.display()
+    );
+    for chunk in chunker.finish()? {
+        emit(chunk)?;
+    }
+    Ok(())
+}
+
+fn chunk(args: ChunkArgs) -> Result<()> {
+    let options = ChunkOptions {
+        max_tokens: args.max_tokens,
+        overlap_tokens: args.overlap,
+        ..ChunkOptions::default()
+    };
+    let format = input_format(args.format, args.field);
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    let mut write = |source: &Path, document: u64, chunk: chunking::Chunk| -> Result<()> {
+        serde_json::to_writer(
+            &mut out,
+            &ChunkRecord {
+                source,
+                document,
+                chunk,
+            },
+        )?;
+        out.write_all(b"\n")?;
+        Ok(())
+    };
+    for path in &args.inputs {
+        if matches!(format, InputFormat::Text) {
+            chunk_file(path, options, |chunk| write(path, 0, chunk))?;
+            continue;
+        }
+        let reader = BufReader::new(
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+        );
+        let mut document = 0;
+        for line in reader.lines() {
+            let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some(text) = stats::extract_document(&line, &format) else {
+                eprintln!("{}: skipping a line without a document", path.display());
+                continue;
+            };
+            for chunk in chunking::chunk_text(&text, options)? {
+                write(path, document, chunk)?;
+            }
+            document += 1;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn preprocess(args: PreprocessArgs) -> Result<()> {
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+    let config = PreprocessConfig {
+        seq_len: args.seq_len,
+        docs_per_shard: args.docs_per_shard,
+        format: match args.format {
+            Format::Npy => ShardFormat::Npy,
+            Format::Raw => ShardFormat::Raw,
+        },
+        reasoning_enabled: !args.no_reasoning,
+        keep_all_reasoning: !args.strip_reasoning,
+        drop_last: args.drop_last,
+    };
+    let stats = dataset::preprocess(&args.inputs, &args.output, &config, |stats| {
+        eprintln!(
+            "shard {}: {} documents, {} tokens, {} skipped",
+            stats.shards, stats.documents, stats.tokens, stats.skipped
+        );
+    })?;
+    println!("{}", serde_json::to_string_pretty(&stats)?);
+    Ok(())
+}
+
+fn text_or_stdin(text: Option<String>) -> Result<String> {
+    match text {
+        Some(text) => Ok(text),
+        None => {
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .context("Failed to read stdin")?;
+            Ok(text)
+        }
+    }
+}
+
+fn explain(args: ExplainArgs) -> Result<()> {
+    let text = text_or_stdin(args.text)?;
+    let explanation = pretokenization::explain_splits(&text)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&explanation)?);
+    } else {
+        print_explanation(&text, &explanation);
+    }
+    Ok(())
+}
+
+/// One line per split: its byte range, the input text, the pre-token BPE sees and
+/// the tokens it became. Strings are quoted so whitespace stays visible.
+fn print_explanation(text: &str, explanation: &Explanation) {
+    println!("{:<12} {:<24} {:<24} tokens", "bytes", "split", "pre-token");
+    for split in &explanation.splits {
+        let tokens: Vec<String> = split
+            .tokens
+            .iter()
+            .map(|token| format!("{:?}={}", token.text, token.id))
+            .collect();
+        let split_text = format!("{:?}", split.text);
+        println!(
+            "{:<12} {:<24} {:<24} {}",
+            format!("{}..{}", split.start, split.end),
+            if split.added {
+                format!("{} (added)", split_text)
+            } else {
+                split_text
+            },
+            format!("{:?}", split.pretoken),
+            tokens.join(" ")
+        );
+    }
+    println!(
+        "{} splits, {} tokens",
+        explanation.splits.len(),
+        explanation.token_count
+    );
+    if explanation.normalized != text {
+        println!("normalized: {:?}", explanation.normalized);
+    }
+}
+
+fn trace(args: TraceArgs) -> Result<()> {
+    let text = text_or_stdin(args.text)?;
+    let options = TraceOptions {
+        max_alternatives: args.alternatives,
+        max_extra_tokens: args.max_extra_tokens,
+    };
+    let trace = merge_trace::trace(&text, &options)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&trace)?);
+    } else {
+        print_trace(&trace);
+    }
+    Ok(())
+}
+
+/// Per split: the starting symbols, one line per merge with its rank and the symbols
+/// after it, the final tokens, then any alternatives.
+fn print_trace(trace: &MergeTrace) {
+    for split in &trace.splits {
+        println!("{}..{} {:?}", split.start, split.end, split.text);
+        if split.added {
+            println!("  added token, no merges");
+        } else {
+            println!("  start: {}", split.initial.join(" "));
+            for step in &split.steps {
+                println!(
+                    "  {:>8}  {} + {}  ->  {}",
+                    format!("#{}", step.rank),
+                    step.left,
+                    step.right,
+                    step.symbols.join(" ")
+                );
+            }
+        }
+        let tokens: Vec<String> = split
+            .tokens
+            .iter()
+            .map(|token| format!("{}={}", token.token, token.id))
+            .collect();
+        println!("  tokens: {}", tokens.join(" "));
+        for alternative in &split.alternatives {
+            let tokens: Vec<String> = alternative
+                .iter()
+                .map(|token| format!("{}={}", token.token, token.id))
+                .collect();
+            println!("  alt ({}): {}", alternative.len(), tokens.join(" "));
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Preprocess(args) => preprocess(args),
+        Command::Stats(args) => stats(args),
+        Command::Compare(args) => compare(args),
+        Command::Chunk(args) => chunk(args),
+        Command::Explain(args) => explain(args),
+        Command::Trace(args) => trace(args),
+    }
+}
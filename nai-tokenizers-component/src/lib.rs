@@ -0,0 +1,178 @@
+// The guest side only exists when building the component (`--target wasm32-wasip2`);
+// on the host this crate is just the WIT world plus the host tests.
+#![cfg(target_arch = "wasm32")]
+
+use std::cell::RefCell;
+
+use nai_tokenizers::detokenization;
+use nai_tokenizers::glm45_template::{
+    Chat, ContextState, Message, PrefillType, ReasoningEnabled, Version,
+};
+use nai_tokenizers::glm45_tokenizer::{self, SpecialTokens};
+use nai_tokenizers::stop_sequences::StopMatcher;
+
+wit_bindgen::generate!({
+    world: "nai-tokenizers",
+    path: "wit",
+});
+
+use exports::novelai::tokenizers::chat::{self, ChatOptions, Prefill, TemplateVersion};
+use exports::novelai::tokenizers::tokenizer::{
+    self, DetokenizeOptions, Detokenized, GuestStreamingDecoder, InvalidBytes,
+};
+
+struct Component;
+
+fn special_tokens_mode(include: bool) -> SpecialTokens {
+    if include {
+        SpecialTokens::Keep
+    } else {
+        SpecialTokens::Ignore
+    }
+}
+
+impl tokenizer::Guest for Component {
+    type StreamingDecoder = StreamingDecoder;
+
+    fn tokenize(text: String, special_tokens: bool) -> Result<Vec<u32>, String> {
+        glm45_tokenizer::tokenize(&text, special_tokens_mode(special_tokens))
+            .map_err(|e| e.to_string())
+    }
+
+    fn detokenize(tokens: Vec<u32>, special_tokens: bool) -> Result<String, String> {
+        glm45_tokenizer::detokenize(&tokens, special_tokens_mode(special_tokens))
+            .map_err(|e| e.to_string())
+    }
+
+    fn detokenize_with_options(
+        tokens: Vec<u32>,
+        special_tokens: bool,
+        options: DetokenizeOptions,
+    ) -> Result<Detokenized, String> {
+        let options = detokenization::DetokenizeOptions {
+            strict: options.strict,
+            invalid_bytes: options.invalid_bytes.into(),
+            clean_up_tokenization_spaces: options.clean_up_tokenization_spaces,
+        };
+        let detokenized = detokenization::detokenize_with_options(
+            &tokens,
+            &special_tokens_mode(special_tokens).into(),
+            &options,
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(Detokenized {
+            text: detokenized.text,
+            strict: detokenized.strict,
+            invalid_bytes: detokenized.invalid_bytes.into(),
+            clean_up_tokenization_spaces: detokenized.clean_up_tokenization_spaces,
+            dropped_ids: detokenized.dropped_ids,
+            invalid_sequences: detokenized.invalid_sequences as u32,
+        })
+    }
+
+    fn count(text: String, special_tokens: bool) -> Result<u32, String> {
+        Self::tokenize(text, special_tokens).map(|tokens| tokens.len() as u32)
+    }
+}
+
+/// A stop matcher without stop sequences does exactly the UTF-8 hold-back we need.
+struct StreamingDecoder {
+    special_tokens: bool,
+    matcher: RefCell<StopMatcher>,
+}
+
+impl GuestStreamingDecoder for StreamingDecoder {
+    fn new(special_tokens: bool) -> Self {
+        Self {
+            special_tokens,
+            matcher: RefCell::new(StopMatcher::new(Vec::<String>::new())),
+        }
+    }
+
+    fn push(&self, token: u32) -> Result<String, String> {
+        if !self.special_tokens && glm45_tokenizer::is_special_token(token) {
+            return Ok(String::new());
+        }
+        self.matcher
+            .borrow_mut()
+            .push(token)
+            .map(|update| update.text)
+            .map_err(|e| e.to_string())
+    }
+
+    fn finish(&self) -> String {
+        self.matcher.borrow_mut().finish()
+    }
+}
+
+impl From<InvalidBytes> for detokenization::InvalidBytes {
+    fn from(invalid_bytes: InvalidBytes) -> Self {
+        match invalid_bytes {
+            InvalidBytes::Replace => detokenization::InvalidBytes::Replace,
+            InvalidBytes::Error => detokenization::InvalidBytes::Error,
+            InvalidBytes::Drop => detokenization::InvalidBytes::Drop,
+        }
+    }
+}
+
+impl From<detokenization::InvalidBytes> for InvalidBytes {
+    fn from(invalid_bytes: detokenization::InvalidBytes) -> Self {
+        match invalid_bytes {
+            detokenization::InvalidBytes::Replace => InvalidBytes::Replace,
+            detokenization::InvalidBytes::Error => InvalidBytes::Error,
+            detokenization::InvalidBytes::Drop => InvalidBytes::Drop,
+        }
+    }
+}
+
+impl From<chat::Message> for Message {
+    fn from(message: chat::Message) -> Self {
+        Message::from_role(&message.role, message.content, message.reasoning_content)
+    }
+}
+
+impl From<Prefill> for PrefillType {
+    fn from(prefill: Prefill) -> Self {
+        match prefill {
+            Prefill::None => PrefillType::None,
+            Prefill::Canonical => PrefillType::Canonical,
+            Prefill::PartialReasoning(reasoning_content) => {
+                PrefillType::PartialReasoning { reasoning_content }
+            }
+            Prefill::FullReasoning(chat::FullReasoning {
+                reasoning_content,
+                content,
+            }) => PrefillType::FullReasoning {
+                reasoning_content,
+                content,
+            },
+        }
+    }
+}
+
+impl chat::Guest for Component {
+    fn chat_template(messages: Vec<chat::Message>, options: ChatOptions) -> String {
+        let reasoning = if options.reasoning_enabled {
+            ReasoningEnabled::Yes
+        } else {
+            ReasoningEnabled::No
+        };
+        let version = match options.version {
+            TemplateVersion::Glm45 | TemplateVersion::Glm46 => Version::GLM456,
+            TemplateVersion::Glm47 => Version::GLM47,
+        };
+        let chat = Chat {
+            messages: messages.into_iter().map(|m| m.into()).collect(),
+        };
+        // Component messages are text only, so there are no placeholders to go over.
+        ContextState::new_with_version(reasoning, version)
+            .chat_with_options(
+                &chat,
+                options.prefill.into(),
+                options.ignore_message_position,
+            )
+            .expect("Text-only chats have no image placeholders")
+    }
+}
+
+export!(Component);
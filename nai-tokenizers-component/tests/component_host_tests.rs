@@ -0,0 +1,239 @@
+//! Instantiates the built component with wasmtime and checks it against the library.
+//!
+//! Build the component first:
+//!
+//! ```sh
+//! cargo build -p nai-tokenizers-component --target wasm32-wasip2
+//! cargo test -p nai-tokenizers-component -- --ignored
+//! ```
+//!
+//! The tests load the newer of the debug and release builds under `$CARGO_TARGET_DIR`
+//! (default `target/`), or the file named by `$NAI_TOKENIZERS_COMPONENT_WASM`. Relative
+//! paths in either are taken from the workspace root.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use nai_tokenizers::glm45_template::{
+    Chat, ContextState, Message, PrefillType, ReasoningEnabled, Version,
+};
+use nai_tokenizers::glm45_tokenizer::{SpecialTokens, detokenize, tokenize};
+use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::{Config, Engine, Store};
+use wasmtime_wasi::{IoView, WasiCtx, WasiCtxBuilder, WasiView};
+
+wasmtime::component::bindgen!({
+    world: "nai-tokenizers",
+    path: "wit",
+});
+
+use exports::novelai::tokenizers::chat;
+use exports::novelai::tokenizers::tokenizer::{DetokenizeOptions, InvalidBytes};
+
+struct Host {
+    ctx: WasiCtx,
+    table: ResourceTable,
+}
+
+impl IoView for Host {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+impl WasiView for Host {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.ctx
+    }
+}
+
+fn component_path() -> anyhow::Result<PathBuf> {
+    // Tests run in the package directory, so relative paths are taken from the
+    // workspace root, where cargo was most likely invoked.
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    if let Some(path) = std::env::var_os("NAI_TOKENIZERS_COMPONENT_WASM") {
+        let path = workspace.join(path);
+        anyhow::ensure!(path.exists(), "{} not found", path.display());
+        return Ok(path);
+    }
+    let target_dir = std::env::var_os("CARGO_TARGET_DIR").unwrap_or_else(|| "target".into());
+    let target_dir = workspace.join(target_dir);
+    ["debug", "release"]
+        .into_iter()
+        .map(|profile| {
+            target_dir
+                .join("wasm32-wasip2")
+                .join(profile)
+                .join("nai_tokenizers_component.wasm")
+        })
+        .filter_map(|path| Some((path.metadata().ok()?.modified().ok()?, path)))
+        .max()
+        .map(|(_, path)| path)
+        .with_context(|| {
+            format!(
+                "No component build in {} - run `cargo build -p nai-tokenizers-component --target wasm32-wasip2` first",
+                target_dir.display()
+            )
+        })
+}
+
+fn instantiate() -> anyhow::Result<(Store<Host>, NaiTokenizers)> {
+    let path = component_path()?;
+
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    let engine = Engine::new(&config)?;
+    let component = Component::from_file(&engine, &path)?;
+    let mut linker = Linker::new(&engine);
+    wasmtime_wasi::add_to_linker_sync(&mut linker)?;
+    let mut store = Store::new(
+        &engine,
+        Host {
+            ctx: WasiCtxBuilder::new().build(),
+            table: ResourceTable::new(),
+        },
+    );
+    let bindings = NaiTokenizers::instantiate(&mut store, &component, &linker)?;
+    Ok((store, bindings))
+}
+
+const TEXT: &str = "[gMASK]this is a test where da goose is cooked<|system|>no<|user|>yes\u{1f600}";
+
+#[test]
+#[ignore = "needs the wasm32-wasip2 component build"]
+fn test_tokenizer_matches_library() -> anyhow::Result<()> {
+    let (mut store, bindings) = instantiate()?;
+    let tokenizer = bindings.novelai_tokenizers_tokenizer();
+
+    for special in [true, false] {
+        let mode = if special {
+            SpecialTokens::Keep
+        } else {
+            SpecialTokens::Ignore
+        };
+        let expected = tokenize(TEXT, mode)?;
+        let tokens = tokenizer
+            .call_tokenize(&mut store, TEXT, special)?
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(tokens, expected);
+        let count = tokenizer
+            .call_count(&mut store, TEXT, special)?
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(count as usize, expected.len());
+        let text = tokenizer
+            .call_detokenize(&mut store, &tokens, special)?
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(text, detokenize(&expected, mode)?);
+    }
+    Ok(())
+}
+
+#[test]
+#[ignore = "needs the wasm32-wasip2 component build"]
+fn test_detokenize_out_of_range() -> anyhow::Result<()> {
+    let (mut store, bindings) = instantiate()?;
+    let tokenizer = bindings.novelai_tokenizers_tokenizer();
+    let mut tokens = tokenize("hi", SpecialTokens::Ignore)?;
+    tokens.push(u32::MAX);
+
+    let text = tokenizer
+        .call_detokenize(&mut store, &tokens, false)?
+        .map_err(anyhow::Error::msg)?;
+    assert_eq!(text, "hi");
+
+    let mut options = DetokenizeOptions {
+        strict: false,
+        invalid_bytes: InvalidBytes::Replace,
+        clean_up_tokenization_spaces: None,
+    };
+    let detokenized = tokenizer
+        .call_detokenize_with_options(&mut store, &tokens, false, options)?
+        .map_err(anyhow::Error::msg)?;
+    assert_eq!(detokenized.text, "hi");
+    assert_eq!(detokenized.dropped_ids, vec![u32::MAX]);
+
+    options.strict = true;
+    let error = tokenizer
+        .call_detokenize_with_options(&mut store, &tokens, false, options)?
+        .unwrap_err();
+    assert!(error.contains("out of range"), "{}", error);
+    Ok(())
+}
+
+#[test]
+#[ignore = "needs the wasm32-wasip2 component build"]
+fn test_streaming_decoder() -> anyhow::Result<()> {
+    let (mut store, bindings) = instantiate()?;
+    let decoder = bindings.novelai_tokenizers_tokenizer().streaming_decoder();
+    let tokens = tokenize(TEXT, SpecialTokens::Keep)?;
+
+    for special in [true, false] {
+        let mode = if special {
+            SpecialTokens::Keep
+        } else {
+            SpecialTokens::Ignore
+        };
+        let handle = decoder.call_constructor(&mut store, special)?;
+        let mut text = String::new();
+        for &token in &tokens {
+            let chunk = decoder
+                .call_push(&mut store, handle, token)?
+                .map_err(anyhow::Error::msg)?;
+            assert!(!chunk.contains('\u{fffd}'));
+            text.push_str(&chunk);
+        }
+        text.push_str(&decoder.call_finish(&mut store, handle)?);
+        handle.resource_drop(&mut store)?;
+        assert_eq!(text, detokenize(&tokens, mode)?);
+    }
+    Ok(())
+}
+
+#[test]
+#[ignore = "needs the wasm32-wasip2 component build"]
+fn test_chat_template_matches_library() -> anyhow::Result<()> {
+    let (mut store, bindings) = instantiate()?;
+    let messages = vec![
+        chat::Message {
+            role: "system".to_string(),
+            content: "Be brief.".to_string(),
+            reasoning_content: None,
+        },
+        chat::Message {
+            role: "user".to_string(),
+            content: "Is 17 prime?".to_string(),
+            reasoning_content: None,
+        },
+    ];
+    let options = chat::ChatOptions {
+        reasoning_enabled: true,
+        prefill: chat::Prefill::PartialReasoning("Check divisors up to 4.".to_string()),
+        ignore_message_position: false,
+        version: chat::TemplateVersion::Glm47,
+    };
+    let rendered = bindings
+        .novelai_tokenizers_chat()
+        .call_chat_template(&mut store, &messages, &options)?;
+
+    let chat = Chat {
+        messages: vec![
+            Message::System {
+                content: "Be brief.".to_string(),
+            },
+            Message::User {
+                content: "Is 17 prime?".to_string(),
+            },
+        ],
+    };
+    let expected = ContextState::new_with_version(ReasoningEnabled::Yes, Version::GLM47)
+        .chat(
+            &chat,
+            PrefillType::PartialReasoning {
+                reasoning_content: "Check divisors up to 4.".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(rendered, expected);
+    Ok(())
+}
@@ -0,0 +1,86 @@
+package tokenizer
+
+import (
+	"encoding/binary"
+	"fmt"
+)
+
+// BatchEncodeInput represents the input for batch encoding. Zero values leave the
+// plugin defaults: pad to the longest row, padding side from the tokenizer config.
+type BatchEncodeInput struct {
+	Texts                   []string `json:"texts" msgpack:"texts"`
+	MaxLength               int      `json:"max_length,omitempty" msgpack:"max_length,omitempty"`
+	ReturnOverflowingTokens bool     `json:"return_overflowing_tokens,omitempty" msgpack:"return_overflowing_tokens,omitempty"`
+	Stride                  int      `json:"stride,omitempty" msgpack:"stride,omitempty"`
+	PaddingSide             string   `json:"padding_side,omitempty" msgpack:"padding_side,omitempty"`
+	PadToMultipleOf         int      `json:"pad_to_multiple_of,omitempty" msgpack:"pad_to_multiple_of,omitempty"`
+	IncludeSpecialTokens    *bool    `json:"include_special_tokens,omitempty" msgpack:"include_special_tokens,omitempty"`
+}
+
+// Batch is a padded batch. InputIDs, AttentionMask and PositionIDs hold [Rows, Width]
+// row-major arrays of little-endian uint32, uint8 and uint32, as returned by the plugin.
+type Batch struct {
+	Rows          int      `msgpack:"rows"`
+	Width         int      `msgpack:"width"`
+	PadTokenID    uint32   `msgpack:"pad_token_id"`
+	InputIDs      []byte   `msgpack:"input_ids"`
+	AttentionMask []byte   `msgpack:"attention_mask"`
+	PositionIDs   []byte   `msgpack:"position_ids"`
+	SampleMapping []uint32 `msgpack:"sample_mapping"`
+	Lengths       []uint32 `msgpack:"lengths"`
+}
+
+// IDs decodes InputIDs into a flat [Rows*Width] slice
+func (b *Batch) IDs() []uint32 {
+	return decodeUint32s(b.InputIDs)
+}
+
+// Positions decodes PositionIDs into a flat [Rows*Width] slice
+func (b *Batch) Positions() []uint32 {
+	return decodeUint32s(b.PositionIDs)
+}
+
+// Row returns the ids of one row, padding included
+func (b *Batch) Row(row int) []uint32 {
+	return decodeUint32s(b.InputIDs[row*b.Width*4 : (row+1)*b.Width*4])
+}
+
+func decodeUint32s(data []byte) []uint32 {
+	values := make([]uint32, len(data)/4)
+	for i := range values {
+		values[i] = binary.LittleEndian.Uint32(data[i*4:])
+	}
+	return values
+}
+
+func (b *Batch) validate() error {
+	cells := b.Rows * b.Width
+	if len(b.InputIDs) != cells*4 || len(b.AttentionMask) != cells || len(b.PositionIDs) != cells*4 {
+		return fmt.Errorf("batch arrays don't match %dx%d", b.Rows, b.Width)
+	}
+	return nil
+}
+
+// BatchEncode tokenizes several texts into one padded batch
+func (t *Tokenizer) BatchEncode(input BatchEncodeInput) (*Batch, error) {
+	var batch Batch
+	if err := t.CallV2("v2_batch_encode", input, &batch); err != nil {
+		return nil, err
+	}
+	if err := batch.validate(); err != nil {
+		return nil, err
+	}
+	return &batch, nil
+}
+
+// BatchEncode tokenizes several texts into one padded batch. Thread-safe.
+func (pt *PooledTokenizer) BatchEncode(input BatchEncodeInput) (*Batch, error) {
+	var batch Batch
+	if err := pt.CallV2("v2_batch_encode", input, &batch); err != nil {
+		return nil, err
+	}
+	if err := batch.validate(); err != nil {
+		return nil, err
+	}
+	return &batch, nil
+}
@@ -0,0 +1,58 @@
+package tokenizer
+
+// tokenizeBytesInput is sent as msgpack, so Bytes goes over the wire as bin
+type tokenizeBytesInput struct {
+	Bytes                []byte `msgpack:"bytes"`
+	IncludeSpecialTokens bool   `msgpack:"include_special_tokens"`
+}
+
+type detokenizeBytesInput struct {
+	Tokens               []uint32 `msgpack:"tokens"`
+	IncludeSpecialTokens bool     `msgpack:"include_special_tokens"`
+}
+
+type tokensResult struct {
+	Tokens []uint32 `msgpack:"tokens"`
+}
+
+type bytesResult struct {
+	Bytes []byte `msgpack:"bytes"`
+}
+
+// TokenizeBytes tokenizes raw bytes that need not be valid UTF-8
+func (t *Tokenizer) TokenizeBytes(data []byte, includeSpecialTokens bool) ([]uint32, error) {
+	var result tokensResult
+	if err := t.CallV2("v2_tokenize_bytes", tokenizeBytesInput{data, includeSpecialTokens}, &result); err != nil {
+		return nil, err
+	}
+	return result.Tokens, nil
+}
+
+// DetokenizeBytes decodes tokens to the exact bytes they stand for, without replacing
+// invalid UTF-8
+func (t *Tokenizer) DetokenizeBytes(tokens []uint32, includeSpecialTokens bool) ([]byte, error) {
+	var result bytesResult
+	if err := t.CallV2("v2_detokenize_bytes", detokenizeBytesInput{tokens, includeSpecialTokens}, &result); err != nil {
+		return nil, err
+	}
+	return result.Bytes, nil
+}
+
+// TokenizeBytes tokenizes raw bytes that need not be valid UTF-8. Thread-safe.
+func (pt *PooledTokenizer) TokenizeBytes(data []byte, includeSpecialTokens bool) ([]uint32, error) {
+	var result tokensResult
+	if err := pt.CallV2("v2_tokenize_bytes", tokenizeBytesInput{data, includeSpecialTokens}, &result); err != nil {
+		return nil, err
+	}
+	return result.Tokens, nil
+}
+
+// DetokenizeBytes decodes tokens to the exact bytes they stand for, without replacing
+// invalid UTF-8. Thread-safe.
+func (pt *PooledTokenizer) DetokenizeBytes(tokens []uint32, includeSpecialTokens bool) ([]byte, error) {
+	var result bytesResult
+	if err := pt.CallV2("v2_detokenize_bytes", detokenizeBytesInput{tokens, includeSpecialTokens}, &result); err != nil {
+		return nil, err
+	}
+	return result.Bytes, nil
+}
@@ -0,0 +1,39 @@
+package tokenizer
+
+// Values for DetokenizeInput.InvalidBytes
+const (
+	InvalidBytesReplace = "replace"
+	InvalidBytesError   = "error"
+	InvalidBytesDrop    = "drop"
+)
+
+// DetokenizeResult is the decoded text along with the options as the plugin applied them
+type DetokenizeResult struct {
+	Text                      string   `msgpack:"text"`
+	Strict                    bool     `msgpack:"strict"`
+	InvalidBytes              string   `msgpack:"invalid_bytes"`
+	CleanUpTokenizationSpaces bool     `msgpack:"clean_up_tokenization_spaces"`
+	DroppedIDs                []uint32 `msgpack:"dropped_ids"`
+	InvalidSequences          int      `msgpack:"invalid_sequences"`
+}
+
+// DetokenizeWithOptions decodes tokens through v2_detokenize. Strict defaults to true
+// (ids outside the vocabulary are an ErrCodeTokenOutOfRange error; with false they
+// are dropped and listed in DroppedIDs), InvalidBytes to InvalidBytesReplace, and
+// CleanUpTokenizationSpaces to the tokenizer config's.
+func (t *Tokenizer) DetokenizeWithOptions(input DetokenizeInput) (*DetokenizeResult, error) {
+	var result DetokenizeResult
+	if err := t.CallV2("v2_detokenize", input, &result); err != nil {
+		return nil, err
+	}
+	return &result, nil
+}
+
+// DetokenizeWithOptions decodes tokens through v2_detokenize. Thread-safe.
+func (pt *PooledTokenizer) DetokenizeWithOptions(input DetokenizeInput) (*DetokenizeResult, error) {
+	var result DetokenizeResult
+	if err := pt.CallV2("v2_detokenize", input, &result); err != nil {
+		return nil, err
+	}
+	return &result, nil
+}
@@ -20,6 +20,12 @@ type PooledTokenizer struct {
 // NewPooled creates a new thread-safe PooledTokenizer from a WASM file path.
 // This tokenizer can be safely used across multiple goroutines.
 func NewPooled(wasmPath string) (*PooledTokenizer, error) {
+	return NewPooledWithConfig(wasmPath, nil)
+}
+
+// NewPooledWithConfig creates a new thread-safe PooledTokenizer with plugin-wide defaults
+// passed through the manifest config. Every instance in the pool shares the same config.
+func NewPooledWithConfig(wasmPath string, pluginConfig map[string]string) (*PooledTokenizer, error) {
 	ctx := context.Background()
 	manifest := extism.Manifest{
 		Wasm: []extism.Wasm{
@@ -27,6 +33,7 @@ func NewPooled(wasmPath string) (*PooledTokenizer, error) {
 				Path: wasmPath,
 			},
 		},
+		Config: pluginConfig,
 	}
 
 	config := extism.PluginConfig{
@@ -83,7 +90,7 @@ func (pt *PooledTokenizer) Tokenize(text string, includeSpecialTokens bool) ([]u
 
 	input := TokenizeInput{
 		Text:                  text,
-		IncludeSpecialTokens: includeSpecialTokens,
+		IncludeSpecialTokens: &includeSpecialTokens,
 	}
 
 	inputData, err := msgpack.Marshal(input)
@@ -114,7 +121,7 @@ func (pt *PooledTokenizer) Detokenize(tokens []uint32, includeSpecialTokens bool
 
 	input := DetokenizeInput{
 		Tokens:                tokens,
-		IncludeSpecialTokens: includeSpecialTokens,
+		IncludeSpecialTokens: &includeSpecialTokens,
 	}
 
 	inputData, err := msgpack.Marshal(input)
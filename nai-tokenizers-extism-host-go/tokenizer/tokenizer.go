@@ -14,18 +14,39 @@ type Tokenizer struct {
 	plugin *extism.Plugin
 }
 
-// TokenizeInput represents the input for tokenization
+// TokenizeInput represents the input for tokenization. AllowedSpecial and
+// DisallowedSpecial take AllSpecialTokens or a []string of special tokens; setting
+// either encodes the special tokens in neither as literal text.
 type TokenizeInput struct {
-	Text                  string `msgpack:"text"`
-	IncludeSpecialTokens bool   `msgpack:"include_special_tokens"`
+	Text                 string      `msgpack:"text"`
+	IncludeSpecialTokens *bool       `msgpack:"include_special_tokens,omitempty"`
+	AllowedSpecial       interface{} `msgpack:"allowed_special,omitempty"`
+	DisallowedSpecial    interface{} `msgpack:"disallowed_special,omitempty"`
 }
 
-// DetokenizeInput represents the input for detokenization
+// DetokenizeInput represents the input for detokenization. SkipSpecial takes
+// AllSpecialTokens or a []string of special tokens to leave out, and overrides
+// IncludeSpecialTokens. Strict, InvalidBytes and CleanUpTokenizationSpaces are
+// described on DetokenizeWithOptions.
 type DetokenizeInput struct {
-	Tokens                []uint32 `msgpack:"tokens"`
-	IncludeSpecialTokens bool     `msgpack:"include_special_tokens"`
+	Tokens                    []uint32    `msgpack:"tokens"`
+	IncludeSpecialTokens      *bool       `msgpack:"include_special_tokens,omitempty"`
+	SkipSpecial               interface{} `msgpack:"skip_special,omitempty"`
+	Strict                    *bool       `msgpack:"strict,omitempty"`
+	InvalidBytes              string      `msgpack:"invalid_bytes,omitempty"`
+	CleanUpTokenizationSpaces *bool       `msgpack:"clean_up_tokenization_spaces,omitempty"`
 }
 
+// Bool returns a pointer to v, for the optional *bool fields. Leaving one nil uses the
+// plugin config's default.
+func Bool(v bool) *bool {
+	return &v
+}
+
+// AllSpecialTokens selects every special token in AllowedSpecial, DisallowedSpecial
+// and SkipSpecial
+const AllSpecialTokens = "all"
+
 // Message represents an OpenAI-compatible chat message
 type Message struct {
 	Role             string  `json:"role" msgpack:"role"`
@@ -69,17 +90,53 @@ func PrefillFullReasoning(reasoningContent, content string) *PrefillType {
 	}
 }
 
+// ReasoningRetention controls which earlier assistant turns keep their reasoning
+type ReasoningRetention struct {
+	Type  string `json:"type" msgpack:"type"`
+	Turns *int   `json:"turns,omitempty" msgpack:"turns,omitempty"`
+}
+
+// RetainStripPast replaces all earlier reasoning with empty think blocks (default)
+func RetainStripPast() *ReasoningRetention {
+	return &ReasoningRetention{Type: "strip_past"}
+}
+
+// RetainSinceLastUser keeps the reasoning of assistant turns after the last user message
+func RetainSinceLastUser() *ReasoningRetention {
+	return &ReasoningRetention{Type: "since_last_user"}
+}
+
+// RetainLastTurns keeps the reasoning of assistant turns answering the last n user messages
+func RetainLastTurns(n int) *ReasoningRetention {
+	return &ReasoningRetention{Type: "last_turns", Turns: &n}
+}
+
+// RetainAll keeps the reasoning of every assistant turn
+func RetainAll() *ReasoningRetention {
+	return &ReasoningRetention{Type: "all"}
+}
+
 // ChatTemplateInput represents the input for chat templating
 type ChatTemplateInput struct {
-	Messages               []Message    `json:"messages" msgpack:"messages"`
-	ReasoningEnabled       bool         `json:"reasoning_enabled,omitempty" msgpack:"reasoning_enabled"`
-	Prefill                *PrefillType `json:"prefill,omitempty" msgpack:"prefill,omitempty"`
-	IgnoreMessagePosition  bool         `json:"ignore_message_position,omitempty" msgpack:"ignore_message_position"`
+	Messages              []Message           `json:"messages" msgpack:"messages"`
+	ReasoningEnabled      *bool               `json:"reasoning_enabled,omitempty" msgpack:"reasoning_enabled,omitempty"`
+	Prefill               *PrefillType        `json:"prefill,omitempty" msgpack:"prefill,omitempty"`
+	IgnoreMessagePosition *bool               `json:"ignore_message_position,omitempty" msgpack:"ignore_message_position,omitempty"`
+	Version               string              `json:"version,omitempty" msgpack:"version,omitempty"`
+	ReasoningRetention    *ReasoningRetention `json:"reasoning_retention,omitempty" msgpack:"reasoning_retention,omitempty"`
+	Template              string              `json:"template,omitempty" msgpack:"template,omitempty"`
 }
 
 // New creates a new Tokenizer from a WASM file path.
 // Note: This is NOT thread-safe. For concurrent use, use NewPooled instead.
 func New(wasmPath string) (*Tokenizer, error) {
+	return NewWithConfig(wasmPath, nil)
+}
+
+// NewWithConfig creates a new Tokenizer with plugin-wide defaults (e.g. "template_version",
+// "reasoning_enabled", "max_input_bytes") passed through the manifest config.
+// Note: This is NOT thread-safe. For concurrent use, use NewPooledWithConfig instead.
+func NewWithConfig(wasmPath string, pluginConfig map[string]string) (*Tokenizer, error) {
 	ctx := context.Background()
 	manifest := extism.Manifest{
 		Wasm: []extism.Wasm{
@@ -87,6 +144,7 @@ func New(wasmPath string) (*Tokenizer, error) {
 				Path: wasmPath,
 			},
 		},
+		Config: pluginConfig,
 	}
 
 	config := extism.PluginConfig{
@@ -105,7 +163,7 @@ func New(wasmPath string) (*Tokenizer, error) {
 func (t *Tokenizer) Tokenize(text string, includeSpecialTokens bool) ([]uint32, error) {
 	input := TokenizeInput{
 		Text:                  text,
-		IncludeSpecialTokens: includeSpecialTokens,
+		IncludeSpecialTokens: &includeSpecialTokens,
 	}
 
 	inputData, err := msgpack.Marshal(input)
@@ -130,7 +188,7 @@ func (t *Tokenizer) Tokenize(text string, includeSpecialTokens bool) ([]uint32,
 func (t *Tokenizer) Detokenize(tokens []uint32, includeSpecialTokens bool) (string, error) {
 	input := DetokenizeInput{
 		Tokens:                tokens,
-		IncludeSpecialTokens: includeSpecialTokens,
+		IncludeSpecialTokens: &includeSpecialTokens,
 	}
 
 	inputData, err := msgpack.Marshal(input)
@@ -0,0 +1,123 @@
+package tokenizer
+
+import (
+	"fmt"
+
+	extism "github.com/extism/go-sdk"
+	"github.com/vmihailenco/msgpack/v5"
+)
+
+// Stable error codes reported by the v2 exports
+const (
+	ErrCodeInvalidRequest     = "invalid_request"
+	ErrCodeUnsupportedVersion = "unsupported_version"
+	ErrCodeTokenOutOfRange    = "token_out_of_range"
+	ErrCodeUnknownToken       = "unknown_token"
+	ErrCodeUnknownHandle      = "unknown_handle"
+	ErrCodeInvalidConfig      = "invalid_config"
+	ErrCodeInputTooLarge      = "input_too_large"
+	ErrCodeTokenizerError     = "tokenizer_error"
+)
+
+// APIError is a structured error returned by a v2 export
+type APIError struct {
+	Code    string `json:"code" msgpack:"code"`
+	Message string `json:"message" msgpack:"message"`
+}
+
+func (e *APIError) Error() string {
+	return fmt.Sprintf("%s: %s", e.Code, e.Message)
+}
+
+// Capabilities describes what a plugin build supports, as reported by the describe export
+type Capabilities struct {
+	PluginVersion    string   `json:"plugin_version" msgpack:"plugin_version"`
+	APIVersions      []uint32 `json:"api_versions" msgpack:"api_versions"`
+	Model            string   `json:"model" msgpack:"model"`
+	VocabSize        uint32   `json:"vocab_size" msgpack:"vocab_size"`
+	TemplateVersions []string `json:"template_versions" msgpack:"template_versions"`
+	Templates        []string `json:"templates" msgpack:"templates"`
+	Encodings        []string `json:"encodings" msgpack:"encodings"`
+	Features         []string `json:"features" msgpack:"features"`
+	Exports          []string `json:"exports" msgpack:"exports"`
+}
+
+type v2Request struct {
+	ID     string      `msgpack:"id,omitempty"`
+	Params interface{} `msgpack:"params"`
+}
+
+type v2Response struct {
+	APIVersion uint32             `msgpack:"api_version"`
+	ID         *string            `msgpack:"id"`
+	OK         bool               `msgpack:"ok"`
+	Result     msgpack.RawMessage `msgpack:"result"`
+	Error      *APIError          `msgpack:"error"`
+}
+
+// callV2 calls a v2 export with msgpack encoding and decodes the result into result.
+// Plugin-reported failures are returned as *APIError.
+func callV2(plugin *extism.Plugin, export string, params interface{}, result interface{}) error {
+	inputData, err := msgpack.Marshal(v2Request{Params: params})
+	if err != nil {
+		return fmt.Errorf("failed to marshal input: %w", err)
+	}
+
+	_, output, err := plugin.Call(export, inputData)
+	if err != nil {
+		return fmt.Errorf("failed to call %s: %w", export, err)
+	}
+
+	var response v2Response
+	if err := msgpack.Unmarshal(output, &response); err != nil {
+		return fmt.Errorf("failed to unmarshal output: %w", err)
+	}
+	if !response.OK {
+		if response.Error == nil {
+			return fmt.Errorf("%s failed without an error", export)
+		}
+		return response.Error
+	}
+
+	if result != nil {
+		if err := msgpack.Unmarshal(response.Result, result); err != nil {
+			return fmt.Errorf("failed to unmarshal result: %w", err)
+		}
+	}
+	return nil
+}
+
+// CallV2 calls any v2 export (e.g. "v2_tokenize") with the given params.
+// Plugin-reported failures are returned as *APIError.
+func (t *Tokenizer) CallV2(export string, params interface{}, result interface{}) error {
+	return callV2(t.plugin, export, params, result)
+}
+
+// Describe reports the plugin version, model, vocabulary size and supported features
+func (t *Tokenizer) Describe() (*Capabilities, error) {
+	var capabilities Capabilities
+	if err := callV2(t.plugin, "describe", nil, &capabilities); err != nil {
+		return nil, err
+	}
+	return &capabilities, nil
+}
+
+// CallV2 calls any v2 export with the given params. Thread-safe.
+func (pt *PooledTokenizer) CallV2(export string, params interface{}, result interface{}) error {
+	plugin, err := pt.getInstance()
+	if err != nil {
+		return err
+	}
+	defer pt.putInstance(plugin)
+
+	return callV2(plugin, export, params, result)
+}
+
+// Describe reports the plugin version, model, vocabulary size and supported features. Thread-safe.
+func (pt *PooledTokenizer) Describe() (*Capabilities, error) {
+	var capabilities Capabilities
+	if err := pt.CallV2("describe", nil, &capabilities); err != nil {
+		return nil, err
+	}
+	return &capabilities, nil
+}
@@ -0,0 +1,528 @@
+//! Request types taken by the Extism plugin's exports, their conversions into the
+//! template types, and chat template resolution.
+//!
+//! This crate doesn't use `extism_pdk`, so the fuzz targets can depend on it directly.
+
+use std::fmt;
+
+use nai_tokenizers::batch::{BatchOptions, PaddingSide};
+use nai_tokenizers::chat_templates::{self, ChatTemplate, Glm};
+use nai_tokenizers::detokenization::{DetokenizeOptions, InvalidBytes};
+use nai_tokenizers::glm45_template::{
+    Chat, ContentPart, Message, PlaceholderLimitError, PrefillType, ReasoningEnabled,
+    ReasoningRetention, Version, VisionConfig,
+};
+use nai_tokenizers::special_tokens::{DecodePolicy, EncodePolicy, TokenSet};
+use nai_tokenizers::truncation::TruncationStrategy;
+use serde::Deserialize;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+
+#[derive(Deserialize)]
+pub struct TokenizeInput {
+    pub text: String,
+    /// Defaults to the `special_tokens` plugin config.
+    #[serde(default)]
+    pub include_special_tokens: Option<bool>,
+    /// Special tokens parsed from `text`: `"all"` or a list. Default: none.
+    #[serde(default)]
+    pub allowed_special: Option<TokenSet>,
+    /// Special tokens that fail the request if they're in `text`. Default: `"all"`.
+    #[serde(default)]
+    pub disallowed_special: Option<TokenSet>,
+}
+
+impl TokenizeInput {
+    /// The policy from `allowed_special`/`disallowed_special`, if either is set. Without
+    /// them, every special token in `text` is parsed, as before.
+    pub fn encode_policy(&self) -> Option<EncodePolicy> {
+        if self.allowed_special.is_none() && self.disallowed_special.is_none() {
+            return None;
+        }
+        let defaults = EncodePolicy::default();
+        Some(EncodePolicy {
+            allowed: self.allowed_special.clone().unwrap_or(defaults.allowed),
+            disallowed: self
+                .disallowed_special
+                .clone()
+                .unwrap_or(defaults.disallowed),
+        })
+    }
+}
+
+/// Raw bytes, sent as msgpack bin. An array of numbers is accepted too, for JSON.
+pub struct Bytes(pub Vec<u8>);
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Bytes;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("bytes")
+            }
+
+            fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Bytes, E> {
+                Ok(Bytes(bytes.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Bytes, E> {
+                Ok(Bytes(bytes))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Bytes, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(Bytes(bytes))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TokenizeBytesInput {
+    pub bytes: Bytes,
+    /// Defaults to the `special_tokens` plugin config.
+    #[serde(default)]
+    pub include_special_tokens: Option<bool>,
+}
+
+// OpenAI-compatible message types
+#[derive(Deserialize)]
+pub struct ExternalMessage {
+    role: String,
+    content: ExternalContent,
+    #[serde(default)]
+    reasoning_content: Option<String>,
+}
+
+/// Either a plain string or a list of content parts, as in the OpenAI API.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ExternalContent {
+    Text(String),
+    Parts(Vec<ExternalContentPart>),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExternalContentPart {
+    Text {
+        text: String,
+    },
+    Image {
+        width: u32,
+        height: u32,
+    },
+    Video {
+        frames: u32,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl From<ExternalContentPart> for ContentPart {
+    fn from(part: ExternalContentPart) -> Self {
+        match part {
+            ExternalContentPart::Text { text } => ContentPart::Text { text },
+            ExternalContentPart::Image { width, height } => ContentPart::Image { width, height },
+            ExternalContentPart::Video {
+                frames,
+                width,
+                height,
+            } => ContentPart::Video {
+                frames,
+                width,
+                height,
+            },
+        }
+    }
+}
+
+impl ExternalContent {
+    /// Flattens the content to text, dropping any image or video parts.
+    fn into_text(self) -> String {
+        match self {
+            ExternalContent::Text(text) => text,
+            ExternalContent::Parts(parts) => parts
+                .into_iter()
+                .filter_map(|part| match part {
+                    ExternalContentPart::Text { text } => Some(text),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<ExternalMessage> for Message {
+    fn from(msg: ExternalMessage) -> Self {
+        let role = msg.role.as_str();
+        match msg.content {
+            ExternalContent::Parts(parts)
+                if !matches!(role, "system" | "developer" | "assistant") =>
+            {
+                Message::MultimodalUser {
+                    content: parts.into_iter().map(|p| p.into()).collect(),
+                }
+            }
+            content => Message::from_role(role, content.into_text(), msg.reasoning_content),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ExternalVisionConfig {
+    #[serde(default)]
+    patch_size: Option<u32>,
+    #[serde(default)]
+    spatial_merge_size: Option<u32>,
+    #[serde(default)]
+    temporal_patch_size: Option<u32>,
+}
+
+impl From<ExternalVisionConfig> for VisionConfig {
+    fn from(config: ExternalVisionConfig) -> Self {
+        let defaults = VisionConfig::default();
+        VisionConfig {
+            patch_size: config.patch_size.unwrap_or(defaults.patch_size),
+            spatial_merge_size: config
+                .spatial_merge_size
+                .unwrap_or(defaults.spatial_merge_size),
+            temporal_patch_size: config
+                .temporal_patch_size
+                .unwrap_or(defaults.temporal_patch_size),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExternalPrefillType {
+    None,
+    Canonical,
+    PartialReasoning {
+        reasoning_content: String,
+    },
+    FullReasoning {
+        reasoning_content: String,
+        content: String,
+    },
+}
+
+impl From<ExternalPrefillType> for PrefillType {
+    fn from(prefill: ExternalPrefillType) -> Self {
+        match prefill {
+            ExternalPrefillType::None => PrefillType::None,
+            ExternalPrefillType::Canonical => PrefillType::Canonical,
+            ExternalPrefillType::PartialReasoning { reasoning_content } => {
+                PrefillType::PartialReasoning { reasoning_content }
+            }
+            ExternalPrefillType::FullReasoning {
+                reasoning_content,
+                content,
+            } => PrefillType::FullReasoning {
+                reasoning_content,
+                content,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExternalReasoningRetention {
+    StripPast,
+    SinceLastUser,
+    LastTurns { turns: usize },
+    All,
+}
+
+impl From<ExternalReasoningRetention> for ReasoningRetention {
+    fn from(retention: ExternalReasoningRetention) -> Self {
+        match retention {
+            ExternalReasoningRetention::StripPast => ReasoningRetention::StripPast,
+            ExternalReasoningRetention::SinceLastUser => ReasoningRetention::SinceLastUser,
+            ExternalReasoningRetention::LastTurns { turns } => ReasoningRetention::LastTurns(turns),
+            ExternalReasoningRetention::All => ReasoningRetention::All,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChatTemplateInput {
+    pub messages: Vec<ExternalMessage>,
+    // Unset fields fall back to the plugin config (see `config`).
+    #[serde(default)]
+    pub reasoning_enabled: Option<bool>,
+    #[serde(default)]
+    pub prefill: Option<ExternalPrefillType>,
+    #[serde(default)]
+    pub ignore_message_position: Option<bool>,
+    #[serde(default)]
+    pub vision: Option<ExternalVisionConfig>,
+    /// One of `TEMPLATE_VERSIONS`.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Which earlier assistant turns keep their reasoning. Defaults to `strip_past`.
+    #[serde(default)]
+    pub reasoning_retention: Option<ExternalReasoningRetention>,
+    /// One of `chat_templates::TEMPLATE_NAMES`. A GLM name works like `version`; the
+    /// other templates ignore the GLM-only options. Defaults to GLM.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+pub const TEMPLATE_VERSIONS: &[&str] = &["glm-4.5", "glm-4.6", "glm-4.7"];
+
+pub fn parse_template_version(version: &str) -> Option<Version> {
+    match version {
+        "glm-4.5" | "glm-4.6" => Some(Version::GLM456),
+        "glm-4.7" => Some(Version::GLM47),
+        _ => None,
+    }
+}
+
+pub fn is_glm_template(name: &str) -> bool {
+    TEMPLATE_VERSIONS.contains(&name)
+}
+
+/// Prefills that don't need any content, so they can be set plugin-wide.
+#[derive(Clone, Copy)]
+pub enum DefaultPrefill {
+    None,
+    Canonical,
+}
+
+impl From<DefaultPrefill> for PrefillType {
+    fn from(prefill: DefaultPrefill) -> Self {
+        match prefill {
+            DefaultPrefill::None => PrefillType::None,
+            DefaultPrefill::Canonical => PrefillType::Canonical,
+        }
+    }
+}
+
+/// What a `ChatTemplateInput` falls back to for the fields it leaves unset.
+#[derive(Clone, Copy)]
+pub struct TemplateDefaults {
+    pub version: Version,
+    pub reasoning_enabled: bool,
+    pub prefill: DefaultPrefill,
+    pub ignore_message_position: bool,
+}
+
+impl Default for TemplateDefaults {
+    fn default() -> Self {
+        Self {
+            version: Version::GLM456,
+            reasoning_enabled: false,
+            prefill: DefaultPrefill::Canonical,
+            ignore_message_position: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+    UnknownTemplate(String),
+    UnsupportedVersion(String),
+    Placeholders(PlaceholderLimitError),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownTemplate(name) => write!(f, "Unknown chat template: {}", name),
+            TemplateError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported template version: {}", version)
+            }
+            TemplateError::Placeholders(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// A chat template request with its defaults filled in, ready to render.
+pub struct ChatTemplateRequest {
+    pub template: Box<dyn ChatTemplate + Send + Sync>,
+    pub chat: Chat,
+    pub prefill: PrefillType,
+    pub reasoning: ReasoningEnabled,
+}
+
+impl ChatTemplateRequest {
+    pub fn render(self) -> Result<String, TemplateError> {
+        self.template
+            .render(&self.chat, self.prefill, self.reasoning)
+            .map_err(TemplateError::Placeholders)
+    }
+}
+
+impl ChatTemplateInput {
+    pub fn resolve(
+        self,
+        defaults: &TemplateDefaults,
+    ) -> Result<ChatTemplateRequest, TemplateError> {
+        let reasoning = if self.reasoning_enabled.unwrap_or(defaults.reasoning_enabled) {
+            ReasoningEnabled::Yes
+        } else {
+            ReasoningEnabled::No
+        };
+        let chat = Chat {
+            messages: self.messages.into_iter().map(|m| m.into()).collect(),
+        };
+        let prefill = match self.prefill {
+            Some(prefill) => prefill.into(),
+            None => defaults.prefill.into(),
+        };
+
+        let template: Box<dyn ChatTemplate + Send + Sync> = match self.template {
+            Some(name) if !is_glm_template(&name) => {
+                chat_templates::by_name(&name).ok_or(TemplateError::UnknownTemplate(name))?
+            }
+            name => Box::new(Glm {
+                version: match name.or(self.version) {
+                    Some(version) => parse_template_version(&version)
+                        .ok_or(TemplateError::UnsupportedVersion(version))?,
+                    None => defaults.version,
+                },
+                vision: self.vision.map(|v| v.into()).unwrap_or_default(),
+                reasoning_retention: self
+                    .reasoning_retention
+                    .map(|r| r.into())
+                    .unwrap_or_default(),
+                ignore_message_position: self
+                    .ignore_message_position
+                    .unwrap_or(defaults.ignore_message_position),
+            }),
+        };
+
+        Ok(ChatTemplateRequest {
+            template,
+            chat,
+            prefill,
+            reasoning,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DetokenizeInput {
+    pub tokens: Vec<u32>,
+    /// Defaults to the `special_tokens` plugin config.
+    #[serde(default)]
+    pub include_special_tokens: Option<bool>,
+    /// Special tokens to leave out: `"all"` or a list. Overrides `include_special_tokens`.
+    #[serde(default)]
+    pub skip_special: Option<TokenSet>,
+    /// Error on ids outside the vocabulary instead of dropping them. Defaults to off for
+    /// `detokenize` and `detokenize_bytes`, and on for their v2 exports.
+    #[serde(default)]
+    pub strict: Option<bool>,
+    /// `replace` (the default), `error` or `drop`.
+    #[serde(default)]
+    pub invalid_bytes: InvalidBytes,
+    /// Defaults to the tokenizer config's.
+    #[serde(default)]
+    pub clean_up_tokenization_spaces: Option<bool>,
+}
+
+impl DetokenizeInput {
+    pub fn decode_policy(&self) -> Option<DecodePolicy> {
+        self.skip_special.clone().map(|skip| DecodePolicy { skip })
+    }
+
+    pub fn options(&self, strict_by_default: bool) -> DetokenizeOptions {
+        DetokenizeOptions {
+            strict: self.strict.unwrap_or(strict_by_default),
+            invalid_bytes: self.invalid_bytes,
+            clean_up_tokenization_spaces: self.clean_up_tokenization_spaces,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StopMatcherNewInput {
+    /// Defaults to the GLM role sentinels and `<|endoftext|>`.
+    #[serde(default)]
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct StopMatcherPushInput {
+    pub handle: u32,
+    pub tokens: Vec<u32>,
+}
+
+/// Tokens to look up, by id and/or by vocabulary form (`Ġhello`, `<|user|>`).
+#[derive(Deserialize)]
+pub struct VocabLookupInput {
+    #[serde(default)]
+    pub ids: Vec<u32>,
+    #[serde(default)]
+    pub tokens: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ChunkTextInput {
+    pub text: String,
+    pub max_tokens: usize,
+    #[serde(default)]
+    pub overlap_tokens: usize,
+    /// Defaults to the `special_tokens` plugin config.
+    #[serde(default)]
+    pub include_special_tokens: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct TruncateInput {
+    pub text: String,
+    pub max_tokens: usize,
+    /// `keep_head` (the default), `keep_tail` or `keep_ends`.
+    #[serde(default)]
+    pub strategy: TruncationStrategy,
+    /// Defaults to `…`.
+    #[serde(default)]
+    pub ellipsis: Option<String>,
+    /// Defaults to the `special_tokens` plugin config.
+    #[serde(default)]
+    pub include_special_tokens: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct EncodeBatchInput {
+    pub texts: Vec<String>,
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    #[serde(default)]
+    pub return_overflowing_tokens: bool,
+    #[serde(default)]
+    pub stride: usize,
+    /// Defaults to the tokenizer config's `padding_side` (left for GLM-4.5).
+    #[serde(default)]
+    pub padding_side: Option<PaddingSide>,
+    #[serde(default)]
+    pub pad_to_multiple_of: Option<usize>,
+    /// Defaults to the `special_tokens` plugin config.
+    #[serde(default)]
+    pub include_special_tokens: Option<bool>,
+}
+
+impl EncodeBatchInput {
+    pub fn options(&self) -> BatchOptions {
+        BatchOptions {
+            max_length: self.max_length,
+            return_overflowing_tokens: self.return_overflowing_tokens,
+            stride: self.stride,
+            padding_side: self.padding_side,
+            pad_to_multiple_of: self.pad_to_multiple_of,
+        }
+    }
+}
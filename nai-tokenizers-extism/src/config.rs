@@ -0,0 +1,110 @@
+//! Plugin-wide defaults read from the Extism manifest `config`.
+//!
+//! | key                       | values                            | default     |
+//! |---------------------------|-----------------------------------|-------------|
+//! | `template_version`        | one of `TEMPLATE_VERSIONS`        | `glm-4.5`   |
+//! | `reasoning_enabled`       | `true` / `false`                  | `false`     |
+//! | `prefill`                 | `none` / `canonical`              | `canonical` |
+//! | `ignore_message_position` | `true` / `false`                  | `false`     |
+//! | `special_tokens`          | `keep` / `ignore`                 | `ignore`    |
+//! | `max_input_bytes`         | request size limit in bytes       | unlimited   |
+//!
+//! The config is read once, on the first call. Fields set on a call override these.
+//! An invalid config fails every call with `invalid_config` rather than being ignored.
+
+use std::sync::LazyLock;
+
+use extism_pdk::*;
+use nai_tokenizers_extism_input::{DefaultPrefill, TemplateDefaults, parse_template_version};
+
+use crate::v2::{ApiError, ErrorCode};
+
+#[derive(Default)]
+pub(crate) struct PluginDefaults {
+    pub template: TemplateDefaults,
+    pub include_special_tokens: bool,
+    pub max_input_bytes: Option<u64>,
+}
+
+fn get(key: &str) -> Result<Option<String>, String> {
+    config::get(key).map_err(|e| format!("Failed to read config key {}: {}", key, e))
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("{} must be true or false, got {:?}", key, value)),
+    }
+}
+
+impl PluginDefaults {
+    fn from_config() -> Result<Self, String> {
+        let mut defaults = PluginDefaults::default();
+        if let Some(value) = get("template_version")? {
+            defaults.template.version = parse_template_version(&value)
+                .ok_or_else(|| format!("Unsupported template version: {}", value))?;
+        }
+        if let Some(value) = get("reasoning_enabled")? {
+            defaults.template.reasoning_enabled = parse_bool("reasoning_enabled", &value)?;
+        }
+        if let Some(value) = get("prefill")? {
+            defaults.template.prefill = match value.as_str() {
+                "none" => DefaultPrefill::None,
+                "canonical" => DefaultPrefill::Canonical,
+                _ => {
+                    return Err(format!(
+                        "prefill must be none or canonical, got {:?}",
+                        value
+                    ));
+                }
+            };
+        }
+        if let Some(value) = get("ignore_message_position")? {
+            defaults.template.ignore_message_position =
+                parse_bool("ignore_message_position", &value)?;
+        }
+        if let Some(value) = get("special_tokens")? {
+            defaults.include_special_tokens = match value.as_str() {
+                "keep" => true,
+                "ignore" => false,
+                _ => {
+                    return Err(format!(
+                        "special_tokens must be keep or ignore, got {:?}",
+                        value
+                    ));
+                }
+            };
+        }
+        if let Some(value) = get("max_input_bytes")? {
+            defaults.max_input_bytes =
+                Some(value.parse().map_err(|_| {
+                    format!("max_input_bytes must be a byte count, got {:?}", value)
+                })?);
+        }
+        Ok(defaults)
+    }
+}
+
+static DEFAULTS: LazyLock<Result<PluginDefaults, String>> =
+    LazyLock::new(PluginDefaults::from_config);
+
+pub(crate) fn defaults() -> Result<&'static PluginDefaults, ApiError> {
+    DEFAULTS.as_ref().map_err(|e| {
+        ApiError::new(
+            ErrorCode::InvalidConfig,
+            format!("Invalid plugin config: {}", e),
+        )
+    })
+}
+
+/// Validates the config and enforces `max_input_bytes` on a request of `len` bytes.
+pub(crate) fn check_input(len: u64) -> Result<(), ApiError> {
+    match defaults()?.max_input_bytes {
+        Some(max) if len > max => Err(ApiError::new(
+            ErrorCode::InputTooLarge,
+            format!("Input is {} bytes, the limit is {}", len, max),
+        )),
+        _ => Ok(()),
+    }
+}
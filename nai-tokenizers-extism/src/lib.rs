@@ -1,149 +1,452 @@
+mod config;
+mod v2;
+
 use extism_pdk::*;
-use nai_tokenizers::glm45_template::{
-    Chat, ContextState, Message, PrefillType, ReasoningEnabled,
+use nai_tokenizers::batch;
+use nai_tokenizers::chunking::{self, Chunk, ChunkOptions};
+use nai_tokenizers::detokenization::{self, Detokenized};
+use nai_tokenizers::glm45_tokenizer::{
+    self, PlaceholderKind, SpecialTokens, placeholder_spans, tokenize as glm_tokenize,
+};
+use nai_tokenizers::special_tokens::{self, DecodePolicy};
+use nai_tokenizers::stop_sequences::StopMatcher;
+use nai_tokenizers::truncation::{self, TruncateOptions, Truncation};
+use nai_tokenizers::vocab::{self, SearchQuery, SearchResults, TokenInfo};
+use nai_tokenizers_extism_input::{
+    self as input, ChatTemplateInput, ChunkTextInput, DetokenizeInput, EncodeBatchInput,
+    StopMatcherNewInput, StopMatcherPushInput, TemplateError, TokenizeBytesInput, TokenizeInput,
+    TruncateInput, VocabLookupInput,
 };
-use nai_tokenizers::glm45_tokenizer::{SpecialTokens, tokenize as glm_tokenize};
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::sync::{LazyLock, Mutex};
+use v2::{ApiError, ErrorCode};
 
-#[derive(Deserialize)]
-struct TokenizeInput {
-    text: String,
-    include_special_tokens: bool,
+/// Decodes a v1 export's msgpack input. The config and `max_input_bytes` are checked
+/// first, so an oversized request is rejected before it's parsed.
+fn decode_input<T: DeserializeOwned>(input: &[u8]) -> Result<T, ApiError> {
+    config::check_input(input.len() as u64)?;
+    rmp_serde::from_slice(input).map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e))
 }
 
-// OpenAI-compatible message types
-#[derive(Deserialize)]
-struct ExternalMessage {
-    role: String,
-    content: String,
-    #[serde(default)]
-    reasoning_content: Option<String>,
+impl From<TemplateError> for ApiError {
+    fn from(error: TemplateError) -> Self {
+        let code = match error {
+            TemplateError::UnknownTemplate(_) | TemplateError::Placeholders(_) => {
+                ErrorCode::InvalidRequest
+            }
+            TemplateError::UnsupportedVersion(_) => ErrorCode::UnsupportedVersion,
+        };
+        ApiError::new(code, error)
+    }
 }
 
-impl From<ExternalMessage> for Message {
-    fn from(msg: ExternalMessage) -> Self {
-        match msg.role.as_str() {
-            "system" | "developer" => Message::System {
-                content: msg.content,
-            },
-            "user" => Message::User {
-                content: msg.content,
-            },
-            "assistant" => Message::Assistant {
-                content: msg.content,
-                reasoning_content: msg.reasoning_content,
-            },
-            _ => Message::User {
-                content: msg.content,
-            },
-        }
+fn special_tokens(include: Option<bool>) -> Result<SpecialTokens, ApiError> {
+    if include.unwrap_or(config::defaults()?.include_special_tokens) {
+        Ok(SpecialTokens::Keep)
+    } else {
+        Ok(SpecialTokens::Ignore)
     }
 }
 
-#[derive(Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-enum ExternalPrefillType {
-    None,
-    Canonical,
-    PartialReasoning { reasoning_content: String },
-    FullReasoning {
-        reasoning_content: String,
-        content: String,
-    },
-}
-
-impl From<ExternalPrefillType> for PrefillType {
-    fn from(prefill: ExternalPrefillType) -> Self {
-        match prefill {
-            ExternalPrefillType::None => PrefillType::None,
-            ExternalPrefillType::Canonical => PrefillType::Canonical,
-            ExternalPrefillType::PartialReasoning { reasoning_content } => {
-                PrefillType::PartialReasoning { reasoning_content }
-            }
-            ExternalPrefillType::FullReasoning {
-                reasoning_content,
-                content,
-            } => PrefillType::FullReasoning {
-                reasoning_content,
-                content,
-            },
-        }
+fn tokenize_text(input: TokenizeInput) -> Result<Vec<u32>, ApiError> {
+    match input.encode_policy() {
+        Some(policy) => special_tokens::tokenize_with_policy(&input.text, &policy)
+            .map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e)),
+        None => glm_tokenize(&input.text, special_tokens(input.include_special_tokens)?)
+            .map_err(|e| ApiError::new(ErrorCode::TokenizerError, e)),
     }
 }
 
-#[derive(Deserialize)]
-struct ChatTemplateInput {
-    messages: Vec<ExternalMessage>,
-    #[serde(default)]
-    reasoning_enabled: bool,
-    #[serde(default)]
-    prefill: Option<ExternalPrefillType>,
-    #[serde(default)]
-    ignore_message_position: bool,
+#[plugin_fn]
+pub fn tokenize(input: Vec<u8>) -> FnResult<Msgpack<Vec<u32>>> {
+    let input: TokenizeInput = decode_input(&input)?;
+    Ok(Msgpack(tokenize_text(input)?))
+}
+
+/// `skip_special` if set, else `include_special_tokens` or the plugin config.
+fn decode_policy(input: &DetokenizeInput) -> Result<DecodePolicy, ApiError> {
+    match input.decode_policy() {
+        Some(policy) => Ok(policy),
+        None => Ok(special_tokens(input.include_special_tokens)?.into()),
+    }
+}
+
+fn detokenize_tokens(
+    input: DetokenizeInput,
+    strict_by_default: bool,
+) -> Result<Detokenized, ApiError> {
+    detokenization::detokenize_with_options(
+        &input.tokens,
+        &decode_policy(&input)?,
+        &input.options(strict_by_default),
+    )
+    .map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e))
 }
 
 #[plugin_fn]
-pub fn tokenize(
-    Msgpack(TokenizeInput {
-        text,
+pub fn detokenize(input: Vec<u8>) -> FnResult<String> {
+    let input: DetokenizeInput = decode_input(&input)?;
+    Ok(detokenize_tokens(input, false)?.text)
+}
+
+fn tokenize_raw_bytes(
+    TokenizeBytesInput {
+        bytes,
         include_special_tokens,
-    }): Msgpack<TokenizeInput>,
-) -> FnResult<Msgpack<Vec<u32>>> {
-    let include_special_tokens = if include_special_tokens {
-        SpecialTokens::Keep
-    } else {
-        SpecialTokens::Ignore
-    };
-    let tokens = glm_tokenize(&text, include_special_tokens)?;
-    Ok(Msgpack(tokens))
+    }: TokenizeBytesInput,
+) -> Result<Vec<u32>, ApiError> {
+    glm45_tokenizer::tokenize_bytes(&bytes.0, special_tokens(include_special_tokens)?)
+        .map_err(|e| ApiError::new(ErrorCode::TokenizerError, e))
 }
 
-#[derive(Deserialize)]
-struct DetokenizeInput {
-    tokens: Vec<u32>,
-    include_special_tokens: bool,
+/// Tokenizes raw bytes (msgpack bin) that need not be valid UTF-8.
+#[plugin_fn]
+pub fn tokenize_bytes(input: Vec<u8>) -> FnResult<Msgpack<Vec<u32>>> {
+    let input: TokenizeBytesInput = decode_input(&input)?;
+    Ok(Msgpack(tokenize_raw_bytes(input)?))
+}
+
+fn detokenize_raw_bytes(
+    input: DetokenizeInput,
+    strict_by_default: bool,
+) -> Result<LeBytes<u8>, ApiError> {
+    detokenization::detokenize_bytes(
+        &input.tokens,
+        &decode_policy(&input)?,
+        input.options(strict_by_default).strict,
+    )
+    .map(LeBytes)
+    .map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e))
+}
+
+/// Decodes tokens to their exact bytes (msgpack bin), without U+FFFD replacement.
+#[plugin_fn]
+pub fn detokenize_bytes(input: Vec<u8>) -> FnResult<Msgpack<LeBytes<u8>>> {
+    let input: DetokenizeInput = decode_input(&input)?;
+    Ok(Msgpack(detokenize_raw_bytes(input, false)?))
+}
+
+fn render_chat_template(input: ChatTemplateInput) -> Result<String, ApiError> {
+    Ok(input.resolve(&config::defaults()?.template)?.render()?)
 }
 
 #[plugin_fn]
-pub fn detokenize(
-    Msgpack(DetokenizeInput {
+pub fn chat_template(input: Vec<u8>) -> FnResult<String> {
+    let input: ChatTemplateInput = decode_input(&input)?;
+    Ok(render_chat_template(input)?)
+}
+
+#[derive(Serialize)]
+struct PlaceholderSpanOutput {
+    kind: &'static str,
+    start: usize,
+    len: usize,
+}
+
+#[derive(Serialize)]
+struct ChatTemplateTokensOutput {
+    tokens: Vec<u32>,
+    placeholder_spans: Vec<PlaceholderSpanOutput>,
+}
+
+/// Renders and tokenizes the chat, reporting where image/video placeholders landed
+/// so the vision encoder outputs can be spliced in.
+fn render_chat_template_tokens(
+    input: ChatTemplateInput,
+) -> Result<ChatTemplateTokensOutput, ApiError> {
+    if let Some(name) = input
+        .template
+        .as_deref()
+        .filter(|name| !input::is_glm_template(name))
+    {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!("Only GLM templates can be tokenized, got {}", name),
+        ));
+    }
+    let text = render_chat_template(input)?;
+    let tokens = glm_tokenize(&text, SpecialTokens::Keep)
+        .map_err(|e| ApiError::new(ErrorCode::TokenizerError, e))?;
+    let placeholder_spans = placeholder_spans(&tokens)
+        .map_err(|e| ApiError::new(ErrorCode::TokenizerError, e))?
+        .into_iter()
+        .map(|span| PlaceholderSpanOutput {
+            kind: match span.kind {
+                PlaceholderKind::Image => "image",
+                PlaceholderKind::Video => "video",
+            },
+            start: span.start,
+            len: span.len,
+        })
+        .collect();
+    Ok(ChatTemplateTokensOutput {
         tokens,
-        include_special_tokens,
-    }): Msgpack<DetokenizeInput>,
-) -> FnResult<String> {
-    let include_special_tokens = if include_special_tokens {
-        SpecialTokens::Keep
-    } else {
-        SpecialTokens::Ignore
+        placeholder_spans,
+    })
+}
+
+#[plugin_fn]
+pub fn chat_template_tokens(input: Vec<u8>) -> FnResult<Msgpack<ChatTemplateTokensOutput>> {
+    let input: ChatTemplateInput = decode_input(&input)?;
+    Ok(Msgpack(render_chat_template_tokens(input)?))
+}
+
+/// Stop matchers live in the plugin instance between calls, keyed by the handle
+/// returned from `stop_matcher_new`. Handles increase, so the first entry is the oldest.
+static STOP_MATCHERS: LazyLock<Mutex<(u32, BTreeMap<u32, StopMatcher>)>> =
+    LazyLock::new(|| Mutex::new((0, BTreeMap::new())));
+
+/// Matchers that are never finished or freed would otherwise live as long as the plugin.
+/// Past this many, creating a matcher evicts the oldest one.
+const MAX_STOP_MATCHERS: usize = 1024;
+
+fn new_stop_matcher(StopMatcherNewInput { stop_sequences }: StopMatcherNewInput) -> u32 {
+    let matcher = match stop_sequences {
+        Some(stops) => StopMatcher::new(stops),
+        None => StopMatcher::default(),
     };
-    let text = nai_tokenizers::glm45_tokenizer::detokenize(&tokens, include_special_tokens)?;
+    let mut matchers = STOP_MATCHERS.lock().unwrap();
+    let (next, map) = &mut *matchers;
+    while map.len() >= MAX_STOP_MATCHERS {
+        map.pop_first();
+    }
+    *next = next.wrapping_add(1);
+    map.insert(*next, matcher);
+    *next
+}
+
+#[plugin_fn]
+pub fn stop_matcher_new(input: Vec<u8>) -> FnResult<Msgpack<u32>> {
+    let input: StopMatcherNewInput = decode_input(&input)?;
+    Ok(Msgpack(new_stop_matcher(input)))
+}
+
+#[derive(Serialize)]
+struct StopMatcherOutput {
+    /// Text that is safe to emit.
+    text: String,
+    stopped: bool,
+    stop_sequence: Option<String>,
+    /// How many of the pushed tokens were consumed; tokens after a match are ignored.
+    consumed: usize,
+}
+
+fn with_stop_matcher<T>(
+    handle: u32,
+    f: impl FnOnce(&mut StopMatcher) -> Result<T, ApiError>,
+) -> Result<T, ApiError> {
+    let mut matchers = STOP_MATCHERS.lock().unwrap();
+    let matcher = matchers.1.get_mut(&handle).ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::UnknownHandle,
+            format!("Unknown stop matcher handle {}", handle),
+        )
+    })?;
+    f(matcher)
+}
+
+fn push_stop_matcher(
+    StopMatcherPushInput { handle, tokens }: StopMatcherPushInput,
+) -> Result<StopMatcherOutput, ApiError> {
+    with_stop_matcher(handle, |matcher| {
+        let mut output = StopMatcherOutput {
+            text: String::new(),
+            stopped: matcher.stopped().is_some(),
+            stop_sequence: matcher.stopped().map(|stop| stop.sequence.clone()),
+            consumed: 0,
+        };
+        for id in tokens {
+            if output.stopped {
+                break;
+            }
+            let update = matcher
+                .push(id)
+                .map_err(|e| ApiError::new(ErrorCode::TokenOutOfRange, e))?;
+            output.text.push_str(&update.text);
+            output.consumed += 1;
+            if let Some(stop) = update.stop {
+                output.stopped = true;
+                output.stop_sequence = Some(stop.sequence);
+            }
+        }
+        Ok(output)
+    })
+}
+
+#[plugin_fn]
+pub fn stop_matcher_push(input: Vec<u8>) -> FnResult<Msgpack<StopMatcherOutput>> {
+    let input: StopMatcherPushInput = decode_input(&input)?;
+    Ok(Msgpack(push_stop_matcher(input)?))
+}
+
+fn finish_stop_matcher(handle: u32) -> Result<String, ApiError> {
+    let text = with_stop_matcher(handle, |matcher| Ok(matcher.finish()))?;
+    free_stop_matcher(handle);
     Ok(text)
 }
 
+fn free_stop_matcher(handle: u32) {
+    STOP_MATCHERS.lock().unwrap().1.remove(&handle);
+}
+
+/// Flushes the held-back text and frees the matcher.
 #[plugin_fn]
-pub fn chat_template(
-    Msgpack(ChatTemplateInput {
-        messages,
-        reasoning_enabled,
-        prefill,
-        ignore_message_position,
-    }): Msgpack<ChatTemplateInput>,
-) -> FnResult<String> {
-    let reasoning = if reasoning_enabled {
-        ReasoningEnabled::Yes
-    } else {
-        ReasoningEnabled::No
-    };
+pub fn stop_matcher_finish(input: Vec<u8>) -> FnResult<String> {
+    let handle: u32 = decode_input(&input)?;
+    Ok(finish_stop_matcher(handle)?)
+}
+
+#[plugin_fn]
+pub fn stop_matcher_free(input: Vec<u8>) -> FnResult<()> {
+    let handle: u32 = decode_input(&input)?;
+    free_stop_matcher(handle);
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct VocabLookupOutput {
+    tokens: Vec<TokenInfo>,
+}
+
+/// Looks up `ids` and then `tokens`, in request order.
+fn lookup_vocab(
+    VocabLookupInput { ids, tokens }: VocabLookupInput,
+) -> Result<VocabLookupOutput, ApiError> {
+    let by_id = ids.into_iter().map(|id| {
+        vocab::token_info(id).ok_or_else(|| {
+            ApiError::new(
+                ErrorCode::TokenOutOfRange,
+                format!("Token id {} is out of range", id),
+            )
+        })
+    });
+    let by_token = tokens.into_iter().map(|token| {
+        vocab::token_to_id(&token)
+            .and_then(vocab::token_info)
+            .ok_or_else(|| {
+                ApiError::new(ErrorCode::UnknownToken, format!("Unknown token: {}", token))
+            })
+    });
+    Ok(VocabLookupOutput {
+        tokens: by_id.chain(by_token).collect::<Result<_, _>>()?,
+    })
+}
+
+#[plugin_fn]
+pub fn vocab_lookup(input: Vec<u8>) -> FnResult<Msgpack<VocabLookupOutput>> {
+    let input: VocabLookupInput = decode_input(&input)?;
+    Ok(Msgpack(lookup_vocab(input)?))
+}
+
+fn search_vocab(query: SearchQuery) -> Result<SearchResults, ApiError> {
+    vocab::search(&query).map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e))
+}
 
-    let internal_messages: Vec<Message> = messages.into_iter().map(|m| m.into()).collect();
-    let chat = Chat {
-        messages: internal_messages,
+#[plugin_fn]
+pub fn vocab_search(input: Vec<u8>) -> FnResult<Msgpack<SearchResults>> {
+    let query: SearchQuery = decode_input(&input)?;
+    Ok(Msgpack(search_vocab(query)?))
+}
+
+#[derive(Serialize)]
+struct ChunkTextOutput {
+    chunks: Vec<Chunk>,
+}
+
+fn split_text(input: ChunkTextInput) -> Result<ChunkTextOutput, ApiError> {
+    let options = ChunkOptions {
+        max_tokens: input.max_tokens,
+        overlap_tokens: input.overlap_tokens,
+        special_tokens: special_tokens(input.include_special_tokens)?,
     };
+    let chunks = chunking::chunk_text(&input.text, options)
+        .map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e))?;
+    Ok(ChunkTextOutput { chunks })
+}
+
+/// Splits text into chunks of at most `max_tokens` tokens, with byte ranges.
+#[plugin_fn]
+pub fn chunk_text(input: Vec<u8>) -> FnResult<Msgpack<ChunkTextOutput>> {
+    let input: ChunkTextInput = decode_input(&input)?;
+    Ok(Msgpack(split_text(input)?))
+}
+
+fn truncate_text(input: TruncateInput) -> Result<Truncation, ApiError> {
+    let mut options = TruncateOptions::new(input.max_tokens, input.strategy);
+    if let Some(ellipsis) = input.ellipsis {
+        options.ellipsis = ellipsis;
+    }
+    let special_tokens = special_tokens(input.include_special_tokens)?;
+    truncation::truncate(&input.text, &options, special_tokens)
+        .map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e))
+}
+
+/// Cuts text to at most `max_tokens` tokens and reports the dropped byte ranges.
+#[plugin_fn]
+pub fn truncate(input: Vec<u8>) -> FnResult<Msgpack<Truncation>> {
+    let input: TruncateInput = decode_input(&input)?;
+    Ok(Msgpack(truncate_text(input)?))
+}
+
+/// Serializes numbers as one little-endian byte string (msgpack `bin`), so hosts can
+/// view it as a typed array without decoding element by element. JSON gets a plain
+/// array of numbers.
+struct LeBytes<T>(Vec<T>);
+
+impl Serialize for LeBytes<u32> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return self.0.serialize(serializer);
+        }
+        let bytes: Vec<u8> = self.0.iter().flat_map(|n| n.to_le_bytes()).collect();
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+impl Serialize for LeBytes<u8> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return self.0.serialize(serializer);
+        }
+        serializer.serialize_bytes(&self.0)
+    }
+}
 
-    let prefill_type = prefill
-        .map(|p| p.into())
-        .unwrap_or(PrefillType::Canonical);
+/// `input_ids`, `attention_mask` and `position_ids` are `[rows, width]` row-major
+/// arrays of `u32`, `u8` and `u32`.
+#[derive(Serialize)]
+struct EncodeBatchOutput {
+    rows: usize,
+    width: usize,
+    pad_token_id: u32,
+    input_ids: LeBytes<u32>,
+    attention_mask: LeBytes<u8>,
+    position_ids: LeBytes<u32>,
+    sample_mapping: Vec<u32>,
+    lengths: Vec<u32>,
+}
 
-    let result = ContextState::new(reasoning).chat_with_options(&chat, prefill_type, ignore_message_position);
-    Ok(result)
+fn encode_batch(input: EncodeBatchInput) -> Result<EncodeBatchOutput, ApiError> {
+    let special_tokens = special_tokens(input.include_special_tokens)?;
+    let batch = batch::encode_batch(&input.texts, &input.options(), special_tokens)
+        .map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e))?;
+    Ok(EncodeBatchOutput {
+        rows: batch.rows,
+        width: batch.width,
+        pad_token_id: batch.pad_token_id,
+        input_ids: LeBytes(batch.input_ids),
+        attention_mask: LeBytes(batch.attention_mask),
+        position_ids: LeBytes(batch.position_ids),
+        sample_mapping: batch.sample_mapping,
+        lengths: batch.lengths,
+    })
+}
+
+/// Tokenizes several texts into one padded batch with attention masks and position ids.
+#[plugin_fn]
+pub fn batch_encode(input: Vec<u8>) -> FnResult<Msgpack<EncodeBatchOutput>> {
+    let input: EncodeBatchInput = decode_input(&input)?;
+    Ok(Msgpack(encode_batch(input)?))
 }
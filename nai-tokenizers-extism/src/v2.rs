@@ -0,0 +1,390 @@
+//! Version 2 of the plugin API.
+//!
+//! Every `v2_*` export takes a request envelope `{"id": ..., "params": {...}}` and
+//! answers with `{"api_version": 2, "id": ..., "ok": true, "result": ...}` or
+//! `{"api_version": 2, "id": ..., "ok": false, "error": {"code": ..., "message": ...}}`.
+//! Requests may be JSON or msgpack; the response uses the same encoding. Failures are
+//! always reported in the envelope, never as a plugin error, so hosts can switch on
+//! `error.code` instead of parsing messages.
+
+use std::fmt;
+
+use extism_pdk::*;
+use nai_tokenizers::chat_templates;
+use nai_tokenizers::glm45_tokenizer;
+use nai_tokenizers_extism_input::{
+    ChatTemplateInput, DetokenizeInput, StopMatcherNewInput, TokenizeBytesInput, TokenizeInput,
+};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Serialize};
+
+pub(crate) const API_VERSION: u32 = 2;
+
+/// Stable error codes. New codes may be added; existing ones never change meaning.
+/// Failures inside the tokenizer itself are reported as `tokenizer_error`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorCode {
+    /// The request isn't valid JSON or msgpack, or doesn't match the export's schema.
+    InvalidRequest,
+    /// A template version or template name isn't supported by this build.
+    UnsupportedVersion,
+    /// A token id is outside the vocabulary.
+    TokenOutOfRange,
+    /// A token string isn't in the vocabulary.
+    UnknownToken,
+    /// The stop matcher handle doesn't exist (or was already finished).
+    UnknownHandle,
+    /// The plugin's manifest config is invalid; every call fails until it's fixed.
+    InvalidConfig,
+    /// The request is larger than the `max_input_bytes` config.
+    InputTooLarge,
+    TokenizerError,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl fmt::Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Json,
+    Msgpack,
+}
+
+impl Encoding {
+    /// JSON requests are objects, so they start with `{` (possibly after whitespace);
+    /// a msgpack map never does. Empty input is treated as JSON.
+    fn sniff(input: &[u8]) -> Self {
+        match input.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') | None => Encoding::Json,
+            Some(_) => Encoding::Msgpack,
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(self, input: &[u8]) -> Result<T, ApiError> {
+        let input = match (self, input.trim_ascii().is_empty()) {
+            (Encoding::Json, true) => b"{}".as_slice(),
+            _ => input,
+        };
+        match self {
+            Encoding::Json => serde_json::from_slice(input)
+                .map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e)),
+            Encoding::Msgpack => rmp_serde::from_slice(input)
+                .map_err(|e| ApiError::new(ErrorCode::InvalidRequest, e)),
+        }
+    }
+
+    /// Msgpack responses use maps with field names, like the JSON ones.
+    fn encode<T: Serialize>(self, value: &T) -> Vec<u8> {
+        match self {
+            Encoding::Json => serde_json::to_vec(value).expect("response serializes"),
+            Encoding::Msgpack => rmp_serde::to_vec_named(value).expect("response serializes"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Request<T> {
+    #[serde(default)]
+    id: Option<String>,
+    params: T,
+}
+
+/// Only used to recover the request id when the params don't parse.
+#[derive(Deserialize)]
+struct RequestId {
+    #[serde(default)]
+    id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Response<T> {
+    api_version: u32,
+    id: Option<String>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ApiError>,
+}
+
+fn handle<P: DeserializeOwned, R: Serialize>(
+    input: &[u8],
+    f: impl FnOnce(P) -> Result<R, ApiError>,
+) -> Vec<u8> {
+    let encoding = Encoding::sniff(input);
+    let request = crate::config::check_input(input.len() as u64)
+        .and_then(|()| encoding.decode::<Request<P>>(input));
+    let (id, result) = match request {
+        Ok(Request { id, params }) => (id, f(params)),
+        Err(e) => {
+            let id = encoding.decode::<RequestId>(input).ok().and_then(|r| r.id);
+            (id, Err(e))
+        }
+    };
+    let response = match result {
+        Ok(result) => Response {
+            api_version: API_VERSION,
+            id,
+            ok: true,
+            result: Some(result),
+            error: None,
+        },
+        Err(error) => Response {
+            api_version: API_VERSION,
+            id,
+            ok: false,
+            result: None,
+            error: Some(error),
+        },
+    };
+    encoding.encode(&response)
+}
+
+#[derive(Serialize)]
+struct TokensOutput {
+    tokens: Vec<u32>,
+}
+
+#[derive(Serialize)]
+struct TextOutput {
+    text: String,
+}
+
+#[plugin_fn]
+pub fn v2_tokenize(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, |params: TokenizeInput| {
+        Ok(TokensOutput {
+            tokens: crate::tokenize_text(params)?,
+        })
+    }))
+}
+
+fn check_token_range(tokens: &[u32]) -> Result<(), ApiError> {
+    let vocab_size = glm45_tokenizer::vocab_size() as u32;
+    match tokens.iter().find(|&&id| id >= vocab_size) {
+        Some(id) => Err(ApiError::new(
+            ErrorCode::TokenOutOfRange,
+            format!("Token id {} is out of range", id),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Strict by default: ids outside the vocabulary are `token_out_of_range`. The result
+/// carries the options as applied next to `text`.
+#[plugin_fn]
+pub fn v2_detokenize(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, |params: DetokenizeInput| {
+        if params.strict.unwrap_or(true) {
+            check_token_range(&params.tokens)?;
+        }
+        crate::detokenize_tokens(params, true)
+    }))
+}
+
+#[plugin_fn]
+pub fn v2_tokenize_bytes(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, |params: TokenizeBytesInput| {
+        Ok(TokensOutput {
+            tokens: crate::tokenize_raw_bytes(params)?,
+        })
+    }))
+}
+
+#[derive(Serialize)]
+struct BytesOutput {
+    bytes: crate::LeBytes<u8>,
+}
+
+/// Strict by default, like `v2_detokenize`.
+#[plugin_fn]
+pub fn v2_detokenize_bytes(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, |params: DetokenizeInput| {
+        if params.strict.unwrap_or(true) {
+            check_token_range(&params.tokens)?;
+        }
+        Ok(BytesOutput {
+            bytes: crate::detokenize_raw_bytes(params, true)?,
+        })
+    }))
+}
+
+#[plugin_fn]
+pub fn v2_chat_template(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, |params: ChatTemplateInput| {
+        Ok(TextOutput {
+            text: crate::render_chat_template(params)?,
+        })
+    }))
+}
+
+#[plugin_fn]
+pub fn v2_chat_template_tokens(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, crate::render_chat_template_tokens))
+}
+
+#[plugin_fn]
+pub fn v2_stop_matcher_new(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    #[derive(Serialize)]
+    struct HandleOutput {
+        handle: u32,
+    }
+    Ok(handle(&input, |params: StopMatcherNewInput| {
+        Ok(HandleOutput {
+            handle: crate::new_stop_matcher(params),
+        })
+    }))
+}
+
+#[plugin_fn]
+pub fn v2_stop_matcher_push(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, crate::push_stop_matcher))
+}
+
+#[derive(Deserialize)]
+struct HandleInput {
+    handle: u32,
+}
+
+#[plugin_fn]
+pub fn v2_stop_matcher_finish(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, |params: HandleInput| {
+        Ok(TextOutput {
+            text: crate::finish_stop_matcher(params.handle)?,
+        })
+    }))
+}
+
+#[plugin_fn]
+pub fn v2_stop_matcher_free(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, |params: HandleInput| {
+        crate::free_stop_matcher(params.handle);
+        Ok(())
+    }))
+}
+
+#[plugin_fn]
+pub fn v2_vocab_lookup(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, crate::lookup_vocab))
+}
+
+#[plugin_fn]
+pub fn v2_vocab_search(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, crate::search_vocab))
+}
+
+#[plugin_fn]
+pub fn v2_chunk_text(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, crate::split_text))
+}
+
+#[plugin_fn]
+pub fn v2_truncate(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, crate::truncate_text))
+}
+
+#[plugin_fn]
+pub fn v2_batch_encode(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, crate::encode_batch))
+}
+
+#[derive(Serialize)]
+struct Description {
+    plugin_version: &'static str,
+    api_versions: &'static [u32],
+    model: &'static str,
+    vocab_size: usize,
+    template_versions: &'static [&'static str],
+    templates: &'static [&'static str],
+    encodings: &'static [&'static str],
+    features: &'static [&'static str],
+    exports: &'static [&'static str],
+}
+
+/// Reports what this build of the plugin supports. Takes an optional envelope (params
+/// are ignored); with no input at all, answers in JSON.
+#[plugin_fn]
+pub fn describe(input: Vec<u8>) -> FnResult<Vec<u8>> {
+    Ok(handle(&input, |_: Option<IgnoredAny>| {
+        Ok(Description {
+            plugin_version: env!("CARGO_PKG_VERSION"),
+            api_versions: &[1, API_VERSION],
+            model: "glm-4.5",
+            vocab_size: glm45_tokenizer::vocab_size(),
+            template_versions: nai_tokenizers_extism_input::TEMPLATE_VERSIONS,
+            templates: chat_templates::TEMPLATE_NAMES,
+            encodings: &["json", "msgpack"],
+            features: &[
+                "special_tokens",
+                "reasoning",
+                "prefill",
+                "multimodal",
+                "stop_sequences",
+                "plugin_config",
+                "reasoning_retention",
+                "chat_templates",
+                "vocab",
+                "chunking",
+                "truncation",
+                "batching",
+                "bytes",
+                "special_token_policy",
+                "detokenize_options",
+            ],
+            exports: &[
+                "tokenize",
+                "detokenize",
+                "chat_template",
+                "chat_template_tokens",
+                "stop_matcher_new",
+                "stop_matcher_push",
+                "stop_matcher_finish",
+                "stop_matcher_free",
+                "vocab_lookup",
+                "vocab_search",
+                "chunk_text",
+                "truncate",
+                "batch_encode",
+                "tokenize_bytes",
+                "detokenize_bytes",
+                "describe",
+                "v2_tokenize",
+                "v2_detokenize",
+                "v2_chat_template",
+                "v2_chat_template_tokens",
+                "v2_stop_matcher_new",
+                "v2_stop_matcher_push",
+                "v2_stop_matcher_finish",
+                "v2_stop_matcher_free",
+                "v2_vocab_lookup",
+                "v2_vocab_search",
+                "v2_chunk_text",
+                "v2_truncate",
+                "v2_batch_encode",
+                "v2_tokenize_bytes",
+                "v2_detokenize_bytes",
+            ],
+        })
+    }))
+}
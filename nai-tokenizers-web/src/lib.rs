@@ -1,4 +1,12 @@
+use nai_tokenizers::chat_templates::{self, TEMPLATE_NAMES};
+use nai_tokenizers::detokenization::{self, DetokenizeOptions};
+use nai_tokenizers::glm45_template::{Chat, Message, PrefillType, ReasoningEnabled};
 use nai_tokenizers::glm45_tokenizer::{self, SpecialTokens};
+use nai_tokenizers::merge_trace::{self, TraceOptions};
+use nai_tokenizers::pretokenization;
+use nai_tokenizers::truncation::{self, TruncateOptions};
+use nai_tokenizers::vocab::{self, SearchQuery};
+use serde::Deserialize;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen(start)]
@@ -32,7 +40,61 @@ pub fn detokenize(ids: Vec<u32>, include_special_tokens: bool) -> Result<String,
         .map_err(|e| JsValue::from_str(&format!("Detokenization error: {}", e)))
 }
 
-/// Decodes a single token ID to its text representation
+/// Decodes token IDs with `{strict, invalid_bytes, clean_up_tokenization_spaces}`.
+/// Returns `{text, strict, invalid_bytes, clean_up_tokenization_spaces, dropped_ids,
+/// invalid_sequences}`
+#[wasm_bindgen]
+pub fn detokenize_with_options(
+    ids: Vec<u32>,
+    options: JsValue,
+    include_special_tokens: bool,
+) -> Result<JsValue, JsValue> {
+    let options: DetokenizeOptions = if options.is_undefined() || options.is_null() {
+        DetokenizeOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid detokenize options: {}", e)))?
+    };
+    let special_tokens = if include_special_tokens {
+        SpecialTokens::Keep
+    } else {
+        SpecialTokens::Ignore
+    };
+    let detokenized =
+        detokenization::detokenize_with_options(&ids, &special_tokens.into(), &options)
+            .map_err(|e| JsValue::from_str(&format!("Detokenization error: {}", e)))?;
+    serde_wasm_bindgen::to_value(&detokenized).map_err(|e| e.into())
+}
+
+/// Tokenizes a `Uint8Array` that need not be valid UTF-8
+#[wasm_bindgen]
+pub fn tokenize_bytes(bytes: &[u8], include_special_tokens: bool) -> Result<Vec<u32>, JsValue> {
+    let special_tokens = if include_special_tokens {
+        SpecialTokens::Keep
+    } else {
+        SpecialTokens::Ignore
+    };
+
+    glm45_tokenizer::tokenize_bytes(bytes, special_tokens)
+        .map_err(|e| JsValue::from_str(&format!("Tokenization error: {}", e)))
+}
+
+/// Decodes token IDs to the exact bytes they stand for, as a `Uint8Array`. IDs outside
+/// the vocabulary are dropped, as in `detokenize`
+#[wasm_bindgen]
+pub fn detokenize_bytes(ids: Vec<u32>, include_special_tokens: bool) -> Result<Vec<u8>, JsValue> {
+    let special_tokens = if include_special_tokens {
+        SpecialTokens::Keep
+    } else {
+        SpecialTokens::Ignore
+    };
+
+    detokenization::detokenize_bytes(&ids, &special_tokens.into(), false)
+        .map_err(|e| JsValue::from_str(&format!("Detokenization error: {}", e)))
+}
+
+/// Decodes a single token ID to its text representation, or an empty string if it's
+/// outside the vocabulary
 #[wasm_bindgen]
 pub fn decode_token(id: u32, include_special_tokens: bool) -> Result<String, JsValue> {
     let special_tokens = if include_special_tokens {
@@ -49,4 +111,162 @@ pub fn decode_token(id: u32, include_special_tokens: bool) -> Result<String, JsV
 #[wasm_bindgen]
 pub fn vocab_size() -> usize {
     glm45_tokenizer::vocab_size()
-}
\ No newline at end of file
+}
+
+/// Returns `{id, token, bytes, text, added, special}` for a token ID, or `undefined`
+/// if it's outside the vocabulary
+#[wasm_bindgen]
+pub fn token_info(id: u32) -> Result<JsValue, JsValue> {
+    match vocab::token_info(id) {
+        Some(info) => serde_wasm_bindgen::to_value(&info).map_err(|e| e.into()),
+        None => Ok(JsValue::UNDEFINED),
+    }
+}
+
+/// Looks up a token by its vocabulary form (e.g. `Ġhello` for " hello")
+#[wasm_bindgen]
+pub fn token_to_id(token: &str) -> Option<u32> {
+    vocab::token_to_id(token)
+}
+
+/// Looks up the token that decodes to exactly these bytes
+#[wasm_bindgen]
+pub fn bytes_to_id(bytes: &[u8]) -> Option<u32> {
+    vocab::bytes_to_id(bytes)
+}
+
+/// Searches the decoded token texts. Takes `{pattern, regex, ignore_case,
+/// exclude_added, offset, limit}` (all optional) and returns `{total, tokens}`
+#[wasm_bindgen]
+pub fn search_vocab(query: JsValue) -> Result<JsValue, JsValue> {
+    let query: SearchQuery = if query.is_undefined() {
+        SearchQuery::default()
+    } else {
+        serde_wasm_bindgen::from_value(query)
+            .map_err(|e| JsValue::from_str(&format!("Invalid search query: {}", e)))?
+    };
+    let results = vocab::search(&query).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    serde_wasm_bindgen::to_value(&results).map_err(|e| e.into())
+}
+
+/// Explains how text is split before BPE. Returns `{normalized, splits, token_count}`,
+/// where each split is `{text, pretoken, start, end, added, tokens}`
+#[wasm_bindgen]
+pub fn explain_splits(text: &str) -> Result<JsValue, JsValue> {
+    let explanation = pretokenization::explain_splits(text)
+        .map_err(|e| JsValue::from_str(&format!("Tokenization error: {}", e)))?;
+    serde_wasm_bindgen::to_value(&explanation).map_err(|e| e.into())
+}
+
+/// Replays the BPE merges for each split. Takes optional
+/// `{max_alternatives, max_extra_tokens}` and returns `{splits}`, where each split is
+/// `{text, start, end, added, initial, steps, tokens, alternatives}`
+#[wasm_bindgen]
+pub fn merge_trace(text: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    let options: TraceOptions = if options.is_undefined() || options.is_null() {
+        TraceOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid trace options: {}", e)))?
+    };
+    let trace = merge_trace::trace(text, &options)
+        .map_err(|e| JsValue::from_str(&format!("Tokenization error: {}", e)))?;
+    serde_wasm_bindgen::to_value(&trace).map_err(|e| e.into())
+}
+
+/// Cuts text to at most `max_tokens` tokens. Takes `{max_tokens, strategy, ellipsis}`
+/// and returns `{text, tokens, dropped}`
+#[wasm_bindgen]
+pub fn truncate(
+    text: &str,
+    options: JsValue,
+    include_special_tokens: bool,
+) -> Result<JsValue, JsValue> {
+    let options: TruncateOptions = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid truncation options: {}", e)))?;
+    let special_tokens = if include_special_tokens {
+        SpecialTokens::Keep
+    } else {
+        SpecialTokens::Ignore
+    };
+    let truncation = truncation::truncate(text, &options, special_tokens)
+        .map_err(|e| JsValue::from_str(&format!("Truncation error: {}", e)))?;
+    serde_wasm_bindgen::to_value(&truncation).map_err(|e| e.into())
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+    #[serde(default)]
+    reasoning_content: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Prefill {
+    None,
+    Canonical,
+    PartialReasoning {
+        reasoning_content: String,
+    },
+    FullReasoning {
+        reasoning_content: String,
+        content: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct ChatInput {
+    messages: Vec<ChatMessage>,
+    #[serde(default)]
+    reasoning_enabled: bool,
+    #[serde(default)]
+    prefill: Option<Prefill>,
+}
+
+/// Renders `{messages, reasoning_enabled, prefill}` with the named chat template
+/// (see `chat_template_names`)
+#[wasm_bindgen]
+pub fn chat_template(template: &str, input: JsValue) -> Result<String, JsValue> {
+    let template = chat_templates::by_name(template)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown chat template: {}", template)))?;
+    let input: ChatInput = serde_wasm_bindgen::from_value(input)
+        .map_err(|e| JsValue::from_str(&format!("Invalid chat input: {}", e)))?;
+
+    let chat = Chat {
+        messages: input
+            .messages
+            .into_iter()
+            .map(|m| Message::from_role(&m.role, m.content, m.reasoning_content))
+            .collect(),
+    };
+    let prefill = match input.prefill.unwrap_or(Prefill::Canonical) {
+        Prefill::None => PrefillType::None,
+        Prefill::Canonical => PrefillType::Canonical,
+        Prefill::PartialReasoning { reasoning_content } => {
+            PrefillType::PartialReasoning { reasoning_content }
+        }
+        Prefill::FullReasoning {
+            reasoning_content,
+            content,
+        } => PrefillType::FullReasoning {
+            reasoning_content,
+            content,
+        },
+    };
+    let reasoning = if input.reasoning_enabled {
+        ReasoningEnabled::Yes
+    } else {
+        ReasoningEnabled::No
+    };
+    template
+        .render(&chat, prefill, reasoning)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Returns the names accepted by `chat_template`
+#[wasm_bindgen]
+pub fn chat_template_names() -> Vec<String> {
+    TEMPLATE_NAMES.iter().map(|name| name.to_string()).collect()
+}
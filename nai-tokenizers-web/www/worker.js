@@ -1,4 +1,4 @@
-import init, { tokenize, detokenize, decode_token, vocab_size } from './pkg/nai_tokenizers_web.js';
+import init, { tokenize, detokenize, decode_token, vocab_size, search_vocab, explain_splits } from './pkg/nai_tokenizers_web.js';
 
 let initialized = false;
 let initPromise = null;
@@ -70,6 +70,27 @@ self.onmessage = async function(e) {
                 break;
             }
 
+            case 'search_vocab': {
+                const result = search_vocab(data);
+                self.postMessage({
+                    type: 'search_vocab_result',
+                    data: result,
+                    id
+                });
+                break;
+            }
+
+            case 'explain_splits': {
+                const { text } = data;
+                const result = explain_splits(text);
+                self.postMessage({
+                    type: 'explain_splits_result',
+                    data: result,
+                    id
+                });
+                break;
+            }
+
             default:
                 throw new Error(`Unknown message type: ${type}`);
         }
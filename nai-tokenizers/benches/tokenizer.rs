@@ -0,0 +1,73 @@
+//! Startup and hot-path benchmarks: `cargo bench -p nai-tokenizers`.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use nai_tokenizers::glm45_template::{Chat, ContextState, Message, PrefillType, ReasoningEnabled};
+use nai_tokenizers::glm45_tokenizer::{self, GLM45_TOKENIZER, SpecialTokens};
+
+const TOKENIZER_JSON: &[u8] = include_bytes!("../tokenizers/glm-4.5-tokenizer.json.br");
+
+const TEXT: &str = "The quick brown fox jumps over the lazy dog. \
+    Pack my box with five dozen liquor jugs! 1234567890 \u{1f600} \
+    fn main() { println!(\"hello, world\"); }\n\n";
+
+fn conversation() -> Chat {
+    let mut messages = vec![Message::System {
+        content: "You are a helpful assistant.".to_string(),
+    }];
+    for turn in 0..8 {
+        messages.push(Message::User {
+            content: format!("Question {}: {}", turn, TEXT),
+        });
+        messages.push(Message::Assistant {
+            content: format!("Answer {}: {}", turn, TEXT),
+            reasoning_content: Some(format!("Thinking about question {}.", turn)),
+        });
+    }
+    messages.push(Message::User {
+        content: "One more thing.".to_string(),
+    });
+    Chat { messages }
+}
+
+fn bench_load(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load");
+    group.sample_size(10);
+    group.bench_function("json", |b| {
+        b.iter(|| glm45_tokenizer::load_from_json(black_box(TOKENIZER_JSON)).unwrap())
+    });
+    group.bench_function("snapshot", |b| b.iter(|| glm45_tokenizer::load().unwrap()));
+    group.finish();
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let text = TEXT.repeat(64);
+    lazy_static::initialize(&GLM45_TOKENIZER);
+    c.bench_function("encode", |b| {
+        b.iter(|| glm45_tokenizer::tokenize(black_box(&text), SpecialTokens::Ignore).unwrap())
+    });
+}
+
+fn bench_chat_render(c: &mut Criterion) {
+    let chat = conversation();
+    lazy_static::initialize(&GLM45_TOKENIZER);
+    c.bench_function("chat_render", |b| {
+        b.iter(|| {
+            ContextState::new(ReasoningEnabled::Yes)
+                .chat(black_box(&chat), PrefillType::Canonical)
+                .unwrap()
+        })
+    });
+    c.bench_function("chat_render_tokens", |b| {
+        b.iter(|| {
+            let prompt = ContextState::new(ReasoningEnabled::Yes)
+                .chat(black_box(&chat), PrefillType::Canonical)
+                .unwrap();
+            glm45_tokenizer::tokenize(&prompt, SpecialTokens::Keep).unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, bench_load, bench_encode, bench_chat_render);
+criterion_main!(benches);
@@ -1,4 +1,7 @@
 use brotli::enc::BrotliEncoderParams;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
 use std::fs;
 use std::io::Read;
 use std::path::Path;
@@ -22,9 +25,122 @@ fn main() {
         &format!("{}/glm-4.5-tokenizer-config.json.br", tokenizers_dir),
     );
 
+    write_snapshot(
+        &format!("{}/glm-4.5-tokenizer.json.br", tokenizers_dir),
+        &Path::new(&env::var("OUT_DIR").unwrap()).join("glm-4.5-tokenizer.snapshot.br"),
+    );
+
     println!("cargo:rerun-if-changed=build.rs");
 }
 
+/// Pre-serializes a compressed tokenizer.json into the snapshot format read by
+/// `nai_tokenizers::tokenizer_snapshot`, so loading it doesn't go through the JSON parser.
+///
+/// Layout (little-endian, Brotli-compressed as a whole):
+/// - magic `NAITOK\0\0`, `u32` format version
+/// - `u32` length + the tokenizer JSON without `model.vocab` / `model.merges`
+/// - `u32` vocab size, one `u16` byte length per token, then the token strings back to back, in id order
+/// - `u32` merge count, then each merge as a pair of `u32` token ids
+fn write_snapshot(compressed_json: &str, destination: &Path) {
+    println!("cargo:rerun-if-changed={}", compressed_json);
+
+    let mut json_data = Vec::new();
+    brotli::BrotliDecompress(
+        &mut &fs::read(compressed_json)
+            .unwrap_or_else(|_| panic!("Failed to read {}", compressed_json))[..],
+        &mut json_data,
+    )
+    .unwrap_or_else(|_| panic!("Failed to decompress {}", compressed_json));
+    let mut tokenizer: Value = serde_json::from_slice(&json_data)
+        .unwrap_or_else(|_| panic!("Failed to parse {}", compressed_json));
+
+    let model = tokenizer["model"]
+        .as_object_mut()
+        .expect("tokenizer.json has no model");
+    assert_eq!(
+        model["type"], "BPE",
+        "Only BPE tokenizers can be snapshotted"
+    );
+    let vocab: HashMap<String, u32> =
+        serde_json::from_value(model.remove("vocab").expect("BPE model has no vocab"))
+            .expect("Invalid BPE vocab");
+    let merges = model.remove("merges").expect("BPE model has no merges");
+
+    let mut tokens = vec![None; vocab.len()];
+    for (token, &id) in &vocab {
+        let slot = tokens
+            .get_mut(id as usize)
+            .expect("BPE vocab ids must be contiguous");
+        *slot = Some(token.as_str());
+    }
+    let tokens: Vec<&str> = tokens
+        .into_iter()
+        .map(|token| token.expect("BPE vocab ids must be contiguous"))
+        .collect();
+
+    // Merges are either "a b" strings (older files) or [a, b] pairs
+    let merge_ids: Vec<(u32, u32)> = merges
+        .as_array()
+        .expect("Invalid BPE merges")
+        .iter()
+        .map(|merge| {
+            let (left, right) = match merge {
+                Value::String(merge) => merge.split_once(' ').expect("Invalid BPE merge"),
+                Value::Array(pair) if pair.len() == 2 => (
+                    pair[0].as_str().expect("Invalid BPE merge"),
+                    pair[1].as_str().expect("Invalid BPE merge"),
+                ),
+                _ => panic!("Invalid BPE merge: {}", merge),
+            };
+            let id = |token: &str| {
+                *vocab
+                    .get(token)
+                    .expect("BPE merge uses a token not in the vocab")
+            };
+            (id(left), id(right))
+        })
+        .collect();
+
+    // Added token ids are reassigned on load anyway, exactly like `Tokenizer::from_bytes` does
+    for token in tokenizer["added_tokens"]
+        .as_array_mut()
+        .expect("tokenizer.json has no added_tokens")
+    {
+        token.as_object_mut().unwrap().remove("id");
+    }
+    let header = serde_json::to_vec(&tokenizer).unwrap();
+
+    let mut snapshot = Vec::with_capacity(json_data.len() / 2);
+    snapshot.extend_from_slice(b"NAITOK\0\0");
+    snapshot.extend_from_slice(&1u32.to_le_bytes());
+    snapshot.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    snapshot.extend_from_slice(&header);
+    snapshot.extend_from_slice(&(tokens.len() as u32).to_le_bytes());
+    for token in &tokens {
+        let len = u16::try_from(token.len()).expect("BPE token longer than 64KiB");
+        snapshot.extend_from_slice(&len.to_le_bytes());
+    }
+    for token in &tokens {
+        snapshot.extend_from_slice(token.as_bytes());
+    }
+    snapshot.extend_from_slice(&(merge_ids.len() as u32).to_le_bytes());
+    for (left, right) in merge_ids {
+        snapshot.extend_from_slice(&left.to_le_bytes());
+        snapshot.extend_from_slice(&right.to_le_bytes());
+    }
+
+    let mut compressed = Vec::new();
+    let params = BrotliEncoderParams {
+        quality: 9,
+        lgwin: 22,
+        ..Default::default()
+    };
+    brotli::BrotliCompress(&mut &snapshot[..], &mut compressed, &params)
+        .expect("Failed to compress tokenizer snapshot");
+    fs::write(destination, &compressed)
+        .unwrap_or_else(|_| panic!("Failed to write {}", destination.display()));
+}
+
 fn download_and_compress(url: &str, json_destination: &str, compressed_destination: &str) {
     let compressed_path = Path::new(compressed_destination);
 
@@ -0,0 +1,145 @@
+//! Batch encoding for inference: tokenizes several texts into one rectangular batch of
+//! ids, attention masks and position ids, padded with the pad token from GLM-4.5's
+//! tokenizer_config.json.
+//!
+//! The arrays are flat and row-major (`[rows, width]`), so they can be handed to numpy
+//! (`np.frombuffer(...).reshape(rows, width)`) or Go without reshaping element by
+//! element.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::glm45_tokenizer::{self, SpecialTokens};
+pub use crate::tokenizer_config::PaddingSide;
+use crate::tokenizer_config::tokenizer_config;
+
+/// The id of the configured pad token, or of the EOS token if there is no pad token.
+pub fn pad_token_id() -> Result<u32> {
+    let config = tokenizer_config();
+    let token = config
+        .pad_token
+        .as_deref()
+        .or(config.eos_token.as_deref())
+        .ok_or_else(|| anyhow::anyhow!("The tokenizer config has no pad or EOS token"))?;
+    glm45_tokenizer::special_token_id(token)
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BatchOptions {
+    /// Cut rows to this many tokens and pad every row to it. Without it, rows are
+    /// padded to the longest one.
+    pub max_length: Option<usize>,
+    /// Turn the tokens past `max_length` into extra rows instead of dropping them.
+    pub return_overflowing_tokens: bool,
+    /// Tokens repeated from the end of one overflow window at the start of the next.
+    pub stride: usize,
+    /// Defaults to the tokenizer config's `padding_side`.
+    pub padding_side: Option<PaddingSide>,
+    /// Round the width up to a multiple of this.
+    pub pad_to_multiple_of: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Batch {
+    pub rows: usize,
+    pub width: usize,
+    pub pad_token_id: u32,
+    /// `[rows, width]`, row-major.
+    pub input_ids: Vec<u32>,
+    /// `[rows, width]`: 1 for tokens, 0 for padding.
+    pub attention_mask: Vec<u8>,
+    /// `[rows, width]`: each row counts from 0 at its first token. Padding gets 0.
+    pub position_ids: Vec<u32>,
+    /// The input each row came from. Overflow windows share their input's index.
+    pub sample_mapping: Vec<u32>,
+    /// Tokens (excluding padding) in each row.
+    pub lengths: Vec<u32>,
+}
+
+impl Batch {
+    /// The ids of row `index`, padding included.
+    pub fn row(&self, index: usize) -> &[u32] {
+        &self.input_ids[index * self.width..(index + 1) * self.width]
+    }
+}
+
+/// Tokenizes `texts` into one padded batch.
+pub fn encode_batch(
+    texts: &[impl AsRef<str>],
+    options: &BatchOptions,
+    special_tokens: SpecialTokens,
+) -> Result<Batch> {
+    if options.return_overflowing_tokens {
+        let max_length = options
+            .max_length
+            .ok_or_else(|| anyhow::anyhow!("return_overflowing_tokens requires max_length"))?;
+        anyhow::ensure!(
+            options.stride < max_length,
+            "stride must be less than max_length"
+        );
+    }
+    anyhow::ensure!(options.max_length != Some(0), "max_length must be positive");
+    anyhow::ensure!(
+        options.pad_to_multiple_of != Some(0),
+        "pad_to_multiple_of must be positive"
+    );
+
+    let mut rows: Vec<(u32, Vec<u32>)> = Vec::new();
+    for (index, text) in texts.iter().enumerate() {
+        let ids = glm45_tokenizer::tokenize(text.as_ref(), special_tokens)?;
+        let index = index as u32;
+        match options.max_length {
+            Some(max_length) if ids.len() > max_length => {
+                rows.push((index, ids[..max_length].to_vec()));
+                if options.return_overflowing_tokens {
+                    let step = max_length - options.stride;
+                    let mut start = step;
+                    while start + options.stride < ids.len() {
+                        let end = (start + max_length).min(ids.len());
+                        rows.push((index, ids[start..end].to_vec()));
+                        start += step;
+                    }
+                }
+            }
+            _ => rows.push((index, ids)),
+        }
+    }
+
+    let longest = rows.iter().map(|(_, ids)| ids.len()).max().unwrap_or(0);
+    let mut width = options.max_length.unwrap_or(longest);
+    if let Some(multiple) = options.pad_to_multiple_of {
+        width = width.div_ceil(multiple) * multiple;
+    }
+    let padding_side = options
+        .padding_side
+        .unwrap_or(tokenizer_config().padding_side);
+    let pad_token_id = pad_token_id()?;
+
+    let mut batch = Batch {
+        rows: rows.len(),
+        width,
+        pad_token_id,
+        input_ids: vec![pad_token_id; rows.len() * width],
+        attention_mask: vec![0; rows.len() * width],
+        position_ids: vec![0; rows.len() * width],
+        sample_mapping: Vec::with_capacity(rows.len()),
+        lengths: Vec::with_capacity(rows.len()),
+    };
+    for (row, (index, ids)) in rows.iter().enumerate() {
+        let start = row * width
+            + match padding_side {
+                PaddingSide::Left => width - ids.len(),
+                PaddingSide::Right => 0,
+            };
+        let span = start..start + ids.len();
+        batch.input_ids[span.clone()].copy_from_slice(ids);
+        batch.attention_mask[span.clone()].fill(1);
+        for (position, slot) in batch.position_ids[span].iter_mut().enumerate() {
+            *slot = position as u32;
+        }
+        batch.sample_mapping.push(*index);
+        batch.lengths.push(ids.len() as u32);
+    }
+    Ok(batch)
+}
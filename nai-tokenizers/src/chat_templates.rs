@@ -0,0 +1,234 @@
+//! Chat templates for other model families, behind one trait.
+//!
+//! Every template renders the same `Chat`/`PrefillType` types as `glm45_template`.
+//! `PrefillType::Canonical` is the generation prompt and `PrefillType::None` renders
+//! only the history. Non-GLM templates render the text parts of multimodal messages
+//! and drop images and videos.
+
+use std::borrow::Cow;
+
+use crate::glm45_template::{
+    Chat, ContentPart, ContextState, Message, PlaceholderLimitError, PrefillType, ReasoningEnabled,
+    ReasoningRetention, Version, VisionConfig,
+};
+
+/// Names accepted by `by_name`.
+pub const TEMPLATE_NAMES: &[&str] = &["glm-4.5", "glm-4.6", "glm-4.7", "chatml", "llama3", "qwen3"];
+
+pub trait ChatTemplate {
+    /// Renders `chat` followed by `prefill`. `reasoning_enabled` decides what the
+    /// generation prompt looks like for templates that can turn thinking off. Fails if
+    /// the chat's images and videos go over the GLM placeholder limits.
+    fn render(
+        &self,
+        chat: &Chat,
+        prefill: PrefillType,
+        reasoning_enabled: ReasoningEnabled,
+    ) -> Result<String, PlaceholderLimitError>;
+}
+
+/// Looks up a template by name, with default options.
+pub fn by_name(name: &str) -> Option<Box<dyn ChatTemplate + Send + Sync>> {
+    match name {
+        "glm-4.5" | "glm-4.6" => Some(Box::new(Glm::new(Version::GLM456))),
+        "glm-4.7" => Some(Box::new(Glm::new(Version::GLM47))),
+        "chatml" => Some(Box::new(ChatMl)),
+        "llama3" => Some(Box::new(Llama3)),
+        "qwen3" => Some(Box::new(Qwen3)),
+        _ => None,
+    }
+}
+
+/// GLM-4.5/4.6/4.7, rendered by `ContextState`.
+#[derive(Clone, Copy)]
+pub struct Glm {
+    pub version: Version,
+    pub vision: VisionConfig,
+    pub reasoning_retention: ReasoningRetention,
+    pub ignore_message_position: bool,
+}
+
+impl Glm {
+    pub fn new(version: Version) -> Self {
+        Self {
+            version,
+            vision: VisionConfig::default(),
+            reasoning_retention: ReasoningRetention::default(),
+            ignore_message_position: false,
+        }
+    }
+}
+
+impl ChatTemplate for Glm {
+    fn render(
+        &self,
+        chat: &Chat,
+        prefill: PrefillType,
+        reasoning_enabled: ReasoningEnabled,
+    ) -> Result<String, PlaceholderLimitError> {
+        ContextState::new_with_version(reasoning_enabled, self.version)
+            .with_vision_config(self.vision)
+            .with_reasoning_retention(self.reasoning_retention)
+            .chat_with_options(chat, prefill, self.ignore_message_position)
+    }
+}
+
+fn role(message: &Message) -> &'static str {
+    match message {
+        Message::System { .. } => "system",
+        Message::User { .. } | Message::MultimodalUser { .. } => "user",
+        Message::Assistant { .. } => "assistant",
+    }
+}
+
+fn text(message: &Message) -> Cow<'_, str> {
+    match message {
+        Message::System { content }
+        | Message::User { content }
+        | Message::Assistant { content, .. } => Cow::Borrowed(content),
+        Message::MultimodalUser { content } => Cow::Owned(
+            content
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Plain ChatML (`<|im_start|>role\n...<|im_end|>`). It has no reasoning markup, so
+/// `reasoning_content` and the reasoning half of prefills are dropped.
+#[derive(Clone, Copy, Default)]
+pub struct ChatMl;
+
+impl ChatTemplate for ChatMl {
+    fn render(
+        &self,
+        chat: &Chat,
+        prefill: PrefillType,
+        _reasoning_enabled: ReasoningEnabled,
+    ) -> Result<String, PlaceholderLimitError> {
+        let mut out = String::new();
+        for message in &chat.messages {
+            out.push_str("<|im_start|>");
+            out.push_str(role(message));
+            out.push('\n');
+            out.push_str(&text(message));
+            out.push_str("<|im_end|>\n");
+        }
+        match prefill {
+            PrefillType::None => {}
+            PrefillType::Canonical | PrefillType::PartialReasoning { .. } => {
+                out.push_str("<|im_start|>assistant\n");
+            }
+            PrefillType::FullReasoning { content, .. } => {
+                out.push_str("<|im_start|>assistant\n");
+                out.push_str(&content);
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Llama 3 Instruct. Message content is trimmed like in the reference template, and
+/// reasoning is dropped as in `ChatMl`.
+#[derive(Clone, Copy, Default)]
+pub struct Llama3;
+
+impl ChatTemplate for Llama3 {
+    fn render(
+        &self,
+        chat: &Chat,
+        prefill: PrefillType,
+        _reasoning_enabled: ReasoningEnabled,
+    ) -> Result<String, PlaceholderLimitError> {
+        let mut out = String::from("<|begin_of_text|>");
+        for message in &chat.messages {
+            out.push_str("<|start_header_id|>");
+            out.push_str(role(message));
+            out.push_str("<|end_header_id|>\n\n");
+            out.push_str(text(message).trim());
+            out.push_str("<|eot_id|>");
+        }
+        match prefill {
+            PrefillType::None => {}
+            PrefillType::Canonical | PrefillType::PartialReasoning { .. } => {
+                out.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+            }
+            PrefillType::FullReasoning { content, .. } => {
+                out.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+                out.push_str(&content);
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Qwen3. Assistant turns after the last user message keep their reasoning in a
+/// `<think>` block; with reasoning disabled the generation prompt gets an empty one.
+#[derive(Clone, Copy, Default)]
+pub struct Qwen3;
+
+impl ChatTemplate for Qwen3 {
+    fn render(
+        &self,
+        chat: &Chat,
+        prefill: PrefillType,
+        reasoning_enabled: ReasoningEnabled,
+    ) -> Result<String, PlaceholderLimitError> {
+        let last_user = chat
+            .messages
+            .iter()
+            .rposition(|m| matches!(m, Message::User { .. } | Message::MultimodalUser { .. }));
+        let mut out = String::new();
+        for (i, message) in chat.messages.iter().enumerate() {
+            out.push_str("<|im_start|>");
+            out.push_str(role(message));
+            out.push('\n');
+            match message {
+                Message::Assistant {
+                    content,
+                    reasoning_content,
+                } => {
+                    let reasoning = reasoning_content.as_deref().unwrap_or("");
+                    let is_last = i + 1 == chat.messages.len();
+                    if last_user.is_some_and(|u| i > u) && (is_last || !reasoning.is_empty()) {
+                        out.push_str("<think>\n");
+                        out.push_str(reasoning.trim_matches('\n'));
+                        out.push_str("\n</think>\n\n");
+                        out.push_str(content.trim_start_matches('\n'));
+                    } else {
+                        out.push_str(content);
+                    }
+                }
+                _ => out.push_str(&text(message)),
+            }
+            out.push_str("<|im_end|>\n");
+        }
+        match prefill {
+            PrefillType::None => {}
+            PrefillType::Canonical => {
+                out.push_str("<|im_start|>assistant\n");
+                if matches!(reasoning_enabled, ReasoningEnabled::No) {
+                    out.push_str("<think>\n\n</think>\n\n");
+                }
+            }
+            PrefillType::PartialReasoning { reasoning_content } => {
+                out.push_str("<|im_start|>assistant\n<think>\n");
+                out.push_str(&reasoning_content);
+            }
+            PrefillType::FullReasoning {
+                reasoning_content,
+                content,
+            } => {
+                out.push_str("<|im_start|>assistant\n<think>\n");
+                out.push_str(&reasoning_content);
+                out.push_str("\n</think>\n\n");
+                out.push_str(&content);
+            }
+        }
+        Ok(out)
+    }
+}
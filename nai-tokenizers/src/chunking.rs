@@ -0,0 +1,279 @@
+//! Splits text into chunks of at most `max_tokens` GLM-4.5 tokens, for retrieval.
+//!
+//! Each chunk ends at the best boundary in the back half of the longest prefix that
+//! fits: a markdown heading, then a paragraph break, a line break, a sentence end and
+//! a word break, falling back to any character boundary. Consecutive chunks can
+//! overlap by up to `overlap_tokens`. Chunks never split a UTF-8 character, and token
+//! counts are of the chunk text tokenized on its own.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::glm45_tokenizer::{self, SpecialTokens};
+
+#[derive(Clone, Copy)]
+pub struct ChunkOptions {
+    pub max_tokens: usize,
+    /// Tokens repeated from the end of one chunk at the start of the next. Must be
+    /// less than `max_tokens`.
+    pub overlap_tokens: usize,
+    pub special_tokens: SpecialTokens,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self {
+            max_tokens: 512,
+            overlap_tokens: 0,
+            special_tokens: SpecialTokens::Ignore,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Chunk {
+    pub text: String,
+    /// Byte range of the chunk in the whole input.
+    pub start: usize,
+    pub end: usize,
+    pub tokens: usize,
+}
+
+/// Splits a whole document at once.
+pub fn chunk_text(text: &str, options: ChunkOptions) -> Result<Vec<Chunk>> {
+    let mut chunker = Chunker::new(options)?;
+    let mut chunks = chunker.push(text)?;
+    chunks.extend(chunker.finish()?);
+    Ok(chunks)
+}
+
+/// Chunks a document that arrives in pieces. Only the text after the last emitted
+/// chunk (plus the overlap) is kept in memory.
+pub struct Chunker {
+    options: ChunkOptions,
+    buffer: String,
+    /// Byte offset of `buffer` in the whole input.
+    offset: usize,
+    /// Length of the overlap with the last chunk at the start of `buffer`.
+    overlap: usize,
+}
+
+/// Where to end the current chunk and where the next one starts.
+struct Cut {
+    end: usize,
+    tokens: usize,
+    next_start: usize,
+}
+
+impl Chunker {
+    pub fn new(options: ChunkOptions) -> Result<Self> {
+        anyhow::ensure!(options.max_tokens > 0, "max_tokens must be positive");
+        anyhow::ensure!(
+            options.overlap_tokens < options.max_tokens,
+            "overlap_tokens must be less than max_tokens"
+        );
+        Ok(Self {
+            options,
+            buffer: String::new(),
+            offset: 0,
+            overlap: 0,
+        })
+    }
+
+    /// Adds text and returns the chunks that are now complete.
+    pub fn push(&mut self, text: &str) -> Result<Vec<Chunk>> {
+        self.buffer.push_str(text);
+        let mut chunks = Vec::new();
+        while let Some(cut) = self.cut(false)? {
+            chunks.push(self.emit(cut));
+        }
+        Ok(chunks)
+    }
+
+    /// Returns the remaining chunks.
+    pub fn finish(mut self) -> Result<Vec<Chunk>> {
+        let mut chunks = Vec::new();
+        while let Some(cut) = self.cut(true)? {
+            chunks.push(self.emit(cut));
+        }
+        Ok(chunks)
+    }
+
+    fn emit(&mut self, cut: Cut) -> Chunk {
+        let chunk = Chunk {
+            text: self.buffer[..cut.end].to_string(),
+            start: self.offset,
+            end: self.offset + cut.end,
+            tokens: cut.tokens,
+        };
+        self.buffer.drain(..cut.next_start);
+        self.offset += cut.next_start;
+        self.overlap = cut.end - cut.next_start;
+        chunk
+    }
+
+    fn count(&self, text: &str) -> Result<usize> {
+        Ok(glm45_tokenizer::tokenize(text, self.options.special_tokens)?.len())
+    }
+
+    fn cut(&self, is_final: bool) -> Result<Option<Cut>> {
+        let text = self.buffer.as_str();
+        let max_tokens = self.options.max_tokens;
+        // Every token covers at least one byte.
+        if text.is_empty() || (!is_final && text.len() <= max_tokens) {
+            return Ok(None);
+        }
+
+        // Grow a window from the start of the buffer until it holds more than
+        // `max_tokens` tokens, so long buffers aren't tokenized in full.
+        let mut window = floor_char_boundary(text, max_tokens.saturating_mul(4));
+        let window_tokens = loop {
+            let tokens = self.count(&text[..window])?;
+            if tokens > max_tokens || window == text.len() {
+                break tokens;
+            }
+            window = floor_char_boundary(text, window.saturating_mul(2)).max(window + 1);
+            window = ceil_char_boundary(text, window);
+        };
+        if window_tokens <= max_tokens {
+            // The rest fits. It's only final once no more text can arrive.
+            return Ok(is_final.then_some(Cut {
+                end: text.len(),
+                tokens: window_tokens,
+                next_start: text.len(),
+            }));
+        }
+
+        // Candidate ends, all strictly inside the window, so the next character is
+        // always known.
+        let candidates: Vec<usize> = text[..window]
+            .char_indices()
+            .skip(1)
+            .map(|(i, _)| i)
+            .collect();
+        let fits = partition_point(&candidates, |end| {
+            Ok(self.count(&text[..end])? <= max_tokens)
+        })?;
+        // Each chunk has to get past the overlap, even if a single character is over
+        // the limit.
+        let past_overlap = candidates
+            .iter()
+            .copied()
+            .find(|&end| end > self.overlap)
+            .unwrap_or(window);
+        let longest = match fits {
+            0 => past_overlap,
+            n => candidates[n - 1].max(past_overlap),
+        };
+
+        let end = candidates[..fits]
+            .iter()
+            .copied()
+            .filter(|&end| end >= longest / 2 && end > self.overlap)
+            .max_by_key(|&end| (boundary_priority(text, end), end))
+            .unwrap_or(longest);
+        let (end, tokens) = match self.count(&text[..end])? {
+            tokens if tokens <= max_tokens || end == longest => (end, tokens),
+            _ => (longest, self.count(&text[..longest])?),
+        };
+
+        Ok(Some(Cut {
+            end,
+            tokens,
+            next_start: self.overlap_start(text, &candidates, end)?,
+        }))
+    }
+
+    /// The start of the next chunk: the earliest word start before `end` that keeps
+    /// the overlap within `overlap_tokens`, or `end` for no overlap.
+    fn overlap_start(&self, text: &str, candidates: &[usize], end: usize) -> Result<usize> {
+        if self.options.overlap_tokens == 0 {
+            return Ok(end);
+        }
+        let before_end = &candidates[..candidates.partition_point(|&c| c < end)];
+        let first = partition_point(before_end, |start| {
+            Ok(self.count(&text[start..end])? > self.options.overlap_tokens)
+        })?;
+        Ok(before_end[first..]
+            .iter()
+            .copied()
+            .find(|&start| boundary_priority(text, start) >= Boundary::Word)
+            .or_else(|| before_end.get(first).copied())
+            .unwrap_or(end))
+    }
+}
+
+/// What a chunk boundary at a position would split, weakest first.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Boundary {
+    Char,
+    Word,
+    Sentence,
+    Line,
+    Paragraph,
+    Heading,
+}
+
+/// The kind of boundary at `at`, which must be a character boundary strictly inside
+/// `text`. Boundaries sit after whitespace, so chunks start with the next word.
+fn boundary_priority(text: &str, at: usize) -> Boundary {
+    let (before, after) = text.split_at(at);
+    let next = after.chars().next().unwrap_or(' ');
+    let previous = before.chars().next_back().unwrap_or(' ');
+    if next.is_whitespace() || !previous.is_whitespace() {
+        return Boundary::Char;
+    }
+    let newlines = before
+        .chars()
+        .rev()
+        .take_while(|c| c.is_whitespace())
+        .filter(|&c| c == '\n')
+        .count();
+    match newlines {
+        0 => {}
+        _ if next == '#' && previous == '\n' => return Boundary::Heading,
+        1 => return Boundary::Line,
+        _ => return Boundary::Paragraph,
+    }
+    match before.trim_end().chars().next_back() {
+        Some('.' | '!' | '?' | '。' | '！' | '？') => Boundary::Sentence,
+        _ => Boundary::Word,
+    }
+}
+
+/// `slice.partition_point` for a predicate that can fail.
+pub(crate) fn partition_point(
+    slice: &[usize],
+    mut pred: impl FnMut(usize) -> Result<bool>,
+) -> Result<usize> {
+    let (mut low, mut high) = (0, slice.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(slice[mid])? {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+pub(crate) fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    if index >= text.len() {
+        return text.len();
+    }
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+pub(crate) fn ceil_char_boundary(text: &str, mut index: usize) -> usize {
+    if index >= text.len() {
+        return text.len();
+    }
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
@@ -0,0 +1,410 @@
+//! Structured diff between two tokenizers, for checking what a new tokenizer release
+//! changes before switching to it.
+//!
+//! The vocabulary, added tokens and BPE merges are compared directly; an optional
+//! corpus shows how token counts shift on real text.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use tokenizers::Tokenizer;
+
+use crate::merge_trace::merges;
+use crate::stats::{self, InputFormat};
+
+#[derive(Clone, Debug)]
+pub struct CompareOptions {
+    /// Maximum number of entries listed per category; counts always cover everything.
+    pub max_examples: usize,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self { max_examples: 100 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct VocabEntry {
+    pub token: String,
+    pub id: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct IdShift {
+    pub token: String,
+    pub base_id: u32,
+    pub other_id: u32,
+}
+
+/// Differences in the model vocabulary, excluding added tokens.
+#[derive(Clone, Debug, Serialize)]
+pub struct VocabDiff {
+    pub base_size: usize,
+    pub other_size: usize,
+    pub added_count: usize,
+    pub removed_count: usize,
+    pub moved_count: usize,
+    pub added: Vec<VocabEntry>,
+    pub removed: Vec<VocabEntry>,
+    /// Tokens present in both whose id differs.
+    pub moved: Vec<IdShift>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AddedTokenEntry {
+    pub content: String,
+    pub id: u32,
+    pub special: bool,
+}
+
+/// An added token present in both tokenizers with a different id or special flag.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AddedTokenChange {
+    pub content: String,
+    pub base: AddedTokenEntry,
+    pub other: AddedTokenEntry,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AddedTokensDiff {
+    pub added: Vec<AddedTokenEntry>,
+    pub removed: Vec<AddedTokenEntry>,
+    pub changed: Vec<AddedTokenChange>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MergeEntry {
+    pub left: String,
+    pub right: String,
+    pub rank: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RankShift {
+    pub left: String,
+    pub right: String,
+    pub base_rank: usize,
+    pub other_rank: usize,
+}
+
+/// Differences in the BPE merge list. Ranks are positions in the list; a lower rank
+/// is applied first.
+#[derive(Clone, Debug, Serialize)]
+pub struct MergesDiff {
+    pub base_count: usize,
+    pub other_count: usize,
+    /// Rank of the first merge that differs, `None` if the lists are identical.
+    pub first_difference: Option<usize>,
+    pub added_count: usize,
+    pub removed_count: usize,
+    pub reranked_count: usize,
+    pub added: Vec<MergeEntry>,
+    pub removed: Vec<MergeEntry>,
+    pub reranked: Vec<RankShift>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DocumentShift {
+    /// Index of the document among those read (skipped lines don't count).
+    pub document: u64,
+    pub base_tokens: u64,
+    pub other_tokens: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CorpusDiff {
+    pub documents: u64,
+    /// Lines that couldn't be parsed as JSON or lacked the field.
+    pub skipped: u64,
+    pub base_tokens: u64,
+    pub other_tokens: u64,
+    /// `other_tokens / base_tokens`.
+    pub ratio: f64,
+    pub fewer_tokens: u64,
+    pub more_tokens: u64,
+    pub unchanged: u64,
+    /// The documents whose token count changed the most, by absolute difference.
+    pub largest_shifts: Vec<DocumentShift>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ComparisonReport {
+    pub vocab: VocabDiff,
+    pub added_tokens: AddedTokensDiff,
+    pub merges: MergesDiff,
+    pub corpus: Option<CorpusDiff>,
+}
+
+/// Loads a tokenizer.json to compare against.
+pub fn load_tokenizer(path: &Path) -> Result<Tokenizer> {
+    Tokenizer::from_file(path)
+        .map_err(|e| anyhow::anyhow!(e.to_string()))
+        .with_context(|| format!("Failed to load tokenizer from {}", path.display()))
+}
+
+/// Compares the vocabularies, added tokens and merges of `base` and `other`.
+pub fn compare(
+    base: &Tokenizer,
+    other: &Tokenizer,
+    options: &CompareOptions,
+) -> Result<ComparisonReport> {
+    Ok(ComparisonReport {
+        vocab: compare_vocab(base, other, options.max_examples),
+        added_tokens: compare_added_tokens(base, other),
+        merges: compare_merges(&merges(base)?, &merges(other)?, options.max_examples),
+        corpus: None,
+    })
+}
+
+fn compare_vocab(base: &Tokenizer, other: &Tokenizer, max_examples: usize) -> VocabDiff {
+    let base_vocab = base.get_vocab(false);
+    let other_vocab = other.get_vocab(false);
+    let entries = |vocab: &HashMap<String, u32>, missing_from: &HashMap<String, u32>| {
+        let mut entries: Vec<VocabEntry> = vocab
+            .iter()
+            .filter(|(token, _)| !missing_from.contains_key(*token))
+            .map(|(token, &id)| VocabEntry {
+                token: token.clone(),
+                id,
+            })
+            .collect();
+        entries.sort_unstable_by_key(|e| e.id);
+        entries
+    };
+    let added = entries(&other_vocab, &base_vocab);
+    let removed = entries(&base_vocab, &other_vocab);
+    let mut moved: Vec<IdShift> = base_vocab
+        .iter()
+        .filter_map(|(token, &base_id)| {
+            let &other_id = other_vocab.get(token)?;
+            (base_id != other_id).then(|| IdShift {
+                token: token.clone(),
+                base_id,
+                other_id,
+            })
+        })
+        .collect();
+    moved.sort_unstable_by_key(|s| s.base_id);
+
+    VocabDiff {
+        base_size: base_vocab.len(),
+        other_size: other_vocab.len(),
+        added_count: added.len(),
+        removed_count: removed.len(),
+        moved_count: moved.len(),
+        added: added.into_iter().take(max_examples).collect(),
+        removed: removed.into_iter().take(max_examples).collect(),
+        moved: moved.into_iter().take(max_examples).collect(),
+    }
+}
+
+fn added_tokens(tokenizer: &Tokenizer) -> HashMap<String, AddedTokenEntry> {
+    tokenizer
+        .get_added_tokens_decoder()
+        .into_iter()
+        .map(|(id, token)| {
+            (
+                token.content.clone(),
+                AddedTokenEntry {
+                    content: token.content,
+                    id,
+                    special: token.special,
+                },
+            )
+        })
+        .collect()
+}
+
+fn compare_added_tokens(base: &Tokenizer, other: &Tokenizer) -> AddedTokensDiff {
+    let base_tokens = added_tokens(base);
+    let other_tokens = added_tokens(other);
+    let missing = |tokens: &HashMap<String, AddedTokenEntry>,
+                   missing_from: &HashMap<String, AddedTokenEntry>| {
+        let mut entries: Vec<AddedTokenEntry> = tokens
+            .values()
+            .filter(|t| !missing_from.contains_key(&t.content))
+            .cloned()
+            .collect();
+        entries.sort_unstable_by_key(|t| t.id);
+        entries
+    };
+    let mut changed: Vec<AddedTokenChange> = base_tokens
+        .values()
+        .filter_map(|base| {
+            let other = other_tokens.get(&base.content)?;
+            (base != other).then(|| AddedTokenChange {
+                content: base.content.clone(),
+                base: base.clone(),
+                other: other.clone(),
+            })
+        })
+        .collect();
+    changed.sort_unstable_by_key(|c| c.base.id);
+
+    AddedTokensDiff {
+        added: missing(&other_tokens, &base_tokens),
+        removed: missing(&base_tokens, &other_tokens),
+        changed,
+    }
+}
+
+/// Diffs two merge lists given in rank order.
+pub fn compare_merges(
+    base: &[(String, String)],
+    other: &[(String, String)],
+    max_examples: usize,
+) -> MergesDiff {
+    let ranks = |merges: &[(String, String)]| -> HashMap<(String, String), usize> {
+        merges
+            .iter()
+            .enumerate()
+            .map(|(rank, merge)| (merge.clone(), rank))
+            .collect()
+    };
+    let base_ranks = ranks(base);
+    let other_ranks = ranks(other);
+    let missing = |merges: &[(String, String)], missing_from: &HashMap<(String, String), usize>| {
+        merges
+            .iter()
+            .enumerate()
+            .filter(|(_, merge)| !missing_from.contains_key(*merge))
+            .map(|(rank, (left, right))| MergeEntry {
+                left: left.clone(),
+                right: right.clone(),
+                rank,
+            })
+            .collect::<Vec<_>>()
+    };
+    let added = missing(other, &base_ranks);
+    let removed = missing(base, &other_ranks);
+
+    // A merge only counts as reranked if its position relative to the merges both
+    // lists share changed; a plain insertion or removal above it doesn't count.
+    let base_shared: Vec<&(String, String)> = base
+        .iter()
+        .filter(|merge| other_ranks.contains_key(*merge))
+        .collect();
+    let other_shared: HashSet<(&(String, String), usize)> = other
+        .iter()
+        .filter(|merge| base_ranks.contains_key(*merge))
+        .enumerate()
+        .map(|(position, merge)| (merge, position))
+        .collect();
+    let reranked: Vec<RankShift> = base_shared
+        .iter()
+        .enumerate()
+        .filter(|(position, merge)| !other_shared.contains(&(**merge, *position)))
+        .map(|(_, (left, right))| {
+            let merge = (left.clone(), right.clone());
+            RankShift {
+                base_rank: base_ranks[&merge],
+                other_rank: other_ranks[&merge],
+                left: merge.0,
+                right: merge.1,
+            }
+        })
+        .collect();
+
+    let first_difference = base
+        .iter()
+        .zip(other)
+        .position(|(a, b)| a != b)
+        .or_else(|| (base.len() != other.len()).then(|| base.len().min(other.len())));
+
+    MergesDiff {
+        base_count: base.len(),
+        other_count: other.len(),
+        first_difference,
+        added_count: added.len(),
+        removed_count: removed.len(),
+        reranked_count: reranked.len(),
+        added: added.into_iter().take(max_examples).collect(),
+        removed: removed.into_iter().take(max_examples).collect(),
+        reranked: reranked.into_iter().take(max_examples).collect(),
+    }
+}
+
+fn count_tokens(tokenizer: &Tokenizer, text: &str) -> Result<u64> {
+    let encoding = tokenizer
+        .encode(text, true)
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    Ok(encoding.get_ids().len() as u64)
+}
+
+/// Tokenizes every document with both tokenizers and summarizes how the counts
+/// shift, listing the `max_examples` largest changes.
+pub fn compare_corpus(
+    base: &Tokenizer,
+    other: &Tokenizer,
+    inputs: &[PathBuf],
+    format: &InputFormat,
+    max_examples: usize,
+) -> Result<CorpusDiff> {
+    let mut diff = CorpusDiff::default();
+    let mut shifts: Vec<DocumentShift> = Vec::new();
+    stats::for_each_batch(inputs, format, |batch| {
+        let counts = batch
+            .par_iter()
+            .map(|line| {
+                let document = line
+                    .as_deref()
+                    .and_then(|line| stats::extract_document(line, format));
+                match document {
+                    Some(text) => Ok(Some((
+                        count_tokens(base, &text)?,
+                        count_tokens(other, &text)?,
+                    ))),
+                    None => Ok(None),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for counts in counts {
+            let Some((base_tokens, other_tokens)) = counts else {
+                diff.skipped += 1;
+                continue;
+            };
+            diff.base_tokens += base_tokens;
+            diff.other_tokens += other_tokens;
+            match other_tokens.cmp(&base_tokens) {
+                std::cmp::Ordering::Less => diff.fewer_tokens += 1,
+                std::cmp::Ordering::Greater => diff.more_tokens += 1,
+                std::cmp::Ordering::Equal => diff.unchanged += 1,
+            }
+            if base_tokens != other_tokens {
+                shifts.push(DocumentShift {
+                    document: diff.documents,
+                    base_tokens,
+                    other_tokens,
+                });
+            }
+            diff.documents += 1;
+        }
+        // Keep memory bounded on large corpora.
+        if shifts.len() > max_examples.saturating_mul(4).max(1024) {
+            largest_first(&mut shifts, max_examples);
+        }
+        Ok(())
+    })?;
+    largest_first(&mut shifts, max_examples);
+    diff.largest_shifts = shifts;
+    diff.ratio = if diff.base_tokens == 0 {
+        0.0
+    } else {
+        diff.other_tokens as f64 / diff.base_tokens as f64
+    };
+    Ok(diff)
+}
+
+fn largest_first(shifts: &mut Vec<DocumentShift>, keep: usize) {
+    shifts.sort_unstable_by(|a, b| {
+        b.base_tokens
+            .abs_diff(b.other_tokens)
+            .cmp(&a.base_tokens.abs_diff(a.other_tokens))
+            .then(a.document.cmp(&b.document))
+    });
+    shifts.truncate(keep);
+}
@@ -0,0 +1,428 @@
+//! SFT dataset preprocessing: OpenAI-format JSONL chats to packed token shards.
+//!
+//! Each chat is rendered with `ContextState`, tokenized with a loss mask covering the
+//! assistant turns, and packed into fixed-length blocks. Every shard is written as
+//! `shard_NNNNN.tokens`, `.loss_mask` and `.doc_starts` arrays next to a `manifest.json`
+//! that records progress, so an interrupted run picks up at the next shard.
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::fs_util::write_atomic;
+use crate::glm45_template::{Chat, ContextState, Message, MessagePosition, ReasoningEnabled};
+use crate::glm45_tokenizer::{self, SpecialTokens};
+
+const END_OF_TEXT: &str = "<|endoftext|>";
+const ASSISTANT_SENTINEL: &str = "<|assistant|>\n";
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Deserialize)]
+struct JsonlMessage {
+    role: String,
+    /// `null` for assistant turns that only make tool calls.
+    #[serde(default)]
+    content: Option<JsonlContent>,
+    #[serde(default)]
+    reasoning_content: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonlContent {
+    Text(String),
+    Parts(Vec<JsonlContentPart>),
+}
+
+#[derive(Deserialize)]
+struct JsonlContentPart {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+impl JsonlContent {
+    /// The message text. Only `text` parts are kept; other parts (images, audio) are
+    /// dropped.
+    fn into_text(self) -> String {
+        match self {
+            JsonlContent::Text(text) => text,
+            JsonlContent::Parts(parts) => parts
+                .into_iter()
+                .filter(|part| part.kind == "text")
+                .filter_map(|part| part.text)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonlChat {
+    messages: Vec<JsonlMessage>,
+}
+
+/// Parses one `{"messages": [{"role": ..., "content": ...}]}` line. `content` may be a
+/// string, an array of content parts or `null`.
+pub fn parse_chat(line: &str) -> Result<Chat> {
+    let chat: JsonlChat = serde_json::from_str(line)?;
+    Ok(Chat {
+        messages: chat
+            .messages
+            .into_iter()
+            .map(|m| {
+                let content = m.content.map(JsonlContent::into_text).unwrap_or_default();
+                Message::from_role(&m.role, content, m.reasoning_content)
+            })
+            .collect(),
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardFormat {
+    /// NumPy `.npy` arrays.
+    Npy,
+    /// Headerless little-endian arrays (`.u32`, `.u8`, `.u64`).
+    Raw,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreprocessConfig {
+    /// Tokens per packed block.
+    pub seq_len: usize,
+    /// Input documents per shard. A shard is also the unit of resumption.
+    pub docs_per_shard: usize,
+    pub format: ShardFormat,
+    pub reasoning_enabled: bool,
+    /// Render reasoning for every assistant turn instead of none, as with
+    /// `ignore_message_position`.
+    pub keep_all_reasoning: bool,
+    /// Drop the final partial block of each shard instead of padding it.
+    pub drop_last: bool,
+}
+
+impl Default for PreprocessConfig {
+    fn default() -> Self {
+        Self {
+            seq_len: 4096,
+            docs_per_shard: 10_000,
+            format: ShardFormat::Npy,
+            reasoning_enabled: true,
+            keep_all_reasoning: true,
+            drop_last: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreprocessStats {
+    pub documents: u64,
+    /// Lines that couldn't be parsed or tokenized.
+    pub skipped: u64,
+    pub tokens: u64,
+    /// Tokens with a loss mask of 1.
+    pub trainable_tokens: u64,
+    pub padding_tokens: u64,
+    /// Tokens lost to `drop_last`.
+    pub dropped_tokens: u64,
+    pub blocks: u64,
+    pub shards: u64,
+}
+
+pub struct TokenizedDocument {
+    pub tokens: Vec<u32>,
+    /// 1 for tokens the model should be trained on, 0 otherwise.
+    pub loss_mask: Vec<u8>,
+}
+
+/// Renders and tokenizes a chat, masking in the assistant turns. The role sentinel right
+/// after an assistant turn (or `<|endoftext|>` when the chat ends on one) is trained too,
+/// so the model learns where to stop.
+pub fn tokenize_chat(chat: &Chat, config: &PreprocessConfig) -> Result<TokenizedDocument> {
+    let reasoning = if config.reasoning_enabled {
+        ReasoningEnabled::Yes
+    } else {
+        ReasoningEnabled::No
+    };
+    let position = if config.keep_all_reasoning {
+        MessagePosition::Last
+    } else {
+        MessagePosition::Intermediate
+    };
+
+    let mut state = ContextState::new(reasoning);
+    let mut trainable = Vec::new();
+    for message in &chat.messages {
+        let start = state.as_str().len();
+        state = state.message(message, &position);
+        if matches!(message, Message::Assistant { .. }) {
+            trainable.push((start + ASSISTANT_SENTINEL.len(), state.as_str().len()));
+        }
+    }
+    if matches!(chat.messages.last(), Some(Message::Assistant { .. })) {
+        state = state.text(END_OF_TEXT);
+    }
+
+    let (tokens, offsets) =
+        glm45_tokenizer::tokenize_with_offsets(state.as_str(), SpecialTokens::Keep)?;
+
+    // Both lists are sorted, so sweep them together. A range's end is inclusive: the
+    // token starting there is the next turn's sentinel.
+    let mut ranges = trainable.into_iter().peekable();
+    let loss_mask = offsets
+        .iter()
+        .map(|&(start, _)| {
+            while ranges.next_if(|&(_, end)| end < start).is_some() {}
+            matches!(ranges.peek(), Some(&(from, _)) if start >= from) as u8
+        })
+        .collect();
+
+    Ok(TokenizedDocument { tokens, loss_mask })
+}
+
+pub struct PackedShard {
+    /// `blocks * seq_len` tokens, row-major.
+    pub tokens: Vec<u32>,
+    pub loss_mask: Vec<u8>,
+    /// Offset of each document's first token in `tokens`. Documents may span blocks.
+    pub doc_starts: Vec<u64>,
+    pub blocks: usize,
+    pub padding: usize,
+    pub dropped: usize,
+}
+
+/// Concatenates documents and cuts them into `seq_len` blocks.
+pub fn pack(
+    documents: &[TokenizedDocument],
+    seq_len: usize,
+    pad_id: u32,
+    drop_last: bool,
+) -> PackedShard {
+    let total: usize = documents.iter().map(|d| d.tokens.len()).sum();
+    let mut tokens = Vec::with_capacity(total.next_multiple_of(seq_len));
+    let mut loss_mask = Vec::with_capacity(total.next_multiple_of(seq_len));
+    let mut doc_starts = Vec::with_capacity(documents.len());
+    for document in documents {
+        doc_starts.push(tokens.len() as u64);
+        tokens.extend_from_slice(&document.tokens);
+        loss_mask.extend_from_slice(&document.loss_mask);
+    }
+
+    let remainder = tokens.len() % seq_len;
+    let (mut padding, mut dropped) = (0, 0);
+    if remainder != 0 {
+        if drop_last {
+            dropped = remainder;
+            tokens.truncate(tokens.len() - remainder);
+            loss_mask.truncate(tokens.len());
+            doc_starts.retain(|&start| (start as usize) < tokens.len());
+        } else {
+            padding = seq_len - remainder;
+            tokens.resize(tokens.len() + padding, pad_id);
+            loss_mask.resize(tokens.len(), 0);
+        }
+    }
+
+    PackedShard {
+        blocks: tokens.len() / seq_len,
+        tokens,
+        loss_mask,
+        doc_starts,
+        padding,
+        dropped,
+    }
+}
+
+/// Builds a NumPy v1.0 header for a little-endian C-order array.
+pub fn npy_header(descr: &str, shape: &[usize]) -> Vec<u8> {
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut dict = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // Magic, version and length take 10 bytes; the whole header must align to 64.
+    let unpadded = 10 + dict.len() + 1;
+    dict.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    dict.push('\n');
+
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+fn write_array(
+    dir: &Path,
+    stem: &str,
+    format: ShardFormat,
+    (descr, raw_ext): (&str, &str),
+    shape: &[usize],
+    data: Vec<u8>,
+) -> Result<()> {
+    let (path, bytes) = match format {
+        ShardFormat::Npy => {
+            let mut bytes = npy_header(descr, shape);
+            bytes.extend_from_slice(&data);
+            (dir.join(format!("{}.npy", stem)), bytes)
+        }
+        ShardFormat::Raw => (dir.join(format!("{}.{}", stem, raw_ext)), data),
+    };
+    write_atomic(&path, &bytes)
+}
+
+/// Writes `shard_NNNNN.tokens`, `.loss_mask` and `.doc_starts` into `dir`.
+pub fn write_shard(
+    dir: &Path,
+    index: u64,
+    shard: &PackedShard,
+    seq_len: usize,
+    format: ShardFormat,
+) -> Result<()> {
+    let stem = format!("shard_{:05}", index);
+    let shape = [shard.blocks, seq_len];
+    write_array(
+        dir,
+        &format!("{}.tokens", stem),
+        format,
+        ("<u4", "u32"),
+        &shape,
+        shard.tokens.iter().flat_map(|t| t.to_le_bytes()).collect(),
+    )?;
+    write_array(
+        dir,
+        &format!("{}.loss_mask", stem),
+        format,
+        ("|u1", "u8"),
+        &shape,
+        shard.loss_mask.clone(),
+    )?;
+    write_array(
+        dir,
+        &format!("{}.doc_starts", stem),
+        format,
+        ("<u8", "u64"),
+        &[shard.doc_starts.len()],
+        shard
+            .doc_starts
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect(),
+    )
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    config: PreprocessConfig,
+    /// Input lines covered by the shards written so far.
+    lines_consumed: u64,
+    stats: PreprocessStats,
+}
+
+/// Runs the whole pipeline over `inputs`, read in order as one stream of JSONL lines.
+/// If `output` already holds a manifest from the same config, processing resumes after
+/// the last completed shard. `on_shard` is called with the running totals after each
+/// shard.
+pub fn preprocess(
+    inputs: &[PathBuf],
+    output: &Path,
+    config: &PreprocessConfig,
+    mut on_shard: impl FnMut(&PreprocessStats),
+) -> Result<PreprocessStats> {
+    anyhow::ensure!(config.seq_len > 0, "seq_len must be positive");
+    anyhow::ensure!(config.docs_per_shard > 0, "docs_per_shard must be positive");
+    fs::create_dir_all(output).with_context(|| format!("Failed to create {}", output.display()))?;
+
+    let manifest_path = output.join(MANIFEST);
+    let mut manifest = if manifest_path.exists() {
+        let manifest: Manifest = serde_json::from_slice(&fs::read(&manifest_path)?)
+            .with_context(|| format!("Invalid manifest {}", manifest_path.display()))?;
+        anyhow::ensure!(
+            manifest.config == *config,
+            "{} was written with a different config",
+            output.display()
+        );
+        manifest
+    } else {
+        Manifest {
+            config: config.clone(),
+            lines_consumed: 0,
+            stats: PreprocessStats::default(),
+        }
+    };
+
+    let pad_id = glm45_tokenizer::special_token_id(END_OF_TEXT)?;
+    let mut lines = inputs
+        .iter()
+        .map(|path| {
+            let file =
+                File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+            Ok(BufReader::new(file).lines())
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .skip(manifest.lines_consumed as usize);
+
+    loop {
+        // A line that isn't valid UTF-8 is skipped like any other unparseable line.
+        let batch = lines
+            .by_ref()
+            .take(config.docs_per_shard)
+            .map(|line| match line {
+                Ok(line) => Ok(Some(line)),
+                Err(e) if e.kind() == std::io::ErrorKind::InvalidData => Ok(None),
+                Err(e) => Err(e),
+            })
+            .collect::<std::io::Result<Vec<Option<String>>>>()?;
+        if batch.is_empty() {
+            break;
+        }
+
+        let results: Vec<Option<TokenizedDocument>> = batch
+            .par_iter()
+            .filter(|line| line.as_ref().is_none_or(|line| !line.trim().is_empty()))
+            .map(|line| {
+                let line = line.as_ref()?;
+                parse_chat(line)
+                    .and_then(|c| tokenize_chat(&c, config))
+                    .ok()
+            })
+            .collect();
+        let stats = &mut manifest.stats;
+        stats.skipped += results.iter().filter(|r| r.is_none()).count() as u64;
+        let documents: Vec<TokenizedDocument> = results.into_iter().flatten().collect();
+
+        let shard = pack(&documents, config.seq_len, pad_id, config.drop_last);
+        if shard.blocks > 0 {
+            write_shard(output, stats.shards, &shard, config.seq_len, config.format)?;
+            stats.shards += 1;
+        }
+        stats.documents += documents.len() as u64;
+        stats.tokens += documents.iter().map(|d| d.tokens.len() as u64).sum::<u64>();
+        stats.trainable_tokens += shard.loss_mask.iter().map(|&m| m as u64).sum::<u64>();
+        stats.padding_tokens += shard.padding as u64;
+        stats.dropped_tokens += shard.dropped as u64;
+        stats.blocks += shard.blocks as u64;
+
+        manifest.lines_consumed += batch.len() as u64;
+        write_atomic(&manifest_path, &serde_json::to_vec_pretty(&manifest)?)?;
+        on_shard(&manifest.stats);
+    }
+
+    Ok(manifest.stats)
+}
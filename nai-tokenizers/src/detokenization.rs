@@ -0,0 +1,136 @@
+//! Decoding with explicit choices for what `detokenize` leaves to the tokenizers crate:
+//! ids outside the vocabulary, bytes that don't form valid UTF-8 (a sequence cut
+//! mid-character, say), and the space clean-up transformers applies after decoding.
+//!
+//! The result reports the options as applied, so callers on every binding can see
+//! exactly what happened to their ids.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::glm45_tokenizer::{self, SpecialTokens};
+use crate::special_tokens::{self, DecodePolicy};
+use crate::tokenizer_config::tokenizer_config;
+
+/// What to do with bytes that aren't valid UTF-8.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidBytes {
+    /// Replace each invalid sequence with U+FFFD, as `detokenize` does.
+    #[default]
+    Replace,
+    Error,
+    Drop,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DetokenizeOptions {
+    /// Error on ids outside the vocabulary instead of dropping them.
+    pub strict: bool,
+    pub invalid_bytes: InvalidBytes,
+    /// Defaults to the tokenizer config's `clean_up_tokenization_spaces`.
+    pub clean_up_tokenization_spaces: Option<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Detokenized {
+    pub text: String,
+    pub strict: bool,
+    pub invalid_bytes: InvalidBytes,
+    pub clean_up_tokenization_spaces: bool,
+    /// Ids outside the vocabulary that were left out.
+    pub dropped_ids: Vec<u32>,
+    /// Invalid UTF-8 sequences that were replaced or dropped.
+    pub invalid_sequences: usize,
+}
+
+/// transformers' `clean_up_tokenization`: drops the space a word-level tokenizer left
+/// before punctuation and contractions.
+const CLEAN_UP_REPLACEMENTS: &[(&str, &str)] = &[
+    (" .", "."),
+    (" ?", "?"),
+    (" !", "!"),
+    (" ,", ","),
+    (" ' ", "'"),
+    (" n't", "n't"),
+    (" 'm", "'m"),
+    (" 's", "'s"),
+    (" 've", "'ve"),
+    (" 're", "'re"),
+];
+
+pub fn clean_up_tokenization(text: &str) -> String {
+    CLEAN_UP_REPLACEMENTS
+        .iter()
+        .fold(text.to_string(), |text, (from, to)| text.replace(from, to))
+}
+
+/// Splits `ids` into those in the vocabulary and those outside it, or errors on the
+/// first one outside it if `strict`.
+fn split_out_of_range(ids: &[u32], strict: bool) -> Result<(Vec<u32>, Vec<u32>)> {
+    let mut known = Vec::with_capacity(ids.len());
+    let mut dropped_ids = Vec::new();
+    for &id in ids {
+        if glm45_tokenizer::token_bytes(id).is_some() {
+            known.push(id);
+        } else if strict {
+            anyhow::bail!("Token id {} is out of range", id);
+        } else {
+            dropped_ids.push(id);
+        }
+    }
+    Ok((known, dropped_ids))
+}
+
+/// The exact bytes `ids` decode to, skipping the special tokens `policy` skips. Ids
+/// outside the vocabulary are dropped unless `strict`, as in `detokenize_with_options`.
+pub fn detokenize_bytes(ids: &[u32], policy: &DecodePolicy, strict: bool) -> Result<Vec<u8>> {
+    let (known, _) = split_out_of_range(ids, strict)?;
+    let kept = special_tokens::skip_special(&known, policy)?;
+    glm45_tokenizer::detokenize_bytes(&kept, SpecialTokens::Keep)
+}
+
+/// Decodes `ids`, skipping the special tokens `policy` skips, as `options` says.
+pub fn detokenize_with_options(
+    ids: &[u32],
+    policy: &DecodePolicy,
+    options: &DetokenizeOptions,
+) -> Result<Detokenized> {
+    let (known, dropped_ids) = split_out_of_range(ids, options.strict)?;
+    let kept = special_tokens::skip_special(&known, policy)?;
+    let bytes = glm45_tokenizer::detokenize_bytes(&kept, SpecialTokens::Keep)?;
+
+    let mut text = String::with_capacity(bytes.len());
+    let mut invalid_sequences = 0;
+    let mut offset = 0;
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        offset += chunk.valid().len();
+        if chunk.invalid().is_empty() {
+            continue;
+        }
+        invalid_sequences += 1;
+        match options.invalid_bytes {
+            InvalidBytes::Replace => text.push(char::REPLACEMENT_CHARACTER),
+            InvalidBytes::Error => anyhow::bail!("Invalid UTF-8 at byte {}", offset),
+            InvalidBytes::Drop => {}
+        }
+        offset += chunk.invalid().len();
+    }
+
+    let clean_up = options
+        .clean_up_tokenization_spaces
+        .unwrap_or(tokenizer_config().clean_up_tokenization_spaces);
+    if clean_up {
+        text = clean_up_tokenization(&text);
+    }
+    Ok(Detokenized {
+        text,
+        strict: options.strict,
+        invalid_bytes: options.invalid_bytes,
+        clean_up_tokenization_spaces: clean_up,
+        dropped_ids,
+        invalid_sequences,
+    })
+}
@@ -1,13 +1,18 @@
 #[cfg(feature = "glm45_tokenizer")]
 pub mod glm45_tokenizer {
+    use std::collections::HashMap;
+
     use anyhow::Result;
 
-    use tokenizers::Tokenizer;
+    use tokenizers::{Model, Tokenizer};
 
+    /// Loads the bundled tokenizer from its build-time snapshot (see `tokenizer_snapshot`).
     pub fn load() -> Result<Tokenizer> {
-        // Load compressed tokenizer data
-        let compressed_data = include_bytes!("../tokenizers/glm-4.5-tokenizer.json.br");
+        crate::tokenizer_snapshot::load(crate::tokenizer_snapshot::GLM45_SNAPSHOT)
+    }
 
+    /// Loads a tokenizer from Brotli-compressed tokenizer.json bytes.
+    pub fn load_from_json(compressed_data: &[u8]) -> Result<Tokenizer> {
         // Decompress with Brotli
         let mut decompressed_data = Vec::new();
         brotli::BrotliDecompress(&mut &compressed_data[..], &mut decompressed_data)
@@ -21,6 +26,37 @@ pub mod glm45_tokenizer {
 
     lazy_static::lazy_static! {
         pub static ref GLM45_TOKENIZER: Tokenizer = load().expect("Failed to load GLM-4.5 tokenizer");
+        /// Added token ids, mapped to whether they're special.
+        static ref ADDED_TOKENS: HashMap<u32, bool> = GLM45_TOKENIZER
+            .get_added_tokens_decoder()
+            .into_iter()
+            .map(|(id, token)| (id, token.special))
+            .collect();
+        static ref BYTE_LEVEL_DECODER: HashMap<char, u8> = byte_level_alphabet()
+            .into_iter()
+            .map(|(byte, c)| (c, byte))
+            .collect();
+        /// `byte_level_alphabet` indexed by byte.
+        pub(crate) static ref BYTE_LEVEL_ENCODER: Vec<char> = {
+            let mut alphabet = byte_level_alphabet();
+            alphabet.sort_unstable();
+            alphabet.into_iter().map(|(_, c)| c).collect()
+        };
+    }
+
+    /// GPT-2's reversible byte-to-char mapping used by the ByteLevel pre-tokenizer:
+    /// printable bytes map to themselves, the rest to code points from U+0100 up.
+    pub(crate) fn byte_level_alphabet() -> Vec<(u8, char)> {
+        let printable: Vec<u8> = (b'!'..=b'~').chain(0xA1..=0xAC).chain(0xAE..=0xFF).collect();
+        let mut alphabet: Vec<(u8, char)> = printable.iter().map(|&b| (b, b as char)).collect();
+        let mut next = 256;
+        for byte in 0..=255u8 {
+            if !printable.contains(&byte) {
+                alphabet.push((byte, char::from_u32(next).unwrap()));
+                next += 1;
+            }
+        }
+        alphabet
     }
 
     #[derive(Clone, Copy)]
@@ -45,6 +81,19 @@ pub mod glm45_tokenizer {
         Ok(encoding.get_ids().to_vec())
     }
 
+    /// Token ids paired with the byte range of the input each one covers.
+    pub type TokensWithOffsets = (Vec<u32>, Vec<(usize, usize)>);
+
+    pub fn tokenize_with_offsets(
+        input: &str,
+        special_tokens: SpecialTokens,
+    ) -> Result<TokensWithOffsets> {
+        let encoding = GLM45_TOKENIZER
+            .encode(input, special_tokens.into())
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        Ok((encoding.get_ids().to_vec(), encoding.get_offsets().to_vec()))
+    }
+
     pub fn detokenize(ids: &[u32], special_tokens: SpecialTokens) -> Result<String> {
         let special_tokens: bool = special_tokens.into();
         let decoded = GLM45_TOKENIZER
@@ -53,9 +102,134 @@ pub mod glm45_tokenizer {
         Ok(decoded)
     }
 
+    /// Tokenizes bytes that need not be valid UTF-8. Valid runs are tokenized as by
+    /// `tokenize`; each run of invalid bytes is mapped through the ByteLevel alphabet and
+    /// merged by BPE as a split of its own, so `detokenize_bytes` gives the input back.
+    pub fn tokenize_bytes(input: &[u8], special_tokens: SpecialTokens) -> Result<Vec<u32>> {
+        let mut ids = Vec::new();
+        let mut invalid = Vec::new();
+        for chunk in input.utf8_chunks() {
+            if !chunk.valid().is_empty() {
+                ids.extend(tokenize_byte_level(&invalid)?);
+                invalid.clear();
+                ids.extend(tokenize(chunk.valid(), special_tokens)?);
+            }
+            invalid.extend_from_slice(chunk.invalid());
+        }
+        ids.extend(tokenize_byte_level(&invalid)?);
+        Ok(ids)
+    }
+
+    fn tokenize_byte_level(bytes: &[u8]) -> Result<Vec<u32>> {
+        if bytes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pretoken: String = bytes
+            .iter()
+            .map(|&byte| BYTE_LEVEL_ENCODER[byte as usize])
+            .collect();
+        let tokens = GLM45_TOKENIZER
+            .get_model()
+            .tokenize(&pretoken)
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        Ok(tokens.into_iter().map(|token| token.id).collect())
+    }
+
+    /// Decodes ids to the exact bytes they stand for, without replacing invalid UTF-8.
+    /// Errors on ids outside the vocabulary instead of skipping them.
+    pub fn detokenize_bytes(ids: &[u32], special_tokens: SpecialTokens) -> Result<Vec<u8>> {
+        let keep_special: bool = special_tokens.into();
+        let mut bytes = Vec::new();
+        for &id in ids {
+            if !keep_special && is_special_token(id) {
+                continue;
+            }
+            let token = token_bytes(id)
+                .ok_or_else(|| anyhow::anyhow!("Token id {} is out of range", id))?;
+            bytes.extend(token);
+        }
+        Ok(bytes)
+    }
+
     pub fn vocab_size() -> usize {
         GLM45_TOKENIZER.get_vocab_size(true)
     }
+
+    pub fn is_special_token(id: u32) -> bool {
+        ADDED_TOKENS.get(&id).copied().unwrap_or(false)
+    }
+
+    /// Whether `id` is an added token (special or not) rather than a BPE token.
+    pub fn is_added_token(id: u32) -> bool {
+        ADDED_TOKENS.contains_key(&id)
+    }
+
+    /// The raw bytes a token stands for, undoing the ByteLevel mapping. Added tokens
+    /// are their literal content. Returns `None` for ids outside the vocabulary.
+    pub fn token_bytes(id: u32) -> Option<Vec<u8>> {
+        let token = GLM45_TOKENIZER.id_to_token(id)?;
+        if ADDED_TOKENS.contains_key(&id) {
+            return Some(token.into_bytes());
+        }
+        token
+            .chars()
+            .map(|c| BYTE_LEVEL_DECODER.get(&c).copied())
+            .collect()
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum PlaceholderKind {
+        Image,
+        Video,
+    }
+
+    /// A run of `<|image|>` placeholder tokens that the vision encoder output replaces.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct PlaceholderSpan {
+        pub kind: PlaceholderKind,
+        /// Index of the first placeholder token.
+        pub start: usize,
+        /// Number of consecutive placeholder tokens.
+        pub len: usize,
+    }
+
+    pub(crate) fn special_token_id(token: &str) -> Result<u32> {
+        GLM45_TOKENIZER
+            .token_to_id(token)
+            .ok_or_else(|| anyhow::anyhow!("Unknown special token: {}", token))
+    }
+
+    /// Finds every placeholder run in a tokenized prompt. Runs inside
+    /// `<|begin_of_video|>`/`<|end_of_video|>` are reported as video frames.
+    pub fn placeholder_spans(ids: &[u32]) -> Result<Vec<PlaceholderSpan>> {
+        let image = special_token_id("<|image|>")?;
+        let begin_video = special_token_id("<|begin_of_video|>")?;
+        let end_video = special_token_id("<|end_of_video|>")?;
+
+        let mut spans: Vec<PlaceholderSpan> = Vec::new();
+        let mut in_video = false;
+        for (i, &id) in ids.iter().enumerate() {
+            if id == begin_video {
+                in_video = true;
+            } else if id == end_video {
+                in_video = false;
+            } else if id == image {
+                match spans.last_mut() {
+                    Some(span) if span.start + span.len == i => span.len += 1,
+                    _ => spans.push(PlaceholderSpan {
+                        kind: if in_video {
+                            PlaceholderKind::Video
+                        } else {
+                            PlaceholderKind::Image
+                        },
+                        start: i,
+                        len: 1,
+                    }),
+                }
+            }
+        }
+        Ok(spans)
+    }
 }
 
 #[cfg(feature = "glm45_template")]
@@ -69,6 +243,7 @@ pub mod glm45_template {
     }
 
     /// Whether reasoning is enabled for the model, in general.
+    #[derive(Clone, Copy)]
     pub enum ReasoningEnabled {
         No,
         Yes,
@@ -81,11 +256,67 @@ pub mod glm45_template {
 
     /// GLM 4.7 uses "</think>" instead of "<think></think>" when a reasoning is empty.
     /// It also doesn't use /nothink
+    #[derive(Clone, Copy)]
     pub enum Version {
         GLM456,
         GLM47,
     }
 
+    /// Which earlier assistant turns keep their reasoning. The message the prompt
+    /// continues (the last one, unless there's no prefill) always keeps it.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub enum ReasoningRetention {
+        /// Earlier reasoning is replaced by empty think blocks, as in the stock template.
+        #[default]
+        StripPast,
+        /// Assistant turns after the last user message keep their reasoning, so an agent
+        /// loop sees its own thinking since the request it's working on.
+        SinceLastUser,
+        /// Assistant turns answering the last N user messages keep their reasoning.
+        LastTurns(usize),
+        /// Every assistant turn keeps its reasoning.
+        All,
+    }
+
+    impl ReasoningRetention {
+        /// For each message, whether it keeps its reasoning regardless of position.
+        fn retained(self, messages: &[Message]) -> Vec<bool> {
+            let mut users_after = 0;
+            let mut retained = vec![false; messages.len()];
+            for (i, message) in messages.iter().enumerate().rev() {
+                retained[i] = match self {
+                    ReasoningRetention::StripPast => false,
+                    ReasoningRetention::SinceLastUser => users_after == 0,
+                    ReasoningRetention::LastTurns(turns) => users_after < turns,
+                    ReasoningRetention::All => true,
+                };
+                if matches!(message, Message::User { .. } | Message::MultimodalUser { .. }) {
+                    users_after += 1;
+                }
+            }
+            retained
+        }
+    }
+
+    /// A piece of multimodal user content.
+    #[derive(Deserialize)]
+    pub enum ContentPart {
+        Text {
+            text: String,
+        },
+        /// Image dimensions in pixels, after the processor's resize.
+        Image {
+            width: u32,
+            height: u32,
+        },
+        /// Number of sampled frames and their dimensions in pixels.
+        Video {
+            frames: u32,
+            width: u32,
+            height: u32,
+        },
+    }
+
     #[derive(Deserialize)]
     pub enum Message {
         System {
@@ -94,17 +325,133 @@ pub mod glm45_template {
         User {
             content: String,
         },
+        MultimodalUser {
+            content: Vec<ContentPart>,
+        },
         Assistant {
             content: String,
             reasoning_content: Option<String>,
         },
     }
 
+    impl Message {
+        /// Maps an OpenAI-style role to a message. Unknown roles are treated as user turns.
+        pub fn from_role(role: &str, content: String, reasoning_content: Option<String>) -> Self {
+            match role {
+                "system" | "developer" => Message::System { content },
+                "assistant" => Message::Assistant {
+                    content,
+                    reasoning_content,
+                },
+                _ => Message::User { content },
+            }
+        }
+    }
+
+    /// Vision encoder geometry, used to work out how many `<|image|>` placeholders
+    /// an image or video expands to. Defaults match GLM-4.5V.
+    #[derive(Clone, Copy)]
+    pub struct VisionConfig {
+        pub patch_size: u32,
+        pub spatial_merge_size: u32,
+        pub temporal_patch_size: u32,
+    }
+
+    impl Default for VisionConfig {
+        fn default() -> Self {
+            Self {
+                patch_size: 14,
+                spatial_merge_size: 2,
+                temporal_patch_size: 2,
+            }
+        }
+    }
+
+    impl VisionConfig {
+        /// Placeholder tokens for a single image (or a single temporal group of video frames).
+        /// Dimensions are rounded up to a multiple of `patch_size * spatial_merge_size`.
+        pub fn image_placeholders(&self, width: u32, height: u32) -> usize {
+            let factor = self.patch_size.saturating_mul(self.spatial_merge_size).max(1);
+            let grid_w = width.div_ceil(factor).max(1) as usize;
+            let grid_h = height.div_ceil(factor).max(1) as usize;
+            grid_w.saturating_mul(grid_h)
+        }
+
+        /// Number of temporal groups a video is encoded as; each gets its own image block.
+        pub fn video_groups(&self, frames: u32) -> usize {
+            frames.div_ceil(self.temporal_patch_size.max(1)).max(1) as usize
+        }
+    }
+
+    /// Placeholders allowed in one image or video. Past this the part is far larger than
+    /// anything GLM-4.5V's processor produces.
+    pub const MAX_PART_PLACEHOLDERS: usize = 65_536;
+
+    /// Placeholders allowed in one chat, GLM-4.5's 128K context.
+    pub const MAX_CHAT_PLACEHOLDERS: usize = 131_072;
+
+    /// Caps on `<|image|>` placeholders, checked before any are rendered, so a request
+    /// with huge dimensions or frame counts fails instead of allocating them.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct PlaceholderLimits {
+        pub per_part: usize,
+        pub per_chat: usize,
+    }
+
+    impl Default for PlaceholderLimits {
+        fn default() -> Self {
+            Self {
+                per_part: MAX_PART_PLACEHOLDERS,
+                per_chat: MAX_CHAT_PLACEHOLDERS,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum PlaceholderLimitError {
+        /// One image or video needs more placeholders than `per_part`.
+        Part { placeholders: usize, limit: usize },
+        /// The chat's images and videos together need more than `per_chat`.
+        Chat { placeholders: usize, limit: usize },
+    }
+
+    impl std::fmt::Display for PlaceholderLimitError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                PlaceholderLimitError::Part {
+                    placeholders,
+                    limit,
+                } => write!(
+                    f,
+                    "An image or video needs {} placeholders, over the limit of {}",
+                    placeholders, limit
+                ),
+                PlaceholderLimitError::Chat {
+                    placeholders,
+                    limit,
+                } => write!(
+                    f,
+                    "The chat needs {} image placeholders, over the limit of {}",
+                    placeholders, limit
+                ),
+            }
+        }
+    }
+
+    impl std::error::Error for PlaceholderLimitError {}
+
     pub struct ContextState {
         buffer: String,
         reasoning_enabled: ReasoningEnabled,
         remove_reasoning: RemoveReasoning,
         version: Version,
+        vision: VisionConfig,
+        reasoning_retention: ReasoningRetention,
+        placeholder_limits: PlaceholderLimits,
+        placeholders: usize,
+        /// The first part that went over `placeholder_limits`. It and everything after
+        /// it render without placeholders; `finish` reports it.
+        placeholder_error: Option<PlaceholderLimitError>,
     }
 
     pub struct Chat {
@@ -130,6 +477,11 @@ pub mod glm45_template {
                 reasoning_enabled: reasoning_enabled,
                 remove_reasoning: RemoveReasoning::No,
                 version: Version::GLM456,
+                vision: VisionConfig::default(),
+                reasoning_retention: ReasoningRetention::default(),
+                placeholder_limits: PlaceholderLimits::default(),
+                placeholders: 0,
+                placeholder_error: None,
             }
         }
         pub fn new_with_version(reasoning_enabled: ReasoningEnabled, version: Version) -> Self {
@@ -138,8 +490,25 @@ pub mod glm45_template {
                 reasoning_enabled: reasoning_enabled,
                 remove_reasoning: RemoveReasoning::No,
                 version,
+                vision: VisionConfig::default(),
+                reasoning_retention: ReasoningRetention::default(),
+                placeholder_limits: PlaceholderLimits::default(),
+                placeholders: 0,
+                placeholder_error: None,
             }
         }
+        pub fn with_vision_config(mut self, vision: VisionConfig) -> Self {
+            self.vision = vision;
+            self
+        }
+        pub fn with_reasoning_retention(mut self, retention: ReasoningRetention) -> Self {
+            self.reasoning_retention = retention;
+            self
+        }
+        pub fn with_placeholder_limits(mut self, limits: PlaceholderLimits) -> Self {
+            self.placeholder_limits = limits;
+            self
+        }
         pub fn system_sentinel(mut self) -> Self {
             self.buffer.push_str("<|system|>\n");
             self
@@ -181,6 +550,64 @@ pub mod glm45_template {
             self = self.think_end();
             self
         }
+        /// Counts a part's placeholders against the limits, or records why it can't have them.
+        fn reserve_placeholders(&mut self, placeholders: usize) -> bool {
+            if self.placeholder_error.is_some() {
+                return false;
+            }
+            let limits = self.placeholder_limits;
+            let total = self.placeholders.saturating_add(placeholders);
+            if placeholders > limits.per_part {
+                self.placeholder_error = Some(PlaceholderLimitError::Part {
+                    placeholders,
+                    limit: limits.per_part,
+                });
+            } else if total > limits.per_chat {
+                self.placeholder_error = Some(PlaceholderLimitError::Chat {
+                    placeholders: total,
+                    limit: limits.per_chat,
+                });
+            } else {
+                self.placeholders = total;
+                return true;
+            }
+            false
+        }
+        fn image_block(&mut self, placeholders: usize) {
+            self.buffer.push_str("<|begin_of_image|>");
+            self.buffer.push_str(&"<|image|>".repeat(placeholders));
+            self.buffer.push_str("<|end_of_image|>");
+        }
+        pub fn image(mut self, width: u32, height: u32) -> Self {
+            let placeholders = self.vision.image_placeholders(width, height);
+            if self.reserve_placeholders(placeholders) {
+                self.image_block(placeholders);
+            }
+            self
+        }
+        pub fn video(mut self, frames: u32, width: u32, height: u32) -> Self {
+            let groups = self.vision.video_groups(frames);
+            let placeholders = self.vision.image_placeholders(width, height);
+            if self.reserve_placeholders(groups.saturating_mul(placeholders)) {
+                self.buffer.push_str("<|begin_of_video|>");
+                for _ in 0..groups {
+                    self.image_block(placeholders);
+                }
+                self.buffer.push_str("<|end_of_video|>");
+            }
+            self
+        }
+        pub fn content_part(self, part: &ContentPart) -> Self {
+            match part {
+                ContentPart::Text { text } => self.text(text),
+                ContentPart::Image { width, height } => self.image(*width, *height),
+                ContentPart::Video {
+                    frames,
+                    width,
+                    height,
+                } => self.video(*frames, *width, *height),
+            }
+        }
         pub fn remove_reasoning(mut self) -> Self {
             self.remove_reasoning = RemoveReasoning::Yes;
             self
@@ -220,15 +647,18 @@ pub mod glm45_template {
                 Message::User { content } => {
                     self = self.user_sentinel();
                     self = self.text(content);
-
-                    // Check if nothink is already there
-                    if content.ends_with("/nothink") {
-                        self = self.remove_reasoning();
-                    } else if matches!(self.remove_reasoning, RemoveReasoning::Yes)
-                        || matches!(self.reasoning_enabled, ReasoningEnabled::No)
-                    {
-                        self = self.remove_reasoning();
+                    self = self.end_user_turn(content.ends_with("/nothink"));
+                }
+                Message::MultimodalUser { content } => {
+                    self = self.user_sentinel();
+                    for part in content {
+                        self = self.content_part(part);
                     }
+                    let ends_with_nothink = matches!(
+                        content.last(),
+                        Some(ContentPart::Text { text }) if text.ends_with("/nothink")
+                    );
+                    self = self.end_user_turn(ends_with_nothink);
                 }
                 Message::System { content } => {
                     self = self.system_sentinel();
@@ -239,6 +669,18 @@ pub mod glm45_template {
             self
         }
 
+        fn end_user_turn(mut self, ends_with_nothink: bool) -> Self {
+            // Check if nothink is already there
+            if ends_with_nothink {
+                self = self.remove_reasoning();
+            } else if matches!(self.remove_reasoning, RemoveReasoning::Yes)
+                || matches!(self.reasoning_enabled, ReasoningEnabled::No)
+            {
+                self = self.remove_reasoning();
+            }
+            self
+        }
+
         // QoL methods for inline message creation
         pub fn intermediate_system_message(self, content: impl Into<String>) -> Self {
             self.message(
@@ -293,7 +735,11 @@ pub mod glm45_template {
             }
         }
 
-        pub fn chat(self, chat: &Chat, prefill: PrefillType) -> String {
+        pub fn chat(
+            self,
+            chat: &Chat,
+            prefill: PrefillType,
+        ) -> Result<String, PlaceholderLimitError> {
             self.chat_with_options(chat, prefill, false)
         }
 
@@ -302,9 +748,10 @@ pub mod glm45_template {
             chat: &Chat,
             prefill: PrefillType,
             ignore_message_position: bool,
-        ) -> String {
+        ) -> Result<String, PlaceholderLimitError> {
+            let retained = self.reasoning_retention.retained(&chat.messages);
             for (i, message) in chat.messages.iter().enumerate() {
-                let message_position = if ignore_message_position {
+                let message_position = if ignore_message_position || retained[i] {
                     MessagePosition::Last
                 } else if i == chat.messages.len() - 1 {
                     if matches!(prefill, PrefillType::None) {
@@ -332,7 +779,21 @@ pub mod glm45_template {
                     .thinking_content(&reasoning_content)
                     .text(&content),
             }
-            .take()
+            .finish()
+        }
+
+        /// The text rendered so far.
+        pub fn as_str(&self) -> &str {
+            &self.buffer
+        }
+
+        /// The rendered text, or the error if an image or video went over the
+        /// placeholder limits.
+        pub fn finish(self) -> Result<String, PlaceholderLimitError> {
+            match self.placeholder_error {
+                Some(error) => Err(error),
+                None => Ok(self.buffer),
+            }
         }
 
         pub fn take(self) -> String {
@@ -340,3 +801,63 @@ pub mod glm45_template {
         }
     }
 }
+
+#[cfg(all(feature = "glm45_tokenizer", feature = "glm45_template"))]
+pub mod prefix_cache;
+
+#[cfg(feature = "glm45_tokenizer")]
+mod fs_util;
+
+#[cfg(feature = "dataset")]
+pub mod dataset;
+
+#[cfg(feature = "stats")]
+pub mod stats;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod token_healing;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod vocab_trie;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod stop_sequences;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod logit_bias;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod tokenizer_snapshot;
+
+#[cfg(feature = "glm45_template")]
+pub mod chat_templates;
+
+#[cfg(feature = "compare")]
+pub mod compare;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod vocab;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod chunking;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod truncation;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod tokenizer_config;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod batch;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod pretokenization;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod merge_trace;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod special_tokens;
+
+#[cfg(feature = "glm45_tokenizer")]
+pub mod detokenization;
@@ -0,0 +1,278 @@
+//! Turning user-supplied words into token-level logit biases.
+//!
+//! The same word tokenizes differently depending on whether it follows a space and
+//! how it's capitalized (`"cat"`, `" cat"`, `" Cat"` and `"CAT"` are all different
+//! tokens), so banning or boosting a word means biasing every variant. A sampler can
+//! also spell a variant with tokens the tokenizer would never pick (`["c", "at"]` for
+//! `"cat"`), so those split forms are found with a bounded search over the vocabulary.
+//! Variants that don't fit in one token are returned as token sequences, for samplers
+//! that can track multi-token bans.
+
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::Result;
+
+use crate::glm45_tokenizer::{self, SpecialTokens};
+use crate::vocab_trie::VocabTrie;
+
+lazy_static::lazy_static! {
+    static ref VOCAB_TRIE: VocabTrie = VocabTrie::new(false);
+}
+
+/// Caps the split-form search on words with many spellings.
+const MAX_SEARCH_NODES: usize = 10_000;
+
+#[derive(Clone, Copy, Debug)]
+pub struct BiasOptions {
+    /// Also bias the word as it appears after a space.
+    pub leading_space: bool,
+    /// Also bias the lowercase, Capitalized and UPPERCASE forms.
+    pub capitalization: bool,
+    /// Split forms listed per variant. 0 disables the search.
+    pub max_split_forms: usize,
+    /// How many tokens longer than the tokenizer's spelling a split form may be.
+    pub max_extra_tokens: usize,
+}
+
+impl Default for BiasOptions {
+    fn default() -> Self {
+        Self {
+            leading_space: true,
+            capitalization: true,
+            max_split_forms: 8,
+            max_extra_tokens: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BiasVariant {
+    pub text: String,
+    /// The tokenizer's spelling.
+    pub tokens: Vec<u32>,
+    /// Other spellings of the same bytes, fewest tokens first.
+    pub split_forms: Vec<Vec<u32>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BiasEntry {
+    /// The string as supplied.
+    pub text: String,
+    pub variants: Vec<BiasVariant>,
+}
+
+impl BiasEntry {
+    /// Whether at least one variant is a single token.
+    pub fn has_single_token(&self) -> bool {
+        self.variants.iter().any(|v| v.tokens.len() == 1)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogitBias {
+    pub entries: Vec<BiasEntry>,
+    /// Every variant that is a single token, sorted.
+    pub single_tokens: Vec<u32>,
+    /// Every spelling that needs more than one token, split forms included, deduplicated.
+    pub sequences: Vec<Vec<u32>>,
+    /// Inputs with no single-token variant; biasing them needs sequence support.
+    pub multi_token_only: Vec<String>,
+}
+
+impl LogitBias {
+    /// Maps every single-token variant to `bias`.
+    pub fn to_map(&self, bias: f32) -> HashMap<u32, f32> {
+        self.single_tokens.iter().map(|&id| (id, bias)).collect()
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// The spellings of `text` to bias, in a stable order, without duplicates.
+pub fn variants(text: &str, options: BiasOptions) -> Vec<String> {
+    let trimmed = text.trim_start();
+    let mut forms = vec![trimmed.to_string()];
+    if options.capitalization {
+        forms.push(trimmed.to_lowercase());
+        forms.push(capitalize(trimmed));
+        forms.push(trimmed.to_uppercase());
+    }
+
+    let mut variants: Vec<String> = Vec::new();
+    for form in forms {
+        let spaced = format!(" {}", form);
+        let candidates = if options.leading_space {
+            vec![form, spaced]
+        } else {
+            vec![form]
+        };
+        for candidate in candidates {
+            if !candidate.trim().is_empty() && !variants.contains(&candidate) {
+                variants.push(candidate);
+            }
+        }
+    }
+    variants
+}
+
+/// Non-canonical spellings of `text` in regular vocabulary tokens, other than
+/// `canonical`, with at most `options.max_extra_tokens` more tokens than it. Fewest
+/// tokens first.
+pub fn split_forms(text: &str, canonical: &[u32], options: BiasOptions) -> Vec<Vec<u32>> {
+    let bytes = text.as_bytes();
+    if options.max_split_forms == 0 || bytes.is_empty() {
+        return Vec::new();
+    }
+
+    // Tokens starting at each byte offset, as (end, id).
+    let pieces: Vec<Vec<(usize, u32)>> = (0..bytes.len())
+        .map(|start| {
+            VOCAB_TRIE
+                .prefixes_of(&bytes[start..])
+                .into_iter()
+                .filter_map(|id| Some((start + glm45_tokenizer::token_bytes(id)?.len(), id)))
+                .collect()
+        })
+        .collect();
+
+    // Fewest tokens needed to spell bytes[i..], if it can be spelled at all.
+    let mut fewest = vec![None; bytes.len() + 1];
+    fewest[bytes.len()] = Some(0);
+    for start in (0..bytes.len()).rev() {
+        fewest[start] = pieces[start]
+            .iter()
+            .filter_map(|&(end, _)| fewest[end].map(|rest: usize| rest + 1))
+            .min();
+    }
+
+    let mut search = SplitSearch {
+        limit: canonical.len() + options.max_extra_tokens,
+        pieces: &pieces,
+        fewest: &fewest,
+        path: Vec::new(),
+        found: Vec::new(),
+        nodes: 0,
+    };
+    search.visit(0);
+
+    let mut found: Vec<Vec<u32>> = search
+        .found
+        .into_iter()
+        .filter(|spelling| spelling != canonical)
+        .collect();
+    found.sort_by_key(|spelling| spelling.len());
+    found.truncate(options.max_split_forms);
+    found
+}
+
+/// Depth-first search over spellings, longest tokens first, pruned by `fewest`.
+struct SplitSearch<'a> {
+    limit: usize,
+    pieces: &'a [Vec<(usize, u32)>],
+    fewest: &'a [Option<usize>],
+    path: Vec<u32>,
+    found: Vec<Vec<u32>>,
+    nodes: usize,
+}
+
+impl SplitSearch<'_> {
+    fn visit(&mut self, start: usize) {
+        self.nodes += 1;
+        if self.nodes > MAX_SEARCH_NODES {
+            return;
+        }
+        if start == self.pieces.len() {
+            self.found.push(self.path.clone());
+            return;
+        }
+        for &(end, id) in self.pieces[start].iter().rev() {
+            let Some(rest) = self.fewest[end] else {
+                continue;
+            };
+            if self.path.len() + 1 + rest > self.limit {
+                continue;
+            }
+            self.path.push(id);
+            self.visit(end);
+            self.path.pop();
+        }
+    }
+}
+
+/// Expands each string into its variants, their tokenizations and split forms.
+pub fn expand(texts: &[impl AsRef<str>], options: BiasOptions) -> Result<LogitBias> {
+    let mut single_tokens = BTreeSet::new();
+    let mut sequences: Vec<Vec<u32>> = Vec::new();
+    let mut entries = Vec::with_capacity(texts.len());
+    let mut multi_token_only = Vec::new();
+
+    for text in texts {
+        let text = text.as_ref();
+        let variants = variants(text, options)
+            .into_iter()
+            .map(|variant| {
+                let tokens = glm45_tokenizer::tokenize(&variant, SpecialTokens::Keep)?;
+                Ok(BiasVariant {
+                    split_forms: split_forms(&variant, &tokens, options),
+                    text: variant,
+                    tokens,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let spellings = variants
+            .iter()
+            .flat_map(|v| std::iter::once(&v.tokens).chain(&v.split_forms));
+        for tokens in spellings {
+            match tokens.as_slice() {
+                [] => {}
+                [id] => {
+                    single_tokens.insert(*id);
+                }
+                tokens => {
+                    if !sequences.iter().any(|s| s == tokens) {
+                        sequences.push(tokens.to_vec());
+                    }
+                }
+            }
+        }
+
+        let entry = BiasEntry {
+            text: text.to_string(),
+            variants,
+        };
+        if !entry.has_single_token() {
+            multi_token_only.push(entry.text.clone());
+        }
+        entries.push(entry);
+    }
+
+    Ok(LogitBias {
+        entries,
+        single_tokens: single_tokens.into_iter().collect(),
+        sequences,
+        multi_token_only,
+    })
+}
+
+/// Builds a bias map from `(text, bias)` pairs. Where variants of different strings
+/// share a token, the bias of the later string wins.
+pub fn bias_map(
+    biases: &[(impl AsRef<str>, f32)],
+    options: BiasOptions,
+) -> Result<HashMap<u32, f32>> {
+    let mut map = HashMap::new();
+    for (text, bias) in biases {
+        map.extend(expand(&[text.as_ref()], options)?.to_map(*bias));
+    }
+    Ok(map)
+}
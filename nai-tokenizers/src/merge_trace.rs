@@ -0,0 +1,267 @@
+//! Replays BPE on each pre-token split to show why text became the tokens it did: the
+//! starting byte symbols, every merge applied with its rank, the final tokens, and other
+//! ways the same split could have been spelled with about as many tokens.
+//!
+//! BPE repeatedly merges the adjacent pair with the lowest rank (the earliest entry in
+//! the merges table), leftmost first, until no pair has a merge.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokenizers::Tokenizer;
+
+use crate::glm45_tokenizer::{self, GLM45_TOKENIZER};
+use crate::pretokenization::{self, SplitToken};
+
+lazy_static::lazy_static! {
+    static ref MERGE_RANKS: HashMap<(String, String), u32> = merges(&GLM45_TOKENIZER)
+        .expect("Failed to read GLM-4.5 merges")
+        .into_iter()
+        .enumerate()
+        .map(|(rank, pair)| (pair, rank as u32))
+        .collect();
+}
+
+/// The BPE merge list in rank order. The model serializes merges either as
+/// `"left right"` strings or, in newer files, as `[left, right]` pairs.
+pub(crate) fn merges(tokenizer: &Tokenizer) -> Result<Vec<(String, String)>> {
+    let model = serde_json::to_value(tokenizer.get_model())?;
+    let merges = match model.get("merges") {
+        Some(serde_json::Value::Array(merges)) => merges,
+        _ => anyhow::bail!("The tokenizer model isn't BPE"),
+    };
+    merges
+        .iter()
+        .map(|merge| match merge {
+            serde_json::Value::String(merge) => merge
+                .split_once(' ')
+                .map(|(left, right)| (left.to_string(), right.to_string())),
+            serde_json::Value::Array(pair) => match pair.as_slice() {
+                [
+                    serde_json::Value::String(left),
+                    serde_json::Value::String(right),
+                ] => Some((left.clone(), right.clone())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|merge| merge.context("Invalid merge in tokenizer model"))
+        .collect()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TraceOptions {
+    /// Alternative spellings listed per split.
+    pub max_alternatives: usize,
+    /// How many tokens longer than BPE's result an alternative may be.
+    pub max_extra_tokens: usize,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        Self {
+            max_alternatives: 5,
+            max_extra_tokens: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MergeStep {
+    pub rank: u32,
+    pub left: String,
+    pub right: String,
+    /// Index of `left` among the symbols before the merge.
+    pub position: usize,
+    /// The symbols after the merge.
+    pub symbols: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SplitTrace {
+    /// The input text of the split.
+    pub text: String,
+    /// Byte range of the split in the input.
+    pub start: usize,
+    pub end: usize,
+    /// Added tokens are matched whole and never go through BPE.
+    pub added: bool,
+    /// The ByteLevel symbols BPE starts from, one per byte.
+    pub initial: Vec<String>,
+    pub steps: Vec<MergeStep>,
+    pub tokens: Vec<SplitToken>,
+    /// Other spellings of the split in vocabulary tokens, fewest tokens first.
+    pub alternatives: Vec<Vec<SplitToken>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MergeTrace {
+    pub splits: Vec<SplitTrace>,
+}
+
+/// Traces the BPE merges for every split of `text`.
+pub fn trace(text: &str, options: &TraceOptions) -> Result<MergeTrace> {
+    let explanation = pretokenization::explain_splits(text)?;
+    let splits = explanation
+        .splits
+        .into_iter()
+        .map(|split| {
+            if split.added {
+                return SplitTrace {
+                    text: split.text,
+                    start: split.start,
+                    end: split.end,
+                    added: true,
+                    initial: Vec::new(),
+                    steps: Vec::new(),
+                    tokens: split.tokens,
+                    alternatives: Vec::new(),
+                };
+            }
+            let (initial, steps) = replay(&split.pretoken);
+            let final_symbols = steps.last().map_or(&initial, |step| &step.symbols);
+            let alternatives = alternatives(&split.pretoken, final_symbols, options);
+            SplitTrace {
+                text: split.text,
+                start: split.start,
+                end: split.end,
+                added: false,
+                initial,
+                steps,
+                tokens: split.tokens,
+                alternatives,
+            }
+        })
+        .collect();
+    Ok(MergeTrace { splits })
+}
+
+/// Runs BPE on one pre-token, recording each merge.
+fn replay(pretoken: &str) -> (Vec<String>, Vec<MergeStep>) {
+    let initial: Vec<String> = pretoken.chars().map(String::from).collect();
+    let mut symbols = initial.clone();
+    let mut steps = Vec::new();
+    loop {
+        let best = symbols
+            .windows(2)
+            .enumerate()
+            .filter_map(|(position, pair)| {
+                MERGE_RANKS
+                    .get(&(pair[0].clone(), pair[1].clone()))
+                    .map(|&rank| (rank, position))
+            })
+            .min();
+        let Some((rank, position)) = best else {
+            break;
+        };
+        let right = symbols.remove(position + 1);
+        let left = symbols[position].clone();
+        symbols[position].push_str(&right);
+        steps.push(MergeStep {
+            rank,
+            left,
+            right,
+            position,
+            symbols: symbols.clone(),
+        });
+    }
+    (initial, steps)
+}
+
+/// Pre-tokens longer than this (in bytes) get no alternatives.
+const MAX_ALTERNATIVE_SYMBOLS: usize = 64;
+/// Caps the search on words with many spellings.
+const MAX_SEARCH_NODES: usize = 10_000;
+
+/// Spellings of `pretoken` in regular vocabulary tokens, other than `chosen`, with at
+/// most `options.max_extra_tokens` more tokens than it.
+fn alternatives(pretoken: &str, chosen: &[String], options: &TraceOptions) -> Vec<Vec<SplitToken>> {
+    let symbols: Vec<char> = pretoken.chars().collect();
+    if options.max_alternatives == 0 || symbols.len() > MAX_ALTERNATIVE_SYMBOLS {
+        return Vec::new();
+    }
+    let piece_id = |start: usize, end: usize| {
+        let piece: String = symbols[start..end].iter().collect();
+        GLM45_TOKENIZER
+            .token_to_id(&piece)
+            .filter(|&id| !glm45_tokenizer::is_added_token(id))
+            .map(|id| (id, piece))
+    };
+
+    // Fewest tokens needed to spell symbols[i..], if it can be spelled at all.
+    let mut fewest = vec![None; symbols.len() + 1];
+    fewest[symbols.len()] = Some(0);
+    for start in (0..symbols.len()).rev() {
+        fewest[start] = (start + 1..=symbols.len())
+            .filter(|&end| piece_id(start, end).is_some())
+            .filter_map(|end| fewest[end].map(|rest: usize| rest + 1))
+            .min();
+    }
+
+    let mut search = Search {
+        limit: chosen.len() + options.max_extra_tokens,
+        fewest: &fewest,
+        piece_id: &piece_id,
+        len: symbols.len(),
+        path: Vec::new(),
+        found: Vec::new(),
+        nodes: 0,
+    };
+    search.visit(0);
+
+    let mut found: Vec<Vec<(u32, String)>> = search
+        .found
+        .into_iter()
+        .filter(|spelling| !spelling.iter().map(|(_, piece)| piece).eq(chosen))
+        .collect();
+    found.sort_by_key(|spelling| spelling.len());
+    found
+        .into_iter()
+        .take(options.max_alternatives)
+        .map(|spelling| {
+            spelling
+                .into_iter()
+                .map(|(id, token)| pretokenization::split_token(id, token))
+                .collect()
+        })
+        .collect()
+}
+
+/// Depth-first search over spellings, longest pieces first, pruned by `fewest`.
+struct Search<'a, F> {
+    limit: usize,
+    fewest: &'a [Option<usize>],
+    piece_id: &'a F,
+    len: usize,
+    path: Vec<(u32, String)>,
+    found: Vec<Vec<(u32, String)>>,
+    nodes: usize,
+}
+
+impl<F: Fn(usize, usize) -> Option<(u32, String)>> Search<'_, F> {
+    fn visit(&mut self, start: usize) {
+        self.nodes += 1;
+        if self.nodes > MAX_SEARCH_NODES {
+            return;
+        }
+        if start == self.len {
+            self.found.push(self.path.clone());
+            return;
+        }
+        for end in (start + 1..=self.len).rev() {
+            let Some(rest) = self.fewest[end] else {
+                continue;
+            };
+            if self.path.len() + 1 + rest > self.limit {
+                continue;
+            }
+            if let Some(piece) = (self.piece_id)(start, end) {
+                self.path.push(piece);
+                self.visit(end);
+                self.path.pop();
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Prefix-cache aware rendering.
+//!
+//! A serving layer that keeps the KV cache between turns needs to know how much of the
+//! new prompt it has already seen. `ContextState::chat` strips reasoning from assistant
+//! turns once they stop being the last message, so appending a turn can rewrite earlier
+//! text; `HistoryRendering::Stable` avoids that at the cost of keeping past reasoning.
+
+use anyhow::Result;
+
+use crate::glm45_template::{
+    Chat, ContextState, PlaceholderLimitError, PrefillType, ReasoningEnabled, Version,
+};
+use crate::glm45_tokenizer::{SpecialTokens, tokenize};
+
+#[derive(Clone, Copy)]
+pub enum HistoryRendering {
+    /// Same output as `ContextState::chat`: only the last assistant turn keeps its reasoning.
+    Canonical,
+    /// Every message renders the same way no matter what follows it, so a prompt only
+    /// ever grows when turns are appended.
+    Stable,
+}
+
+#[derive(Clone, Copy)]
+pub struct RenderOptions {
+    pub reasoning_enabled: ReasoningEnabled,
+    pub version: Version,
+    pub history: HistoryRendering,
+}
+
+impl RenderOptions {
+    pub fn new(reasoning_enabled: ReasoningEnabled) -> Self {
+        Self {
+            reasoning_enabled,
+            version: Version::GLM456,
+            history: HistoryRendering::Stable,
+        }
+    }
+
+    pub fn render(
+        &self,
+        chat: &Chat,
+        prefill: PrefillType,
+    ) -> Result<String, PlaceholderLimitError> {
+        // Rendering every message as if it were last keeps its reasoning in place, which
+        // is what makes the output independent of later turns.
+        let ignore_message_position = matches!(self.history, HistoryRendering::Stable);
+        ContextState::new_with_version(self.reasoning_enabled, self.version).chat_with_options(
+            chat,
+            prefill,
+            ignore_message_position,
+        )
+    }
+
+    pub fn render_tokens(&self, chat: &Chat, prefill: PrefillType) -> Result<Vec<u32>> {
+        tokenize(&self.render(chat, prefill)?, SpecialTokens::Keep)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrefixDelta {
+    /// Length of the previous prompt in tokens.
+    pub previous_len: usize,
+    /// Number of leading tokens both prompts share. Cache entries past this point are stale.
+    pub shared_len: usize,
+    /// Tokens of the new prompt after the shared prefix.
+    pub delta: Vec<u32>,
+}
+
+impl PrefixDelta {
+    /// Whether the whole previous prompt can be reused and only `delta` needs prefilling.
+    pub fn is_append_only(&self) -> bool {
+        self.shared_len == self.previous_len
+    }
+}
+
+pub fn prefix_delta(previous: &[u32], next: &[u32]) -> PrefixDelta {
+    let shared_len = previous
+        .iter()
+        .zip(next)
+        .take_while(|(a, b)| a == b)
+        .count();
+    PrefixDelta {
+        previous_len: previous.len(),
+        shared_len,
+        delta: next[shared_len..].to_vec(),
+    }
+}
+
+/// Renders both chats with the same options and compares them token by token.
+pub fn chat_delta(
+    options: &RenderOptions,
+    previous: &Chat,
+    previous_prefill: PrefillType,
+    next: &Chat,
+    next_prefill: PrefillType,
+) -> Result<PrefixDelta> {
+    let previous = options.render_tokens(previous, previous_prefill)?;
+    let next = options.render_tokens(next, next_prefill)?;
+    Ok(prefix_delta(&previous, &next))
+}
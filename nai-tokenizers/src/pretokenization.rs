@@ -0,0 +1,106 @@
+//! Shows how GLM-4.5 gets from text to tokens: the normalized text, the splits the
+//! pre-tokenizer regex makes before BPE runs, and the tokens each split becomes.
+//!
+//! Runs the same stages as `Tokenizer::encode`: added tokens are split out first, the
+//! rest is normalized and pre-tokenized, and BPE runs on each split on its own. A token
+//! never crosses a split, so an unexpected count usually shows up as a surprising split.
+
+use anyhow::Result;
+use serde::Serialize;
+use tokenizers::{
+    Model, NormalizedString, Normalizer, OffsetReferential, OffsetType, PreTokenizer,
+};
+
+use crate::glm45_tokenizer::{self, GLM45_TOKENIZER};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SplitToken {
+    pub id: u32,
+    /// The token as stored in the vocabulary.
+    pub token: String,
+    /// The decoded bytes as UTF-8; a token holding part of a character gets U+FFFD.
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Split {
+    /// The input text of the split.
+    pub text: String,
+    /// What BPE sees: the normalized split, ByteLevel-mapped (`Ġhello` for " hello").
+    pub pretoken: String,
+    /// Byte range of the split in the input.
+    pub start: usize,
+    pub end: usize,
+    /// Whether the split is an added token, matched before pre-tokenization.
+    pub added: bool,
+    pub tokens: Vec<SplitToken>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Explanation {
+    /// The input after the normalizer, which is the input itself if there is none.
+    pub normalized: String,
+    pub splits: Vec<Split>,
+    /// Tokens over all splits, as `tokenize` would return.
+    pub token_count: usize,
+}
+
+pub(crate) fn split_token(id: u32, token: String) -> SplitToken {
+    SplitToken {
+        id,
+        token,
+        text: glm45_tokenizer::token_bytes(id)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .unwrap_or_default(),
+    }
+}
+
+/// Breaks down how `text` is tokenized, split by split.
+pub fn explain_splits(text: &str) -> Result<Explanation> {
+    let tokenizer = &*GLM45_TOKENIZER;
+    let to_anyhow = |e: tokenizers::Error| anyhow::anyhow!(e.to_string());
+
+    let mut normalized = NormalizedString::from(text);
+    if let Some(normalizer) = tokenizer.get_normalizer() {
+        normalizer.normalize(&mut normalized).map_err(to_anyhow)?;
+    }
+
+    let mut pretokenized = tokenizer
+        .get_added_vocabulary()
+        .extract_and_normalize(tokenizer.get_normalizer(), text);
+    if let Some(pre_tokenizer) = tokenizer.get_pre_tokenizer() {
+        pre_tokenizer
+            .pre_tokenize(&mut pretokenized)
+            .map_err(to_anyhow)?;
+    }
+    let model = tokenizer.get_model();
+    pretokenized
+        .tokenize(|split| model.tokenize(split.get()))
+        .map_err(to_anyhow)?;
+
+    let splits: Vec<Split> = pretokenized
+        .get_splits(OffsetReferential::Original, OffsetType::Byte)
+        .into_iter()
+        .map(|(pretoken, (start, end), tokens)| {
+            let tokens: Vec<SplitToken> = tokens
+                .iter()
+                .flatten()
+                .map(|token| split_token(token.id, token.value.clone()))
+                .collect();
+            Split {
+                text: text[start..end].to_string(),
+                pretoken: pretoken.to_string(),
+                start,
+                end,
+                added: matches!(tokens.as_slice(), [token] if glm45_tokenizer::is_added_token(token.id)),
+                tokens,
+            }
+        })
+        .collect();
+
+    Ok(Explanation {
+        normalized: normalized.get().to_string(),
+        token_count: splits.iter().map(|split| split.tokens.len()).sum(),
+        splits,
+    })
+}
@@ -0,0 +1,238 @@
+//! tiktoken-style control over special tokens, beyond `SpecialTokens::Keep`/`Ignore`.
+//!
+//! On encode, each special token found in the text is either parsed as its id
+//! (`allowed`), rejected with an error (`disallowed`), or encoded as the literal text it
+//! spells. On decode, a set of special tokens can be skipped while the rest are kept.
+//!
+//! `tokenize` always parses special tokens out of the text; `EncodePolicy::allow_all`
+//! does the same here. The default policy follows tiktoken instead: nothing is allowed
+//! and everything is disallowed, so untrusted text can't inject role sentinels.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Deserialize;
+use tokenizers::Tokenizer;
+
+use crate::glm45_tokenizer::{self, GLM45_TOKENIZER, SpecialTokens};
+
+lazy_static::lazy_static! {
+    /// The special tokens by content, longest first so a token never loses to its prefix.
+    static ref SPECIAL_TOKENS: Vec<String> = {
+        let mut tokens: Vec<String> = GLM45_TOKENIZER
+            .get_added_tokens_decoder()
+            .into_values()
+            .filter(|token| token.special)
+            .map(|token| token.content)
+            .collect();
+        tokens.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        tokens
+    };
+    static ref SPECIAL_TOKEN_PATTERN: Regex = Regex::new(
+        &SPECIAL_TOKENS
+            .iter()
+            .map(|token| regex::escape(token))
+            .collect::<Vec<_>>()
+            .join("|"),
+    )
+    .expect("Failed to build special token pattern");
+    /// Encodes special tokens as the text they spell instead of parsing them.
+    static ref LITERAL_TOKENIZER: Tokenizer = {
+        let mut tokenizer = GLM45_TOKENIZER.clone();
+        tokenizer.set_encode_special_tokens(true);
+        tokenizer
+    };
+}
+
+/// A set of special tokens by content: `"all"`, or a list such as `["<|user|>"]`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "TokenSetRepr")]
+pub enum TokenSet {
+    All,
+    Only(BTreeSet<String>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenSetRepr {
+    Keyword(String),
+    Tokens(Vec<String>),
+}
+
+impl TryFrom<TokenSetRepr> for TokenSet {
+    type Error = String;
+
+    fn try_from(repr: TokenSetRepr) -> Result<Self, String> {
+        match repr {
+            TokenSetRepr::Keyword(keyword) if keyword == "all" => Ok(TokenSet::All),
+            TokenSetRepr::Keyword(keyword) => Err(format!(
+                "Expected \"all\" or a list of special tokens, got {:?}",
+                keyword
+            )),
+            TokenSetRepr::Tokens(tokens) => Ok(TokenSet::Only(tokens.into_iter().collect())),
+        }
+    }
+}
+
+impl Default for TokenSet {
+    fn default() -> Self {
+        TokenSet::Only(BTreeSet::new())
+    }
+}
+
+impl TokenSet {
+    pub fn only<S: Into<String>>(tokens: impl IntoIterator<Item = S>) -> Self {
+        TokenSet::Only(tokens.into_iter().map(Into::into).collect())
+    }
+
+    pub fn contains(&self, token: &str) -> bool {
+        match self {
+            TokenSet::All => true,
+            TokenSet::Only(tokens) => tokens.contains(token),
+        }
+    }
+
+    /// Errors on entries that aren't special tokens, which are most likely typos.
+    fn validate(&self) -> Result<()> {
+        if let TokenSet::Only(tokens) = self {
+            for token in tokens {
+                anyhow::ensure!(
+                    SPECIAL_TOKENS.contains(token),
+                    "Unknown special token: {:?}",
+                    token
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct EncodePolicy {
+    /// Special tokens parsed as such when they appear in the text.
+    pub allowed: TokenSet,
+    /// Special tokens that are an error if they appear in the text. Tokens in neither
+    /// set are encoded as literal text.
+    pub disallowed: TokenSet,
+}
+
+impl Default for EncodePolicy {
+    fn default() -> Self {
+        Self {
+            allowed: TokenSet::default(),
+            disallowed: TokenSet::All,
+        }
+    }
+}
+
+impl EncodePolicy {
+    /// Parses every special token, as `tokenize` does.
+    pub fn allow_all() -> Self {
+        Self {
+            allowed: TokenSet::All,
+            disallowed: TokenSet::default(),
+        }
+    }
+
+    /// Encodes every special token as literal text.
+    pub fn literal() -> Self {
+        Self {
+            allowed: TokenSet::default(),
+            disallowed: TokenSet::default(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.allowed.validate()?;
+        self.disallowed.validate()?;
+        let overlap = match (&self.allowed, &self.disallowed) {
+            (TokenSet::Only(allowed), TokenSet::Only(disallowed)) => {
+                allowed.intersection(disallowed).next()
+            }
+            _ => None,
+        };
+        if let Some(token) = overlap {
+            anyhow::bail!("{:?} is both allowed and disallowed", token);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DecodePolicy {
+    /// Special tokens left out of the decoded text.
+    pub skip: TokenSet,
+}
+
+impl From<SpecialTokens> for DecodePolicy {
+    fn from(special_tokens: SpecialTokens) -> Self {
+        match special_tokens {
+            SpecialTokens::Keep => Self::default(),
+            SpecialTokens::Ignore => Self {
+                skip: TokenSet::All,
+            },
+        }
+    }
+}
+
+fn tokenize_literal(text: &str) -> Result<Vec<u32>> {
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+    let encoding = LITERAL_TOKENIZER
+        .encode(text, false)
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+    Ok(encoding.get_ids().to_vec())
+}
+
+/// Tokenizes `text`, treating the special tokens in it as `policy` says. Allowed tokens
+/// win over `disallowed: "all"`.
+pub fn tokenize_with_policy(text: &str, policy: &EncodePolicy) -> Result<Vec<u32>> {
+    policy.validate()?;
+    let mut ids = Vec::new();
+    // Start of the text not yet tokenized, which may hold literal special tokens.
+    let mut pending = 0;
+    for found in SPECIAL_TOKEN_PATTERN.find_iter(text) {
+        let token = found.as_str();
+        if policy.allowed.contains(token) {
+            ids.extend(tokenize_literal(&text[pending..found.start()])?);
+            ids.push(glm45_tokenizer::special_token_id(token)?);
+            pending = found.end();
+        } else if policy.disallowed.contains(token) {
+            anyhow::bail!(
+                "Disallowed special token {:?} at byte {}",
+                token,
+                found.start()
+            );
+        }
+    }
+    ids.extend(tokenize_literal(&text[pending..])?);
+    Ok(ids)
+}
+
+/// `ids` without the special tokens `policy` skips, for decoders other than
+/// `detokenize_with_policy` (such as `detokenize_bytes`).
+pub fn skip_special(ids: &[u32], policy: &DecodePolicy) -> Result<Vec<u32>> {
+    policy.skip.validate()?;
+    Ok(ids
+        .iter()
+        .copied()
+        .filter(|&id| {
+            !glm45_tokenizer::is_special_token(id)
+                || GLM45_TOKENIZER
+                    .id_to_token(id)
+                    .is_none_or(|token| !policy.skip.contains(&token))
+        })
+        .collect())
+}
+
+/// Decodes `ids`, leaving out the special tokens `policy` skips.
+pub fn detokenize_with_policy(ids: &[u32], policy: &DecodePolicy) -> Result<String> {
+    let kept = skip_special(ids, policy)?;
+    GLM45_TOKENIZER
+        .decode(&kept, false)
+        .map_err(|e| anyhow::anyhow!(e.to_string()))
+}
@@ -0,0 +1,279 @@
+//! Token statistics over large corpora, for estimating cost and context usage.
+//!
+//! Documents are streamed in batches and tokenized in parallel; only per-document
+//! lengths and per-token counts are kept in memory.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::glm45_tokenizer::{self, SpecialTokens};
+
+const BATCH_SIZE: usize = 1024;
+
+/// How documents are read from each input file.
+#[derive(Clone, Debug)]
+pub enum InputFormat {
+    /// Each file is one document.
+    Text,
+    /// Each line is one document.
+    Lines,
+    /// Each line is a JSON object; the document is the string in `field`.
+    Jsonl { field: String },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LengthSummary {
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+/// Documents whose token count falls in `min_tokens..=max_tokens`.
+#[derive(Clone, Debug, Serialize)]
+pub struct HistogramBucket {
+    pub min_tokens: u64,
+    pub max_tokens: u64,
+    pub documents: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TokenCount {
+    pub id: u32,
+    pub text: String,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CorpusStats {
+    pub documents: u64,
+    /// Lines that couldn't be parsed as JSON or lacked the field.
+    pub skipped: u64,
+    pub tokens: u64,
+    pub chars: u64,
+    pub bytes: u64,
+    pub chars_per_token: f64,
+    pub bytes_per_token: f64,
+    pub tokens_per_document: Option<LengthSummary>,
+    /// Power-of-two buckets; empty buckets are left out.
+    pub length_histogram: Vec<HistogramBucket>,
+    pub top_tokens: Vec<TokenCount>,
+    /// Occurrences of special tokens written literally in the text.
+    pub special_tokens: Vec<TokenCount>,
+}
+
+/// Running totals. Accumulators from separate threads can be merged.
+#[derive(Default)]
+pub struct StatsAccumulator {
+    documents: u64,
+    skipped: u64,
+    chars: u64,
+    bytes: u64,
+    lengths: Vec<u64>,
+    counts: HashMap<u32, u64>,
+}
+
+impl StatsAccumulator {
+    pub fn add_document(&mut self, text: &str) -> Result<()> {
+        let ids = glm45_tokenizer::tokenize(text, SpecialTokens::Keep)?;
+        self.documents += 1;
+        self.chars += text.chars().count() as u64;
+        self.bytes += text.len() as u64;
+        self.lengths.push(ids.len() as u64);
+        for id in ids {
+            *self.counts.entry(id).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    pub fn add_skipped(&mut self) {
+        self.skipped += 1;
+    }
+
+    pub fn merge(&mut self, other: StatsAccumulator) {
+        self.documents += other.documents;
+        self.skipped += other.skipped;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+        self.lengths.extend(other.lengths);
+        for (id, count) in other.counts {
+            *self.counts.entry(id).or_default() += count;
+        }
+    }
+
+    pub fn finish(mut self, top_k: usize) -> CorpusStats {
+        let tokens: u64 = self.lengths.iter().sum();
+        let ratio = |n: u64| {
+            if tokens == 0 {
+                0.0
+            } else {
+                n as f64 / tokens as f64
+            }
+        };
+
+        self.lengths.sort_unstable();
+        let percentile = |p: usize| self.lengths[(self.lengths.len() - 1) * p / 100];
+        let tokens_per_document = (!self.lengths.is_empty()).then(|| LengthSummary {
+            min: self.lengths[0],
+            max: self.lengths[self.lengths.len() - 1],
+            mean: tokens as f64 / self.lengths.len() as f64,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+        });
+
+        let mut length_histogram: Vec<HistogramBucket> = Vec::new();
+        for &length in &self.lengths {
+            let (min_tokens, max_tokens) = match length {
+                0 => (0, 0),
+                n => {
+                    let min = 1 << n.ilog2();
+                    (min, min * 2 - 1)
+                }
+            };
+            match length_histogram.last_mut() {
+                Some(bucket) if bucket.min_tokens == min_tokens => bucket.documents += 1,
+                _ => length_histogram.push(HistogramBucket {
+                    min_tokens,
+                    max_tokens,
+                    documents: 1,
+                }),
+            }
+        }
+
+        let token_count = |(id, count): (u32, u64)| TokenCount {
+            id,
+            text: glm45_tokenizer::detokenize(&[id], SpecialTokens::Keep).unwrap_or_default(),
+            count,
+        };
+        let mut by_count: Vec<(u32, u64)> = self.counts.into_iter().collect();
+        by_count.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let special_tokens = by_count
+            .iter()
+            .filter(|(id, _)| glm45_tokenizer::is_special_token(*id))
+            .map(|&c| token_count(c))
+            .collect();
+        let top_tokens = by_count.into_iter().take(top_k).map(token_count).collect();
+
+        CorpusStats {
+            documents: self.documents,
+            skipped: self.skipped,
+            tokens,
+            chars: self.chars,
+            bytes: self.bytes,
+            chars_per_token: ratio(self.chars),
+            bytes_per_token: ratio(self.bytes),
+            tokens_per_document,
+            length_histogram,
+            top_tokens,
+            special_tokens,
+        }
+    }
+}
+
+/// The document a line holds, or `None` if it should be counted as skipped.
+pub fn extract_document(line: &str, format: &InputFormat) -> Option<String> {
+    match format {
+        InputFormat::Jsonl { field } => {
+            let mut value: serde_json::Value = serde_json::from_str(line).ok()?;
+            match value.get_mut(field)?.take() {
+                serde_json::Value::String(text) => Some(text),
+                _ => None,
+            }
+        }
+        _ => Some(line.to_string()),
+    }
+}
+
+fn add_batch(
+    accumulator: &mut StatsAccumulator,
+    batch: &[Option<String>],
+    format: &InputFormat,
+) -> Result<()> {
+    let batch_stats = batch
+        .par_iter()
+        .try_fold(StatsAccumulator::default, |mut acc, line| {
+            let document = line
+                .as_deref()
+                .and_then(|line| extract_document(line, format));
+            match document {
+                Some(text) => acc.add_document(&text)?,
+                None => acc.add_skipped(),
+            }
+            Ok::<_, anyhow::Error>(acc)
+        })
+        .try_reduce(StatsAccumulator::default, |mut a, b| {
+            a.merge(b);
+            Ok(a)
+        })?;
+    accumulator.merge(batch_stats);
+    Ok(())
+}
+
+/// `None` for text that isn't valid UTF-8, which is skipped rather than failing the run.
+fn skip_invalid_utf8(text: std::io::Result<String>) -> std::io::Result<Option<String>> {
+    match text {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Streams every input and hands batches of up to `BATCH_SIZE` raw lines (or whole
+/// files, for `InputFormat::Text`) to `f`. Blank lines are dropped, and lines that
+/// aren't valid UTF-8 are passed as `None` so they can be counted as skipped.
+pub(crate) fn for_each_batch(
+    inputs: &[PathBuf],
+    format: &InputFormat,
+    mut f: impl FnMut(&[Option<String>]) -> Result<()>,
+) -> Result<()> {
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    for path in inputs {
+        let mut reader = BufReader::new(
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+        );
+        if matches!(format, InputFormat::Text) {
+            let mut text = String::new();
+            batch.push(
+                skip_invalid_utf8(reader.read_to_string(&mut text).map(|_| text))
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+            );
+        } else {
+            for line in reader.lines() {
+                let line = skip_invalid_utf8(line)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                if line.as_deref().is_some_and(|line| line.trim().is_empty()) {
+                    continue;
+                }
+                batch.push(line);
+                if batch.len() == BATCH_SIZE {
+                    f(&batch)?;
+                    batch.clear();
+                }
+            }
+        }
+        if batch.len() >= BATCH_SIZE {
+            f(&batch)?;
+            batch.clear();
+        }
+    }
+    f(&batch)
+}
+
+/// Streams every input and returns the combined statistics, with the `top_k` most
+/// frequent tokens.
+pub fn corpus_stats(inputs: &[PathBuf], format: &InputFormat, top_k: usize) -> Result<CorpusStats> {
+    let mut accumulator = StatsAccumulator::default();
+    for_each_batch(inputs, format, |batch| {
+        add_batch(&mut accumulator, batch, format)
+    })?;
+    Ok(accumulator.finish(top_k))
+}
@@ -0,0 +1,157 @@
+//! Incremental stop-sequence detection over sampled tokens.
+//!
+//! Stop strings can span token boundaries (`"\n\nUser"` may arrive as three tokens),
+//! so the matcher works on each token's decoded bytes and holds back any tail that
+//! could still turn into a match, along with incomplete UTF-8 sequences. Everything
+//! it returns is safe to show to the user.
+
+use anyhow::Result;
+
+use crate::glm45_tokenizer;
+
+/// Role sentinels and end-of-text: the points where GLM hands the turn back.
+pub const DEFAULT_STOP_SEQUENCES: &[&str] = &["<|user|>", "<|observation|>", "<|endoftext|>"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StopMatch {
+    /// Index into the matcher's stop sequences.
+    pub index: usize,
+    pub sequence: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StopUpdate {
+    /// Text that can be emitted now. On a match, this ends right before the stop
+    /// sequence.
+    pub text: String,
+    pub stop: Option<StopMatch>,
+}
+
+#[derive(Clone)]
+pub struct StopMatcher {
+    stops: Vec<String>,
+    pending: Vec<u8>,
+    stopped: Option<StopMatch>,
+}
+
+impl Default for StopMatcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_STOP_SEQUENCES.iter().copied())
+    }
+}
+
+impl StopMatcher {
+    /// Empty stop sequences are ignored.
+    pub fn new(stops: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            stops: stops
+                .into_iter()
+                .map(Into::into)
+                .filter(|s: &String| !s.is_empty())
+                .collect(),
+            pending: Vec::new(),
+            stopped: None,
+        }
+    }
+
+    pub fn stop_sequences(&self) -> &[String] {
+        &self.stops
+    }
+
+    pub fn stopped(&self) -> Option<&StopMatch> {
+        self.stopped.as_ref()
+    }
+
+    /// Consumes one sampled token.
+    pub fn push(&mut self, id: u32) -> Result<StopUpdate> {
+        let bytes = glm45_tokenizer::token_bytes(id)
+            .ok_or_else(|| anyhow::anyhow!("Token id {} is out of range", id))?;
+        Ok(self.push_bytes(&bytes))
+    }
+
+    /// Consumes raw decoded bytes. Once a stop sequence has matched, further input is
+    /// ignored until `reset`.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> StopUpdate {
+        if self.stopped.is_some() {
+            return StopUpdate::default();
+        }
+        self.pending.extend_from_slice(bytes);
+
+        // Earliest match wins; on a tie, the stop listed first.
+        let found = self
+            .stops
+            .iter()
+            .enumerate()
+            .filter_map(|(index, stop)| Some((find(&self.pending, stop.as_bytes())?, index)))
+            .min();
+        if let Some((start, index)) = found {
+            let text = String::from_utf8_lossy(&self.pending[..start]).into_owned();
+            let stop = StopMatch {
+                index,
+                sequence: self.stops[index].clone(),
+            };
+            self.pending.clear();
+            self.stopped = Some(stop.clone());
+            return StopUpdate {
+                text,
+                stop: Some(stop),
+            };
+        }
+
+        let hold = self
+            .partial_match_len()
+            .max(incomplete_utf8_len(&self.pending));
+        let emit = self.pending.len() - hold;
+        let text = String::from_utf8_lossy(&self.pending[..emit]).into_owned();
+        self.pending.drain(..emit);
+        StopUpdate { text, stop: None }
+    }
+
+    /// Flushes the held-back text at the end of generation.
+    pub fn finish(&mut self) -> String {
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending.clear();
+        text
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.stopped = None;
+    }
+
+    /// Length of the longest pending suffix that is a proper prefix of a stop sequence.
+    fn partial_match_len(&self) -> usize {
+        self.stops
+            .iter()
+            .map(|stop| {
+                let stop = stop.as_bytes();
+                (1..stop.len().min(self.pending.len() + 1))
+                    .rev()
+                    .find(|&n| self.pending.ends_with(&stop[..n]))
+                    .unwrap_or(0)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Length of a trailing UTF-8 sequence that's been started but not finished.
+fn incomplete_utf8_len(bytes: &[u8]) -> usize {
+    for len in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - len];
+        if byte & 0xC0 != 0x80 {
+            let needed = match byte {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            };
+            return if needed > len { len } else { 0 };
+        }
+    }
+    0
+}
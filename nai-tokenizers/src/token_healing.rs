@@ -0,0 +1,122 @@
+//! Token healing for prefilled prompts.
+//!
+//! A prompt that ends mid-word (or on a trailing space) usually ends on a token the
+//! model would never have produced there. Healing removes the last few tokens and asks
+//! the sampler to regenerate them, constrained to continuations whose text starts with
+//! the removed bytes.
+
+use anyhow::Result;
+
+use crate::glm45_tokenizer::{self, SpecialTokens};
+use crate::vocab_trie::VocabTrie;
+
+/// Prefix lookups over every non-special token, backed by a `VocabTrie`.
+pub struct VocabPrefixIndex {
+    trie: VocabTrie,
+}
+
+impl VocabPrefixIndex {
+    /// Builds the index over the GLM-4.5 vocabulary. Special tokens are left out since
+    /// they never continue text.
+    pub fn new() -> Self {
+        Self {
+            trie: VocabTrie::new(false),
+        }
+    }
+
+    /// Wraps a trie, e.g. one loaded with `VocabTrie::load_or_build`. It should be built
+    /// without special tokens.
+    pub fn from_trie(trie: VocabTrie) -> Self {
+        Self { trie }
+    }
+
+    pub fn trie(&self) -> &VocabTrie {
+        &self.trie
+    }
+
+    /// Tokens whose bytes start with `prefix`.
+    pub fn with_prefix<'a>(&'a self, prefix: &[u8]) -> impl Iterator<Item = u32> + 'a {
+        self.trie.with_prefix(prefix).iter().copied()
+    }
+
+    /// Tokens whose bytes are exactly `bytes`.
+    pub fn exact<'a>(&'a self, bytes: &[u8]) -> impl Iterator<Item = u32> + 'a {
+        self.trie.exact(bytes).iter().copied()
+    }
+}
+
+impl Default for VocabPrefixIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct TokenHealing {
+    /// The prompt with the healed tokens removed.
+    pub ids: Vec<u32>,
+    /// Bytes the generated text still has to start with.
+    pub prefix: Vec<u8>,
+}
+
+impl TokenHealing {
+    pub fn prefix_text(&self) -> String {
+        String::from_utf8_lossy(&self.prefix).into_owned()
+    }
+
+    pub fn is_satisfied(&self) -> bool {
+        self.prefix.is_empty()
+    }
+
+    /// Tokens the sampler may pick next: those that start with the remaining prefix,
+    /// and those that cover part of it.
+    pub fn allowed_tokens(&self, index: &VocabPrefixIndex) -> Vec<u32> {
+        index.trie.compatible(&self.prefix)
+    }
+
+    /// Consumes a sampled token. Returns whether the prefix has been fully matched.
+    pub fn advance(&mut self, id: u32) -> Result<bool> {
+        let bytes = glm45_tokenizer::token_bytes(id)
+            .ok_or_else(|| anyhow::anyhow!("Token id {} is out of range", id))?;
+        if bytes.starts_with(&self.prefix) {
+            self.prefix.clear();
+        } else if self.prefix.starts_with(&bytes) && !bytes.is_empty() {
+            self.prefix.drain(..bytes.len());
+        } else {
+            anyhow::bail!("Token {} doesn't match the healing prefix", id);
+        }
+        Ok(self.is_satisfied())
+    }
+}
+
+/// Backs off up to `max_tokens` trailing tokens. Special tokens (role sentinels, think
+/// tags) are never removed, so healing stops at the start of the prefilled text.
+pub fn heal(ids: &[u32], max_tokens: usize) -> Result<TokenHealing> {
+    let keep = ids.len()
+        - ids
+            .iter()
+            .rev()
+            .take(max_tokens)
+            .take_while(|&&id| !glm45_tokenizer::is_special_token(id))
+            .count();
+
+    let mut prefix = Vec::new();
+    for &id in &ids[keep..] {
+        prefix.extend(
+            glm45_tokenizer::token_bytes(id)
+                .ok_or_else(|| anyhow::anyhow!("Token id {} is out of range", id))?,
+        );
+    }
+    Ok(TokenHealing {
+        ids: ids[..keep].to_vec(),
+        prefix,
+    })
+}
+
+/// Tokenizes a rendered prompt (e.g. from `ContextState::chat` with a
+/// `PrefillType::FullReasoning` prefill) and heals its end.
+pub fn heal_prompt(prompt: &str, max_tokens: usize) -> Result<TokenHealing> {
+    heal(
+        &glm45_tokenizer::tokenize(prompt, SpecialTokens::Keep)?,
+        max_tokens,
+    )
+}
@@ -0,0 +1,48 @@
+//! GLM-4.5's bundled tokenizer_config.json: the pad and EOS tokens, padding side and
+//! decoding defaults that batching and detokenization fall back on.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const TOKENIZER_CONFIG_JSON: &[u8] =
+    include_bytes!("../tokenizers/glm-4.5-tokenizer-config.json.br");
+
+lazy_static::lazy_static! {
+    static ref TOKENIZER_CONFIG: TokenizerConfig =
+        load_tokenizer_config().expect("Failed to load GLM-4.5 tokenizer config");
+}
+
+/// The parts of tokenizer_config.json that the crate uses.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenizerConfig {
+    #[serde(default)]
+    pub pad_token: Option<String>,
+    #[serde(default)]
+    pub eos_token: Option<String>,
+    #[serde(default)]
+    pub padding_side: PaddingSide,
+    #[serde(default)]
+    pub model_max_length: Option<usize>,
+    #[serde(default)]
+    pub clean_up_tokenization_spaces: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaddingSide {
+    Left,
+    #[default]
+    Right,
+}
+
+fn load_tokenizer_config() -> Result<TokenizerConfig> {
+    let mut json = Vec::new();
+    brotli::BrotliDecompress(&mut &TOKENIZER_CONFIG_JSON[..], &mut json)
+        .map_err(|e| anyhow::anyhow!("Failed to decompress tokenizer config: {}", e))?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// GLM-4.5's bundled tokenizer_config.json.
+pub fn tokenizer_config() -> &'static TokenizerConfig {
+    &TOKENIZER_CONFIG
+}
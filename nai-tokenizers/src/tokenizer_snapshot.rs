@@ -0,0 +1,148 @@
+//! Loads the GLM-4.5 tokenizer from the binary snapshot generated by `build.rs`.
+//!
+//! Parsing the full tokenizer.json dominates startup: most of it is the ~150k entry
+//! vocabulary and merge list. The snapshot stores those as flat arrays (token strings in
+//! id order, merges as id pairs) and keeps only the small remainder as JSON, so loading
+//! is a straight copy into the BPE model. The resulting tokenizer is identical to the
+//! one `Tokenizer::from_bytes` builds from the JSON.
+
+use std::io::Read;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tokenizers::models::bpe::{BPE, Vocab};
+use tokenizers::{
+    AddedToken, DecoderWrapper, ModelWrapper, NormalizerWrapper, PaddingParams,
+    PostProcessorWrapper, PreTokenizerWrapper, Tokenizer, TokenizerBuilder, TruncationParams,
+};
+
+const MAGIC: &[u8; 8] = b"NAITOK\0\0";
+const FORMAT_VERSION: u32 = 1;
+
+/// The Brotli-compressed snapshot of the bundled GLM-4.5 tokenizer.
+pub static GLM45_SNAPSHOT: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/glm-4.5-tokenizer.snapshot.br"));
+
+/// Everything in tokenizer.json except the BPE vocabulary and merges.
+#[derive(Deserialize)]
+struct Header {
+    truncation: Option<TruncationParams>,
+    padding: Option<PaddingParams>,
+    added_tokens: Vec<AddedToken>,
+    normalizer: Option<NormalizerWrapper>,
+    pre_tokenizer: Option<PreTokenizerWrapper>,
+    post_processor: Option<PostProcessorWrapper>,
+    decoder: Option<DecoderWrapper>,
+    model: BpeOptions,
+}
+
+#[derive(Deserialize)]
+struct BpeOptions {
+    dropout: Option<f32>,
+    unk_token: Option<String>,
+    continuing_subword_prefix: Option<String>,
+    end_of_word_suffix: Option<String>,
+    #[serde(default)]
+    fuse_unk: bool,
+    #[serde(default)]
+    byte_fallback: bool,
+    #[serde(default)]
+    ignore_merges: bool,
+}
+
+/// Decompresses and loads a tokenizer snapshot.
+pub fn load(compressed: &[u8]) -> Result<Tokenizer> {
+    let mut data = Vec::new();
+    brotli::BrotliDecompress(&mut &compressed[..], &mut data)
+        .map_err(|e| anyhow::anyhow!("Failed to decompress tokenizer snapshot: {}", e))?;
+    read_from(&mut &data[..])
+}
+
+/// Loads an uncompressed tokenizer snapshot.
+pub fn read_from(reader: &mut impl Read) -> Result<Tokenizer> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    anyhow::ensure!(&magic == MAGIC, "Not a tokenizer snapshot");
+    let version = read_u32(reader)?;
+    anyhow::ensure!(
+        version == FORMAT_VERSION,
+        "Unsupported tokenizer snapshot version {}",
+        version
+    );
+
+    let mut header = vec![0; read_u32(reader)? as usize];
+    reader.read_exact(&mut header)?;
+    let header: Header =
+        serde_json::from_slice(&header).context("Invalid tokenizer snapshot header")?;
+
+    let vocab_size = read_u32(reader)? as usize;
+    let mut lengths = vec![0; vocab_size * 2];
+    reader.read_exact(&mut lengths)?;
+    let mut tokens = Vec::with_capacity(vocab_size);
+    for len in lengths.chunks_exact(2) {
+        let mut token = vec![0; u16::from_le_bytes([len[0], len[1]]) as usize];
+        reader.read_exact(&mut token)?;
+        tokens.push(String::from_utf8(token).context("Invalid token in tokenizer snapshot")?);
+    }
+
+    let merge_count = read_u32(reader)? as usize;
+    let mut merge_ids = vec![0; merge_count * 8];
+    reader.read_exact(&mut merge_ids)?;
+    let token = |id: &[u8]| {
+        tokens
+            .get(u32::from_le_bytes(id.try_into().unwrap()) as usize)
+            .cloned()
+            .context("Merge references a token id out of range")
+    };
+    let merges = merge_ids
+        .chunks_exact(8)
+        .map(|pair| Ok((token(&pair[..4])?, token(&pair[4..])?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let vocab: Vocab = tokens
+        .into_iter()
+        .enumerate()
+        .map(|(id, token)| (token, id as u32))
+        .collect();
+    let options = header.model;
+    let mut bpe = BPE::builder()
+        .vocab_and_merges(vocab, merges)
+        .fuse_unk(options.fuse_unk)
+        .byte_fallback(options.byte_fallback)
+        .ignore_merges(options.ignore_merges);
+    if let Some(dropout) = options.dropout {
+        bpe = bpe.dropout(dropout);
+    }
+    if let Some(unk_token) = options.unk_token {
+        bpe = bpe.unk_token(unk_token);
+    }
+    if let Some(prefix) = options.continuing_subword_prefix {
+        bpe = bpe.continuing_subword_prefix(prefix);
+    }
+    if let Some(suffix) = options.end_of_word_suffix {
+        bpe = bpe.end_of_word_suffix(suffix);
+    }
+    let bpe = bpe.build().map_err(|e| anyhow::anyhow!(e.to_string()))?;
+
+    let mut tokenizer: Tokenizer = TokenizerBuilder::new()
+        .with_model(ModelWrapper::BPE(bpe))
+        .with_normalizer(header.normalizer)
+        .with_pre_tokenizer(header.pre_tokenizer)
+        .with_post_processor(header.post_processor)
+        .with_decoder(header.decoder)
+        .with_truncation(header.truncation)
+        .with_padding(header.padding)
+        .build()
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?
+        .into();
+    // Same as `Tokenizer::from_bytes`: added tokens get their ids from the model or,
+    // for new ones, the next free id, in file order.
+    tokenizer.add_tokens(&header.added_tokens);
+    Ok(tokenizer)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
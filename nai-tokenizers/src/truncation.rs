@@ -0,0 +1,208 @@
+//! Token-exact truncation: cuts text to at most `max_tokens` GLM-4.5 tokens, keeping the
+//! head, the tail, or both ends around an ellipsis.
+//!
+//! The limit holds for the returned text tokenized on its own, which is what a caller
+//! that re-tokenizes it will see. Cuts land on character boundaries, and never inside
+//! an added token such as `<|user|>`.
+
+use std::ops::Range;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::chunking::{ceil_char_boundary, floor_char_boundary, partition_point};
+use crate::glm45_tokenizer::{self, SpecialTokens};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStrategy {
+    /// Keep the start of the text and drop the end.
+    #[default]
+    KeepHead,
+    /// Keep the end of the text and drop the start.
+    KeepTail,
+    /// Keep both ends and replace the middle with the ellipsis.
+    KeepEnds,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TruncateOptions {
+    pub max_tokens: usize,
+    #[serde(default)]
+    pub strategy: TruncationStrategy,
+    /// Inserted where `KeepEnds` drops text. Its tokens count towards the limit.
+    #[serde(default = "default_ellipsis")]
+    pub ellipsis: String,
+}
+
+fn default_ellipsis() -> String {
+    "…".to_string()
+}
+
+impl TruncateOptions {
+    pub fn new(max_tokens: usize, strategy: TruncationStrategy) -> Self {
+        Self {
+            max_tokens,
+            strategy,
+            ellipsis: default_ellipsis(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Truncation {
+    pub text: String,
+    pub tokens: usize,
+    /// Byte ranges of the input that were left out, in order. Empty if the text
+    /// already fit.
+    pub dropped: Vec<Range<usize>>,
+}
+
+impl Truncation {
+    pub fn truncated(&self) -> bool {
+        !self.dropped.is_empty()
+    }
+}
+
+/// Cuts `text` to at most `options.max_tokens` tokens. Fails if `KeepEnds` has no
+/// room for the ellipsis.
+pub fn truncate(
+    text: &str,
+    options: &TruncateOptions,
+    special_tokens: SpecialTokens,
+) -> Result<Truncation> {
+    let (ids, offsets) = glm45_tokenizer::tokenize_with_offsets(text, special_tokens)?;
+    if ids.len() <= options.max_tokens {
+        return Ok(Truncation {
+            text: text.to_string(),
+            tokens: ids.len(),
+            dropped: Vec::new(),
+        });
+    }
+
+    let added = ids
+        .iter()
+        .zip(&offsets)
+        .filter(|&(&id, _)| glm45_tokenizer::is_added_token(id))
+        .map(|(_, &(start, end))| start..end)
+        .collect();
+    let cutter = Cutter {
+        text,
+        offsets: &offsets,
+        added,
+        special_tokens,
+    };
+    let (kept, dropped) = match options.strategy {
+        TruncationStrategy::KeepHead => {
+            let end = cutter.longest_head(options.max_tokens)?;
+            (&text[..end], end..text.len())
+        }
+        TruncationStrategy::KeepTail => {
+            let start = cutter.longest_tail(options.max_tokens, 0)?;
+            (&text[start..], 0..start)
+        }
+        TruncationStrategy::KeepEnds => {
+            return cutter.keep_ends(options.max_tokens, &options.ellipsis);
+        }
+    };
+    Ok(Truncation {
+        text: kept.to_string(),
+        tokens: cutter.count(kept)?,
+        dropped: vec![dropped],
+    })
+}
+
+struct Cutter<'a> {
+    text: &'a str,
+    /// Token offsets of the whole text, to bound the searches.
+    offsets: &'a [(usize, usize)],
+    /// Spans of the added tokens in the text, in order. A partial added token
+    /// tokenizes to far more than the whole one, which would throw off the searches.
+    added: Vec<Range<usize>>,
+    special_tokens: SpecialTokens,
+}
+
+impl Cutter<'_> {
+    fn count(&self, text: &str) -> Result<usize> {
+        Ok(glm45_tokenizer::tokenize(text, self.special_tokens)?.len())
+    }
+
+    /// The cut points in `range`.
+    fn candidates(&self, range: std::ops::RangeInclusive<usize>) -> Vec<usize> {
+        range
+            .filter(|&i| self.text.is_char_boundary(i))
+            .filter(|&i| {
+                let next = self.added.partition_point(|span| span.end <= i);
+                self.added.get(next).is_none_or(|span| span.start >= i)
+            })
+            .collect()
+    }
+
+    /// The end of the longest prefix that fits in `budget` tokens.
+    fn longest_head(&self, budget: usize) -> Result<usize> {
+        let text = self.text;
+        // The prefix through token `budget` of the whole text almost never fits.
+        let limit = match self.offsets.get(budget) {
+            Some(&(_, end)) => ceil_char_boundary(text, end),
+            None => text.len(),
+        };
+        let candidates = self.candidates(0..=limit);
+        let fits = partition_point(&candidates, |end| Ok(self.count(&text[..end])? <= budget))?;
+        // The empty prefix always fits.
+        Ok(candidates[fits.max(1) - 1])
+    }
+
+    /// The start of the longest suffix that fits in `budget` tokens, no earlier than
+    /// `min_start`.
+    fn longest_tail(&self, budget: usize, min_start: usize) -> Result<usize> {
+        let text = self.text;
+        let limit = match self.offsets.len().checked_sub(budget + 1) {
+            Some(index) => floor_char_boundary(text, self.offsets[index].0),
+            None => 0,
+        };
+        let candidates = self.candidates(limit.max(min_start)..=text.len());
+        let too_long =
+            partition_point(
+                &candidates,
+                |start| Ok(self.count(&text[start..])? > budget),
+            )?;
+        // The empty suffix always fits.
+        Ok(candidates[too_long.min(candidates.len() - 1)])
+    }
+
+    /// Splits what's left after the ellipsis between head and tail, giving the head the
+    /// odd token, and shrinks them until the joined text fits: tokens can merge across
+    /// the joins.
+    fn keep_ends(&self, max_tokens: usize, ellipsis: &str) -> Result<Truncation> {
+        let text = self.text;
+        let ellipsis_tokens = self.count(ellipsis)?;
+        anyhow::ensure!(
+            ellipsis_tokens <= max_tokens,
+            "The ellipsis takes {} tokens, more than max_tokens ({})",
+            ellipsis_tokens,
+            max_tokens
+        );
+        let budget = max_tokens - ellipsis_tokens;
+        let (mut head_budget, mut tail_budget) = (budget - budget / 2, budget / 2);
+        loop {
+            let head_end = self.longest_head(head_budget)?;
+            let tail_start = self.longest_tail(tail_budget, head_end)?;
+            let joined = format!("{}{}{}", &text[..head_end], ellipsis, &text[tail_start..]);
+            let tokens = self.count(&joined)?;
+            // With both budgets spent this is just the ellipsis, which fits.
+            if tokens <= max_tokens {
+                let dropped = head_end..tail_start;
+                return Ok(Truncation {
+                    text: joined,
+                    tokens,
+                    dropped: vec![dropped],
+                });
+            }
+            if tail_budget >= head_budget {
+                tail_budget -= 1;
+            } else {
+                head_budget -= 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Vocabulary inspection: id/token lookups, the bytes behind each token, and search.
+//!
+//! Regular tokens are stored ByteLevel-mapped (`Ġhello` for " hello"); `TokenInfo`
+//! carries both that form and the decoded bytes, and search matches the decoded text.
+
+use anyhow::Result;
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::glm45_tokenizer::{self, GLM45_TOKENIZER};
+
+lazy_static::lazy_static! {
+    /// Decoded text of every id, for search. `None` for ids with no token.
+    static ref TOKEN_TEXTS: Vec<Option<String>> = (0..glm45_tokenizer::vocab_size() as u32)
+        .map(|id| {
+            glm45_tokenizer::token_bytes(id)
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        })
+        .collect();
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TokenInfo {
+    pub id: u32,
+    /// The token as stored in the vocabulary.
+    pub token: String,
+    /// The bytes the token decodes to.
+    pub bytes: Vec<u8>,
+    /// `bytes` as UTF-8; tokens holding part of a character get U+FFFD in its place.
+    pub text: String,
+    /// Added tokens are matched before BPE runs, by their literal content.
+    pub added: bool,
+    pub special: bool,
+}
+
+/// Looks up a token by id. Returns `None` for ids outside the vocabulary.
+pub fn token_info(id: u32) -> Option<TokenInfo> {
+    let token = GLM45_TOKENIZER.id_to_token(id)?;
+    let bytes = glm45_tokenizer::token_bytes(id)?;
+    Some(TokenInfo {
+        id,
+        token,
+        text: String::from_utf8_lossy(&bytes).into_owned(),
+        bytes,
+        added: glm45_tokenizer::is_added_token(id),
+        special: glm45_tokenizer::is_special_token(id),
+    })
+}
+
+/// The id of a token in its vocabulary form (`Ġhello`, `<|user|>`).
+pub fn token_to_id(token: &str) -> Option<u32> {
+    GLM45_TOKENIZER.token_to_id(token)
+}
+
+/// The id of the token that decodes to exactly `bytes`, if there is one. Added
+/// tokens are found by their content.
+pub fn bytes_to_id(bytes: &[u8]) -> Option<u32> {
+    if let Some(id) = std::str::from_utf8(bytes).ok().and_then(token_to_id)
+        && glm45_tokenizer::is_added_token(id)
+    {
+        return Some(id);
+    }
+    let token: String = bytes
+        .iter()
+        .map(|&b| glm45_tokenizer::BYTE_LEVEL_ENCODER[b as usize])
+        .collect();
+    token_to_id(&token).filter(|&id| !glm45_tokenizer::is_added_token(id))
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SearchQuery {
+    /// Substring (or regex) to find in the decoded token text.
+    pub pattern: String,
+    pub regex: bool,
+    pub ignore_case: bool,
+    /// Leave out added tokens.
+    pub exclude_added: bool,
+    /// Results to skip, for paging.
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for SearchQuery {
+    fn default() -> Self {
+        Self {
+            pattern: String::new(),
+            regex: false,
+            ignore_case: false,
+            exclude_added: false,
+            offset: 0,
+            limit: 100,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchResults {
+    /// Number of matching tokens, before `offset`/`limit`.
+    pub total: usize,
+    /// Matches in id order.
+    pub tokens: Vec<TokenInfo>,
+}
+
+/// Finds the tokens whose decoded text matches `query`. An empty pattern matches
+/// everything, which pages through the whole vocabulary.
+pub fn search(query: &SearchQuery) -> Result<SearchResults> {
+    let matcher: Box<dyn Fn(&str) -> bool> = if query.regex {
+        let regex = RegexBuilder::new(&query.pattern)
+            .case_insensitive(query.ignore_case)
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid search pattern: {}", e))?;
+        Box::new(move |text| regex.is_match(text))
+    } else if query.ignore_case {
+        let pattern = query.pattern.to_lowercase();
+        Box::new(move |text| text.to_lowercase().contains(&pattern))
+    } else {
+        Box::new(|text| text.contains(&query.pattern))
+    };
+
+    let mut total = 0;
+    let mut tokens = Vec::new();
+    for (id, text) in TOKEN_TEXTS.iter().enumerate() {
+        let (id, Some(text)) = (id as u32, text) else {
+            continue;
+        };
+        if (query.exclude_added && glm45_tokenizer::is_added_token(id)) || !matcher(text) {
+            continue;
+        }
+        if total >= query.offset && tokens.len() < query.limit {
+            tokens.extend(token_info(id));
+        }
+        total += 1;
+    }
+    Ok(SearchResults { total, tokens })
+}
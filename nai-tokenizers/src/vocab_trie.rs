@@ -0,0 +1,324 @@
+//! Byte-level trie over the GLM-4.5 vocabulary, for constrained decoding.
+//!
+//! Tokens are stored in lexicographic order of their decoded bytes, which is also the
+//! trie's depth-first order, so every node's subtree is one contiguous slice of ids and
+//! prefix queries don't allocate. The trie can be cached to disk with `save`/`load` so
+//! services don't rebuild it on every start.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::fs_util;
+use crate::glm45_tokenizer;
+
+const MAGIC: &[u8; 8] = b"NAITRIE\0";
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Default)]
+struct TrieNode {
+    /// Children live in `edge_bytes`/`edge_targets[children_start..][..children_len]`.
+    children_start: u32,
+    children_len: u32,
+    /// The subtree's tokens are `token_ids[start..end]`; the first `own` of them decode
+    /// to exactly this node's bytes.
+    start: u32,
+    own: u32,
+    end: u32,
+}
+
+pub struct VocabTrie {
+    nodes: Vec<TrieNode>,
+    /// Edge bytes and target nodes, sorted by byte within each node.
+    edge_bytes: Vec<u8>,
+    edge_targets: Vec<u32>,
+    token_ids: Vec<u32>,
+    include_special: bool,
+    vocab_size: u32,
+}
+
+impl VocabTrie {
+    /// Builds the trie over the GLM-4.5 vocabulary. Special tokens match by their
+    /// literal text only when `include_special` is set.
+    pub fn new(include_special: bool) -> Self {
+        let vocab_size = glm45_tokenizer::vocab_size() as u32;
+        let mut entries: Vec<(Vec<u8>, u32)> = (0..vocab_size)
+            .filter(|&id| include_special || !glm45_tokenizer::is_special_token(id))
+            .filter_map(|id| Some((glm45_tokenizer::token_bytes(id)?, id)))
+            .filter(|(bytes, _)| !bytes.is_empty())
+            .collect();
+        entries.sort_unstable();
+
+        // Children are created in byte order because entries are sorted, so the newest
+        // child is the only one a later entry can share.
+        let mut nodes = vec![TrieNode::default()];
+        let mut children: Vec<Vec<(u8, u32)>> = vec![Vec::new()];
+        for (i, (bytes, _)) in entries.iter().enumerate() {
+            let i = i as u32;
+            let mut node = 0;
+            nodes[0].end = i + 1;
+            for &byte in bytes {
+                node = match children[node].last() {
+                    Some(&(b, child)) if b == byte => child as usize,
+                    _ => {
+                        let child = nodes.len();
+                        nodes.push(TrieNode {
+                            start: i,
+                            ..TrieNode::default()
+                        });
+                        children.push(Vec::new());
+                        children[node].push((byte, child as u32));
+                        child
+                    }
+                };
+                nodes[node].end = i + 1;
+            }
+            nodes[node].own += 1;
+        }
+
+        let mut edge_bytes = Vec::with_capacity(nodes.len());
+        let mut edge_targets = Vec::with_capacity(nodes.len());
+        for (node, node_children) in nodes.iter_mut().zip(children) {
+            node.children_start = edge_bytes.len() as u32;
+            node.children_len = node_children.len() as u32;
+            for (byte, child) in node_children {
+                edge_bytes.push(byte);
+                edge_targets.push(child);
+            }
+        }
+
+        Self {
+            nodes,
+            edge_bytes,
+            edge_targets,
+            token_ids: entries.into_iter().map(|(_, id)| id).collect(),
+            include_special,
+            vocab_size,
+        }
+    }
+
+    fn child(&self, node: usize, byte: u8) -> Option<usize> {
+        let TrieNode {
+            children_start,
+            children_len,
+            ..
+        } = self.nodes[node];
+        let range = children_start as usize..(children_start + children_len) as usize;
+        let i = self.edge_bytes[range.clone()].binary_search(&byte).ok()?;
+        Some(self.edge_targets[range.start + i] as usize)
+    }
+
+    fn find(&self, bytes: &[u8]) -> Option<usize> {
+        bytes
+            .iter()
+            .try_fold(0, |node, &byte| self.child(node, byte))
+    }
+
+    /// Tokens whose bytes start with `prefix`, in byte order.
+    pub fn with_prefix(&self, prefix: &[u8]) -> &[u32] {
+        match self.find(prefix) {
+            Some(node) => {
+                let node = self.nodes[node];
+                &self.token_ids[node.start as usize..node.end as usize]
+            }
+            None => &[],
+        }
+    }
+
+    /// Tokens whose bytes are exactly `bytes`.
+    pub fn exact(&self, bytes: &[u8]) -> &[u32] {
+        match self.find(bytes) {
+            Some(node) if !bytes.is_empty() => {
+                let node = self.nodes[node];
+                &self.token_ids[node.start as usize..(node.start + node.own) as usize]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Tokens whose bytes are a non-empty prefix of `bytes`, shortest first.
+    pub fn prefixes_of(&self, bytes: &[u8]) -> Vec<u32> {
+        let mut tokens = Vec::new();
+        let mut node = 0;
+        for &byte in bytes {
+            match self.child(node, byte) {
+                Some(child) => node = child,
+                None => break,
+            }
+            let n = self.nodes[node];
+            tokens.extend_from_slice(&self.token_ids[n.start as usize..(n.start + n.own) as usize]);
+        }
+        tokens
+    }
+
+    /// Tokens that keep generated text consistent with a required byte prefix: those
+    /// that start with all of it, plus those that cover only part of it.
+    pub fn compatible(&self, prefix: &[u8]) -> Vec<u32> {
+        let mut tokens = self.with_prefix(prefix).to_vec();
+        if let Some((_, shorter)) = prefix.split_last() {
+            tokens.extend(self.prefixes_of(shorter));
+        }
+        tokens
+    }
+
+    /// Number of tokens in the trie.
+    pub fn len(&self) -> usize {
+        self.token_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.token_ids.is_empty()
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        for value in [
+            FORMAT_VERSION,
+            self.vocab_size,
+            self.include_special as u32,
+            self.nodes.len() as u32,
+            self.edge_bytes.len() as u32,
+            self.token_ids.len() as u32,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for node in &self.nodes {
+            for value in [
+                node.children_start,
+                node.children_len,
+                node.start,
+                node.own,
+                node.end,
+            ] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        writer.write_all(&self.edge_bytes)?;
+        for value in self.edge_targets.iter().chain(&self.token_ids) {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Parses a cache written by `write_to`. Counts and indices are checked against the
+    /// input, so a truncated or corrupt cache is an error instead of a panic later on.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        anyhow::ensure!(
+            take(&mut bytes, MAGIC.len())? == MAGIC,
+            "Not a vocabulary trie cache"
+        );
+
+        let [version, vocab_size, include_special, nodes, edges, tokens] =
+            read_u32s(&mut bytes, 6)?[..]
+        else {
+            unreachable!()
+        };
+        anyhow::ensure!(
+            version == FORMAT_VERSION,
+            "Unsupported trie cache version {}",
+            version
+        );
+
+        let node_values = (nodes as usize)
+            .checked_mul(5)
+            .context("Trie cache is truncated")?;
+        let nodes = read_u32s(&mut bytes, node_values)?
+            .chunks_exact(5)
+            .map(|n| TrieNode {
+                children_start: n[0],
+                children_len: n[1],
+                start: n[2],
+                own: n[3],
+                end: n[4],
+            })
+            .collect();
+        let edge_bytes = take(&mut bytes, edges as usize)?.to_vec();
+        let edge_targets = read_u32s(&mut bytes, edges as usize)?;
+        let token_ids = read_u32s(&mut bytes, tokens as usize)?;
+        anyhow::ensure!(bytes.is_empty(), "Trailing data in trie cache");
+
+        let trie = Self {
+            nodes,
+            edge_bytes,
+            edge_targets,
+            token_ids,
+            include_special: include_special != 0,
+            vocab_size,
+        };
+        trie.validate()?;
+        Ok(trie)
+    }
+
+    fn validate(&self) -> Result<()> {
+        anyhow::ensure!(!self.nodes.is_empty(), "Trie cache has no root node");
+        let edges = self.edge_bytes.len() as u64;
+        let tokens = self.token_ids.len() as u64;
+        for node in &self.nodes {
+            let children_end = node.children_start as u64 + node.children_len as u64;
+            let own_end = node.start as u64 + node.own as u64;
+            anyhow::ensure!(
+                children_end <= edges && own_end <= node.end as u64 && node.end as u64 <= tokens,
+                "Trie cache has an out-of-range node"
+            );
+        }
+        anyhow::ensure!(
+            self.edge_targets
+                .iter()
+                .all(|&target| (target as usize) < self.nodes.len()),
+            "Trie cache has an out-of-range edge"
+        );
+        anyhow::ensure!(
+            self.token_ids.iter().all(|&id| id < self.vocab_size),
+            "Trie cache has an out-of-range token id"
+        );
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+        fs_util::write_atomic(path, &bytes)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::read_from(&mut bytes.as_slice())
+    }
+
+    /// Loads the cache at `path`, rebuilding and rewriting it if it's missing, corrupt,
+    /// or was built for a different vocabulary or `include_special` setting.
+    pub fn load_or_build(path: &Path, include_special: bool) -> Result<Self> {
+        if let Ok(trie) = Self::load(path)
+            && trie.include_special == include_special
+            && trie.vocab_size == glm45_tokenizer::vocab_size() as u32
+        {
+            return Ok(trie);
+        }
+        let trie = Self::new(include_special);
+        trie.save(path)?;
+        Ok(trie)
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    anyhow::ensure!(len <= input.len(), "Trie cache is truncated");
+    let (head, rest) = input.split_at(len);
+    *input = rest;
+    Ok(head)
+}
+
+fn read_u32s(input: &mut &[u8], n: usize) -> Result<Vec<u32>> {
+    let len = n.checked_mul(4).context("Trie cache is truncated")?;
+    Ok(take(input, len)?
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+        .collect())
+}
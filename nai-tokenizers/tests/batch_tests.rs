@@ -0,0 +1,167 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod batch_tests {
+    use nai_tokenizers::batch::*;
+    use nai_tokenizers::glm45_tokenizer::{GLM45_TOKENIZER, SpecialTokens, tokenize};
+    use nai_tokenizers::tokenizer_config::tokenizer_config;
+
+    const TEXTS: [&str; 3] = [
+        "Hello, world!",
+        "A somewhat longer sentence with quite a few more words in it.",
+        "",
+    ];
+
+    fn ids(text: &str) -> Vec<u32> {
+        tokenize(text, SpecialTokens::Ignore).unwrap()
+    }
+
+    fn encode(options: &BatchOptions) -> Batch {
+        encode_batch(&TEXTS, options, SpecialTokens::Ignore).unwrap()
+    }
+
+    /// The tokens of each row, with the padding stripped using the mask.
+    fn unpadded(batch: &Batch) -> Vec<Vec<u32>> {
+        (0..batch.rows)
+            .map(|row| {
+                let mask = &batch.attention_mask[row * batch.width..(row + 1) * batch.width];
+                batch
+                    .row(row)
+                    .iter()
+                    .zip(mask)
+                    .filter(|&(_, &m)| m == 1)
+                    .map(|(&id, _)| id)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pad_token_from_config() {
+        let config = tokenizer_config();
+        let pad_token = config.pad_token.as_deref().unwrap();
+        assert_eq!(
+            pad_token_id().unwrap(),
+            GLM45_TOKENIZER.token_to_id(pad_token).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_right_padding() {
+        let batch = encode(&BatchOptions {
+            padding_side: Some(PaddingSide::Right),
+            ..BatchOptions::default()
+        });
+        let longest = ids(TEXTS[1]).len();
+        assert_eq!((batch.rows, batch.width), (3, longest));
+        assert_eq!(batch.input_ids.len(), 3 * longest);
+        assert_eq!(batch.sample_mapping, [0, 1, 2]);
+        assert_eq!(
+            unpadded(&batch),
+            TEXTS.iter().map(|t| ids(t)).collect::<Vec<_>>()
+        );
+
+        let first = ids(TEXTS[0]);
+        assert_eq!(&batch.row(0)[..first.len()], first.as_slice());
+        assert!(
+            batch.row(0)[first.len()..]
+                .iter()
+                .all(|&id| id == batch.pad_token_id)
+        );
+        let positions = &batch.position_ids[..batch.width];
+        assert!((0..first.len()).all(|i| positions[i] == i as u32));
+        assert!(batch.row(2).iter().all(|&id| id == batch.pad_token_id));
+        assert_eq!(batch.lengths[2], 0);
+    }
+
+    #[test]
+    fn test_left_padding() {
+        let batch = encode(&BatchOptions {
+            padding_side: Some(PaddingSide::Left),
+            pad_to_multiple_of: Some(8),
+            ..BatchOptions::default()
+        });
+        assert_eq!(batch.width % 8, 0);
+        assert!(batch.width >= ids(TEXTS[1]).len());
+        let first = ids(TEXTS[0]);
+        let padding = batch.width - first.len();
+        assert_eq!(&batch.row(0)[padding..], first.as_slice());
+        assert_eq!(batch.position_ids[padding], 0);
+        assert_eq!(batch.position_ids[batch.width - 1], first.len() as u32 - 1);
+        assert!(batch.attention_mask[..padding].iter().all(|&m| m == 0));
+    }
+
+    #[test]
+    fn test_padding_side_defaults_to_config() {
+        let default = encode(&BatchOptions::default());
+        let explicit = encode(&BatchOptions {
+            padding_side: Some(tokenizer_config().padding_side),
+            ..BatchOptions::default()
+        });
+        assert_eq!(default, explicit);
+    }
+
+    #[test]
+    fn test_truncation_and_overflow() {
+        let long = ids(TEXTS[1]);
+        let max_length = 6;
+        assert!(long.len() > 2 * max_length);
+
+        let truncated = encode(&BatchOptions {
+            max_length: Some(max_length),
+            ..BatchOptions::default()
+        });
+        assert_eq!((truncated.rows, truncated.width), (3, max_length));
+        assert_eq!(unpadded(&truncated)[1], long[..max_length]);
+
+        let stride = 2;
+        let batch = encode(&BatchOptions {
+            max_length: Some(max_length),
+            return_overflowing_tokens: true,
+            stride,
+            padding_side: Some(PaddingSide::Right),
+            ..BatchOptions::default()
+        });
+        let rows = unpadded(&batch);
+        let windows: Vec<&Vec<u32>> = rows
+            .iter()
+            .zip(&batch.sample_mapping)
+            .filter(|&(_, &sample)| sample == 1)
+            .map(|(row, _)| row)
+            .collect();
+        assert!(windows.len() > 2);
+        assert_eq!(windows[0], &long[..max_length]);
+        for pair in windows.windows(2) {
+            assert_eq!(pair[0][max_length - stride..], pair[1][..stride]);
+        }
+        // Stitching the windows back together gives the whole sequence.
+        let mut stitched = windows[0].clone();
+        for window in &windows[1..] {
+            stitched.extend_from_slice(&window[stride..]);
+        }
+        assert_eq!(stitched, long);
+        assert!(batch.sample_mapping.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(batch.sample_mapping.last(), Some(&2));
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let options = [
+            BatchOptions {
+                return_overflowing_tokens: true,
+                ..BatchOptions::default()
+            },
+            BatchOptions {
+                max_length: Some(4),
+                return_overflowing_tokens: true,
+                stride: 4,
+                ..BatchOptions::default()
+            },
+            BatchOptions {
+                max_length: Some(0),
+                ..BatchOptions::default()
+            },
+        ];
+        for options in options {
+            assert!(encode_batch(&TEXTS, &options, SpecialTokens::Ignore).is_err());
+        }
+    }
+}
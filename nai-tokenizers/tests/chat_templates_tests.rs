@@ -0,0 +1,133 @@
+#[cfg(feature = "glm45_template")]
+mod chat_templates_tests {
+    use nai_tokenizers::chat_templates::*;
+    use nai_tokenizers::glm45_template::{
+        Chat, ContextState, Message, PrefillType, ReasoningEnabled, Version,
+    };
+
+    fn conversation() -> Chat {
+        Chat {
+            messages: vec![
+                Message::System {
+                    content: "You are a helpful assistant.".to_string(),
+                },
+                Message::User {
+                    content: "Is 17 prime?".to_string(),
+                },
+                Message::Assistant {
+                    reasoning_content: Some("No divisor up to 4 works.".to_string()),
+                    content: "Yes, 17 is prime.".to_string(),
+                },
+                Message::User {
+                    content: "  What about 21?  ".to_string(),
+                },
+            ],
+        }
+    }
+
+    fn render(name: &str, prefill: PrefillType, reasoning_enabled: ReasoningEnabled) -> String {
+        by_name(name)
+            .unwrap()
+            .render(&conversation(), prefill, reasoning_enabled)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_by_name() {
+        for name in TEMPLATE_NAMES {
+            assert!(by_name(name).is_some(), "{}", name);
+        }
+        assert!(by_name("mistral").is_none());
+
+        // The GLM templates are `ContextState` under another name.
+        let chat = conversation();
+        for (name, version) in [("glm-4.5", Version::GLM456), ("glm-4.7", Version::GLM47)] {
+            assert_eq!(
+                render(name, PrefillType::Canonical, ReasoningEnabled::Yes),
+                ContextState::new_with_version(ReasoningEnabled::Yes, version)
+                    .chat(&chat, PrefillType::Canonical)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_chatml() {
+        insta::assert_snapshot!(render(
+            "chatml",
+            PrefillType::Canonical,
+            ReasoningEnabled::Yes
+        ));
+    }
+
+    #[test]
+    fn test_llama3() {
+        insta::assert_snapshot!(render(
+            "llama3",
+            PrefillType::Canonical,
+            ReasoningEnabled::Yes
+        ));
+    }
+
+    #[test]
+    fn test_qwen3_thinking() {
+        insta::assert_snapshot!(render(
+            "qwen3",
+            PrefillType::Canonical,
+            ReasoningEnabled::Yes
+        ));
+    }
+
+    #[test]
+    fn test_qwen3_no_thinking() {
+        insta::assert_snapshot!(render(
+            "qwen3",
+            PrefillType::Canonical,
+            ReasoningEnabled::No
+        ));
+    }
+
+    #[test]
+    fn test_qwen3_agent_loop() {
+        // Reasoning after the last user message is kept, the final turn always gets a block.
+        let mut chat = conversation();
+        chat.messages.push(Message::Assistant {
+            reasoning_content: Some("\n21 = 3 * 7.\n".to_string()),
+            content: "Let me double check.".to_string(),
+        });
+        chat.messages.push(Message::Assistant {
+            reasoning_content: None,
+            content: "\nNo, 21 = 3 * 7.".to_string(),
+        });
+        insta::assert_snapshot!(
+            Qwen3
+                .render(&chat, PrefillType::None, ReasoningEnabled::Yes)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_qwen3_prefills() {
+        insta::assert_snapshot!(render(
+            "qwen3",
+            PrefillType::FullReasoning {
+                reasoning_content: "21 = 3 * 7.".to_string(),
+                content: "No,".to_string(),
+            },
+            ReasoningEnabled::Yes,
+        ));
+        assert!(
+            render(
+                "qwen3",
+                PrefillType::PartialReasoning {
+                    reasoning_content: "Check 3.".to_string(),
+                },
+                ReasoningEnabled::Yes,
+            )
+            .ends_with("<|im_start|>assistant\n<think>\nCheck 3.")
+        );
+        assert!(
+            render("qwen3", PrefillType::None, ReasoningEnabled::Yes).ends_with("<|im_end|>\n")
+        );
+    }
+}
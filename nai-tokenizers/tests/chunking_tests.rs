@@ -0,0 +1,152 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod chunking_tests {
+    use nai_tokenizers::chunking::*;
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, tokenize};
+
+    const DOCUMENT: &str = "# Tokenizers\n\n\
+        A tokenizer splits text into tokens. Each token is an id in the vocabulary! \
+        Models only ever see these ids.\n\n\
+        ## Byte pair encoding\n\n\
+        BPE starts from bytes and repeatedly merges the most frequent pair. \
+        The merge list is learned once, on a large corpus.\n\
+        Rare words end up as several tokens; common ones as one.\n\n\
+        ## 日本語\n\n\
+        トークナイザーはテキストをトークンに分割します。絵文字も使えます 🎉🎉🎉。\n";
+
+    fn options(max_tokens: usize, overlap_tokens: usize) -> ChunkOptions {
+        ChunkOptions {
+            max_tokens,
+            overlap_tokens,
+            ..ChunkOptions::default()
+        }
+    }
+
+    fn count(text: &str) -> usize {
+        tokenize(text, SpecialTokens::Ignore).unwrap().len()
+    }
+
+    fn check_chunks(text: &str, chunks: &[Chunk], options: ChunkOptions) {
+        assert!(!chunks.is_empty());
+        assert_eq!(chunks[0].start, 0);
+        assert_eq!(chunks[chunks.len() - 1].end, text.len());
+        for chunk in chunks {
+            assert_eq!(chunk.text, text[chunk.start..chunk.end]);
+            assert_eq!(chunk.tokens, count(&chunk.text));
+            assert!(chunk.tokens <= options.max_tokens, "{:?}", chunk);
+        }
+        for pair in chunks.windows(2) {
+            // No gaps, and every chunk gets further than the last.
+            assert!(pair[1].start <= pair[0].end);
+            assert!(pair[1].start > pair[0].start);
+            assert!(pair[1].end > pair[0].end);
+            let overlap = &text[pair[1].start..pair[0].end];
+            assert!(count(overlap) <= options.overlap_tokens);
+        }
+    }
+
+    #[test]
+    fn test_short_text_is_one_chunk() {
+        let chunks = chunk_text("Hello, world!", options(16, 0)).unwrap();
+        assert_eq!(
+            chunks,
+            [Chunk {
+                text: "Hello, world!".to_string(),
+                start: 0,
+                end: 13,
+                tokens: count("Hello, world!"),
+            }]
+        );
+        assert!(chunk_text("", options(16, 0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_chunks_fit_and_cover() {
+        for max_tokens in [4, 8, 16, 32, 64] {
+            let options = options(max_tokens, 0);
+            check_chunks(DOCUMENT, &chunk_text(DOCUMENT, options).unwrap(), options);
+        }
+    }
+
+    #[test]
+    fn test_overlap() {
+        for (max_tokens, overlap_tokens) in [(8, 2), (16, 4), (32, 8), (64, 16)] {
+            let options = options(max_tokens, overlap_tokens);
+            let chunks = chunk_text(DOCUMENT, options).unwrap();
+            check_chunks(DOCUMENT, &chunks, options);
+            assert!(chunks.windows(2).any(|pair| pair[1].start < pair[0].end));
+        }
+    }
+
+    /// Chunks `text` with room for exactly the tokens of `fits`, a prefix of it.
+    fn first_chunk(text: &str, fits: &str) -> Vec<Chunk> {
+        assert!(text.starts_with(fits));
+        let options = options(count(fits), 0);
+        let chunks = chunk_text(text, options).unwrap();
+        check_chunks(text, &chunks, options);
+        chunks
+    }
+
+    #[test]
+    fn test_prefers_structural_boundaries() {
+        let paragraph = "The first paragraph has a few short sentences. It ends here.";
+        let next = "The second paragraph keeps going for a while. It has more words.";
+
+        let text = format!("{}\n\n{}", paragraph, next);
+        let chunks = first_chunk(&text, &format!("{}\n\nThe second paragraph", paragraph));
+        assert_eq!(chunks[0].text, format!("{}\n\n", paragraph));
+
+        let text = format!("{}\n## Heading\n{}", paragraph, next);
+        let chunks = first_chunk(&text, &format!("{}\n## Heading\nThe second", paragraph));
+        assert_eq!(chunks[0].text, format!("{}\n", paragraph));
+        assert!(chunks[1].text.starts_with("## Heading"));
+
+        let chunks = first_chunk(next, "The second paragraph keeps going for a while. It has");
+        assert_eq!(
+            chunks[0].text,
+            "The second paragraph keeps going for a while. "
+        );
+
+        let chunks = first_chunk(
+            DOCUMENT,
+            &DOCUMENT[..DOCUMENT.find("A tokenizer").unwrap() + 5],
+        );
+        assert_eq!(chunks[0].text, "# Tokenizers\n\n");
+    }
+
+    #[test]
+    fn test_streaming_matches_whole() {
+        for (max_tokens, overlap_tokens) in [(8, 0), (16, 4), (32, 0)] {
+            let options = options(max_tokens, overlap_tokens);
+            let whole = chunk_text(DOCUMENT, options).unwrap();
+
+            let mut chunker = Chunker::new(options).unwrap();
+            let mut streamed = Vec::new();
+            let mut rest = DOCUMENT;
+            while !rest.is_empty() {
+                let mut at = rest.len().min(7);
+                while !rest.is_char_boundary(at) {
+                    at += 1;
+                }
+                streamed.extend(chunker.push(&rest[..at]).unwrap());
+                rest = &rest[at..];
+            }
+            streamed.extend(chunker.finish().unwrap());
+            assert_eq!(streamed, whole);
+        }
+    }
+
+    #[test]
+    fn test_long_run_without_boundaries() {
+        let text = "🎉".repeat(64);
+        let options = options(4, 0);
+        let chunks = chunk_text(&text, options).unwrap();
+        check_chunks(&text, &chunks, options);
+        assert!(chunks.iter().all(|c| c.text.chars().all(|ch| ch == '🎉')));
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(Chunker::new(options(0, 0)).is_err());
+        assert!(Chunker::new(options(8, 8)).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(feature = "compare")]
+mod compare_tests {
+    use nai_tokenizers::compare::*;
+    use nai_tokenizers::glm45_tokenizer::GLM45_TOKENIZER;
+    use nai_tokenizers::stats::InputFormat;
+    use serde_json::{Value, json};
+    use tokenizers::Tokenizer;
+
+    fn merge(left: &str, right: &str) -> (String, String) {
+        (left.to_string(), right.to_string())
+    }
+
+    /// The bundled tokenizer with its last merge dropped and the token that merge
+    /// produced renamed, one added token more, the first added token's special flag
+    /// flipped and the first two merges swapped.
+    fn modified_tokenizer() -> Tokenizer {
+        let mut json: Value =
+            serde_json::from_str(&GLM45_TOKENIZER.to_string(false).unwrap()).unwrap();
+
+        let merges = json["model"]["merges"].as_array_mut().unwrap();
+        let last = merges.pop().unwrap();
+        merges.swap(0, 1);
+        let product = match &last {
+            Value::String(merge) => merge.replacen(' ', "", 1),
+            _ => format!("{}{}", last[0].as_str().unwrap(), last[1].as_str().unwrap()),
+        };
+        let vocab = json["model"]["vocab"].as_object_mut().unwrap();
+        let id = vocab.remove(&product).unwrap();
+        vocab.insert("naitok_new".to_string(), id);
+
+        let added_tokens = json["added_tokens"].as_array_mut().unwrap();
+        let special = added_tokens[0]["special"].as_bool().unwrap();
+        added_tokens[0]["special"] = json!(!special);
+        added_tokens.push(json!({
+            "id": GLM45_TOKENIZER.get_vocab_size(true),
+            "content": "<|naitok_new|>",
+            "single_word": false,
+            "lstrip": false,
+            "rstrip": false,
+            "normalized": false,
+            "special": true,
+        }));
+
+        json.to_string().parse().unwrap()
+    }
+
+    #[test]
+    fn test_identical_tokenizers() {
+        let report = compare(
+            &GLM45_TOKENIZER,
+            &GLM45_TOKENIZER,
+            &CompareOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(report.vocab.base_size, report.vocab.other_size);
+        assert_eq!(report.vocab.added_count, 0);
+        assert_eq!(report.vocab.removed_count, 0);
+        assert_eq!(report.vocab.moved_count, 0);
+        assert!(report.added_tokens.added.is_empty());
+        assert!(report.added_tokens.removed.is_empty());
+        assert!(report.added_tokens.changed.is_empty());
+        assert_eq!(report.merges.base_count, report.merges.other_count);
+        assert_eq!(report.merges.first_difference, None);
+        assert_eq!(report.merges.reranked_count, 0);
+    }
+
+    #[test]
+    fn test_modified_tokenizer() {
+        let other = modified_tokenizer();
+        let report = compare(&GLM45_TOKENIZER, &other, &CompareOptions::default()).unwrap();
+
+        assert_eq!(report.vocab.added_count, 1);
+        assert_eq!(report.vocab.added[0].token, "naitok_new");
+        assert_eq!(report.vocab.removed_count, 1);
+        assert_eq!(report.vocab.removed[0].id, report.vocab.added[0].id);
+        assert_eq!(report.vocab.moved_count, 0);
+
+        assert_eq!(report.added_tokens.added.len(), 1);
+        assert_eq!(report.added_tokens.added[0].content, "<|naitok_new|>");
+        assert!(report.added_tokens.removed.is_empty());
+        assert_eq!(report.added_tokens.changed.len(), 1);
+        let change = &report.added_tokens.changed[0];
+        assert_eq!(change.base.id, change.other.id);
+        assert_ne!(change.base.special, change.other.special);
+
+        assert_eq!(report.merges.other_count, report.merges.base_count - 1);
+        assert_eq!(report.merges.first_difference, Some(0));
+        assert_eq!(report.merges.added_count, 0);
+        assert_eq!(report.merges.removed_count, 1);
+        assert_eq!(report.merges.removed[0].rank, report.merges.base_count - 1);
+        assert_eq!(report.merges.reranked_count, 2);
+    }
+
+    #[test]
+    fn test_max_examples() {
+        let other = modified_tokenizer();
+        let options = CompareOptions { max_examples: 0 };
+        let report = compare(&GLM45_TOKENIZER, &other, &options).unwrap();
+        assert_eq!(report.vocab.added_count, 1);
+        assert!(report.vocab.added.is_empty());
+        assert_eq!(report.merges.reranked_count, 2);
+        assert!(report.merges.reranked.is_empty());
+    }
+
+    #[test]
+    fn test_compare_merges() {
+        let base = [merge("a", "b"), merge("c", "d"), merge("e", "f")];
+
+        // An insertion shifts ranks but doesn't reorder anything.
+        let inserted = [
+            merge("a", "b"),
+            merge("x", "y"),
+            merge("c", "d"),
+            merge("e", "f"),
+        ];
+        let diff = compare_merges(&base, &inserted, 10);
+        assert_eq!(diff.first_difference, Some(1));
+        assert_eq!(diff.added_count, 1);
+        assert_eq!(
+            diff.added[0],
+            MergeEntry {
+                left: "x".to_string(),
+                right: "y".to_string(),
+                rank: 1,
+            }
+        );
+        assert_eq!(diff.reranked_count, 0);
+
+        let moved = [merge("c", "d"), merge("e", "f"), merge("a", "b")];
+        let diff = compare_merges(&base, &moved, 10);
+        assert_eq!(diff.first_difference, Some(0));
+        assert_eq!((diff.added_count, diff.removed_count), (0, 0));
+        assert!(diff.reranked.contains(&RankShift {
+            left: "a".to_string(),
+            right: "b".to_string(),
+            base_rank: 0,
+            other_rank: 2,
+        }));
+
+        let truncated = compare_merges(&base, &base[..2], 10);
+        assert_eq!(truncated.first_difference, Some(2));
+        assert_eq!(truncated.removed_count, 1);
+    }
+
+    #[test]
+    fn test_compare_corpus() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("corpus.jsonl");
+        let lines = [
+            r#"{"text": "hello world"}"#,
+            "not json",
+            r#"{"text": "<|naitok_new|> goes here"}"#,
+        ];
+        std::fs::write(&input, lines.join("\n")).unwrap();
+        let format = InputFormat::Jsonl {
+            field: "text".to_string(),
+        };
+
+        let same = compare_corpus(
+            &GLM45_TOKENIZER,
+            &GLM45_TOKENIZER,
+            std::slice::from_ref(&input),
+            &format,
+            10,
+        )
+        .unwrap();
+        assert_eq!(same.documents, 2);
+        assert_eq!(same.skipped, 1);
+        assert_eq!(same.unchanged, 2);
+        assert_eq!(same.base_tokens, same.other_tokens);
+        assert_eq!(same.ratio, 1.0);
+        assert!(same.largest_shifts.is_empty());
+
+        // The new added token collapses into a single token.
+        let other = modified_tokenizer();
+        let diff = compare_corpus(
+            &GLM45_TOKENIZER,
+            &other,
+            std::slice::from_ref(&input),
+            &format,
+            10,
+        )
+        .unwrap();
+        let shift = &diff.largest_shifts[0];
+        assert_eq!(shift.document, 1);
+        assert!(shift.other_tokens < shift.base_tokens);
+        assert!(diff.ratio < 1.0);
+    }
+}
@@ -0,0 +1,149 @@
+#[cfg(feature = "dataset")]
+mod dataset_tests {
+    use nai_tokenizers::dataset::*;
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, detokenize};
+
+    const CHAT: &str = r#"{"messages":[{"role":"system","content":"Be brief."},{"role":"user","content":"Is 17 prime?"},{"role":"assistant","content":"Yes.","reasoning_content":"No divisors."},{"role":"user","content":"And 18?"},{"role":"assistant","content":"No."}]}"#;
+
+    fn masked_text(document: &TokenizedDocument) -> String {
+        let ids: Vec<u32> = document
+            .tokens
+            .iter()
+            .zip(&document.loss_mask)
+            .filter(|&(_, &m)| m == 1)
+            .map(|(&t, _)| t)
+            .collect();
+        detokenize(&ids, SpecialTokens::Keep).unwrap()
+    }
+
+    #[test]
+    fn test_loss_mask_covers_assistant_turns() {
+        let chat = parse_chat(CHAT).unwrap();
+        let document = tokenize_chat(&chat, &PreprocessConfig::default()).unwrap();
+        assert_eq!(document.tokens.len(), document.loss_mask.len());
+        assert_eq!(
+            masked_text(&document),
+            "<think>No divisors.</think>\nYes.<|user|><think></think>\nNo.<|endoftext|>"
+        );
+    }
+
+    #[test]
+    fn test_loss_mask_strips_reasoning() {
+        let chat = parse_chat(CHAT).unwrap();
+        let config = PreprocessConfig {
+            keep_all_reasoning: false,
+            ..PreprocessConfig::default()
+        };
+        let document = tokenize_chat(&chat, &config).unwrap();
+        assert_eq!(
+            masked_text(&document),
+            "<think></think>\nYes.<|user|><think></think>\nNo.<|endoftext|>"
+        );
+    }
+
+    #[test]
+    fn test_parse_content_shapes() {
+        let config = PreprocessConfig::default();
+        let text = r#"{"messages":[{"role":"user","content":"Hi there"},{"role":"assistant","content":""}]}"#;
+        let parts = r#"{"messages":[{"role":"user","content":[{"type":"text","text":"Hi "},{"type":"image_url","image_url":{"url":"x"}},{"type":"text","text":"there"}]},{"role":"assistant","content":null,"tool_calls":[]}]}"#;
+        let expected = tokenize_chat(&parse_chat(text).unwrap(), &config).unwrap();
+        let document = tokenize_chat(&parse_chat(parts).unwrap(), &config).unwrap();
+        assert_eq!(document.tokens, expected.tokens);
+        assert_eq!(document.loss_mask, expected.loss_mask);
+    }
+
+    fn document(len: usize) -> TokenizedDocument {
+        TokenizedDocument {
+            tokens: vec![7; len],
+            loss_mask: vec![1; len],
+        }
+    }
+
+    #[test]
+    fn test_pack_pads_last_block() {
+        let shard = pack(&[document(3), document(4)], 4, 0, false);
+        assert_eq!(shard.blocks, 2);
+        assert_eq!(shard.doc_starts, vec![0, 3]);
+        assert_eq!(shard.padding, 1);
+        assert_eq!(shard.tokens[7], 0);
+        assert_eq!(shard.loss_mask[7], 0);
+    }
+
+    #[test]
+    fn test_pack_drop_last() {
+        let shard = pack(&[document(3), document(3)], 4, 0, true);
+        assert_eq!(shard.blocks, 1);
+        assert_eq!(shard.tokens.len(), 4);
+        assert_eq!(shard.doc_starts, vec![0, 3]);
+        assert_eq!(shard.dropped, 2);
+
+        let shard = pack(&[document(4), document(3)], 4, 0, true);
+        assert_eq!(shard.doc_starts, vec![0]);
+    }
+
+    #[test]
+    fn test_npy_header_alignment() {
+        let header = npy_header("<u4", &[3, 4096]);
+        assert_eq!(header.len() % 64, 0);
+        assert!(header.starts_with(b"\x93NUMPY\x01\x00"));
+        assert!(header.ends_with(b"\n"));
+        let dict = String::from_utf8(header[10..].to_vec()).unwrap();
+        assert!(dict.starts_with("{'descr': '<u4', 'fortran_order': False, 'shape': (3, 4096), }"));
+    }
+
+    #[test]
+    fn test_preprocess_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("chats.jsonl");
+        let lines = [CHAT, "not json", "", CHAT, CHAT];
+        std::fs::write(&input, lines.join("\n")).unwrap();
+        let output = dir.path().join("out");
+        let config = PreprocessConfig {
+            seq_len: 16,
+            docs_per_shard: 2,
+            ..PreprocessConfig::default()
+        };
+
+        let mut progress = 0;
+        let stats = preprocess(std::slice::from_ref(&input), &output, &config, |_| {
+            progress += 1
+        })
+        .unwrap();
+        assert_eq!(progress, 3);
+        assert_eq!(stats.documents, 3);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.shards, 3);
+        assert_eq!(stats.blocks * 16, stats.tokens + stats.padding_tokens);
+        assert!(output.join("shard_00002.tokens.npy").exists());
+        assert!(output.join("shard_00002.doc_starts.npy").exists());
+
+        // Nothing left to do on a second run.
+        let resumed =
+            preprocess(std::slice::from_ref(&input), &output, &config, |_| panic!()).unwrap();
+        assert_eq!(resumed, stats);
+
+        let other = PreprocessConfig {
+            seq_len: 32,
+            ..config
+        };
+        assert!(preprocess(&[input], &output, &other, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_preprocess_skips_invalid_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("chats.jsonl");
+        let mut bytes = CHAT.as_bytes().to_vec();
+        bytes.extend_from_slice(b"\n{\"messages\":\xff}\n");
+        bytes.extend_from_slice(CHAT.as_bytes());
+        std::fs::write(&input, bytes).unwrap();
+        let config = PreprocessConfig {
+            seq_len: 16,
+            ..PreprocessConfig::default()
+        };
+
+        let stats = preprocess(&[input], &dir.path().join("out"), &config, |_| {}).unwrap();
+        assert_eq!(stats.documents, 2);
+        assert_eq!(stats.skipped, 1);
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod detokenization_tests {
+    use nai_tokenizers::detokenization::*;
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, detokenize, tokenize, vocab_size};
+    use nai_tokenizers::special_tokens::DecodePolicy;
+
+    fn decode(ids: &[u32], options: &DetokenizeOptions) -> anyhow::Result<Detokenized> {
+        detokenize_with_options(ids, &DecodePolicy::default(), options)
+    }
+
+    /// Ids for "héllo" with the "é" cut after its first byte.
+    fn partial_utf8() -> Vec<u32> {
+        let ids = tokenize("h\u{e9}llo", SpecialTokens::Ignore).unwrap();
+        let first = ids
+            .iter()
+            .position(|&id| {
+                let decoded = detokenize(&[id], SpecialTokens::Keep).unwrap();
+                decoded.contains('\u{fffd}')
+            })
+            .expect("expected a token holding part of \"é\"");
+        let mut partial = ids[..=first].to_vec();
+        partial.extend(tokenize("llo", SpecialTokens::Ignore).unwrap());
+        partial
+    }
+
+    #[test]
+    fn test_defaults_match_detokenize() {
+        let text = "<|user|>hello, world! 日本語";
+        let ids = tokenize(text, SpecialTokens::Keep).unwrap();
+        for mode in [SpecialTokens::Keep, SpecialTokens::Ignore] {
+            let decoded =
+                detokenize_with_options(&ids, &mode.into(), &DetokenizeOptions::default()).unwrap();
+            assert_eq!(decoded.text, detokenize(&ids, mode).unwrap());
+            assert!(!decoded.strict);
+            assert_eq!(decoded.invalid_bytes, InvalidBytes::Replace);
+            assert_eq!(decoded.invalid_sequences, 0);
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut ids = tokenize("hi", SpecialTokens::Ignore).unwrap();
+        ids.push(vocab_size() as u32);
+        ids.push(u32::MAX);
+
+        let lenient = decode(&ids, &DetokenizeOptions::default()).unwrap();
+        assert_eq!(lenient.text, "hi");
+        assert_eq!(lenient.dropped_ids, vec![vocab_size() as u32, u32::MAX]);
+
+        let strict = DetokenizeOptions {
+            strict: true,
+            ..DetokenizeOptions::default()
+        };
+        let error = decode(&ids, &strict).unwrap_err();
+        assert!(error.to_string().contains("out of range"), "{}", error);
+    }
+
+    /// Every binding decodes through one of these, so they must agree on ids outside
+    /// the vocabulary: dropped by default, an error when strict.
+    #[test]
+    fn test_out_of_range_every_entry_point() {
+        let mut ids = tokenize("hi", SpecialTokens::Ignore).unwrap();
+        ids.push(vocab_size() as u32);
+        ids.push(u32::MAX);
+        let policy = DecodePolicy::default();
+
+        assert_eq!(detokenize(&ids, SpecialTokens::Keep).unwrap(), "hi");
+        assert_eq!(detokenize(&[u32::MAX], SpecialTokens::Keep).unwrap(), "");
+        assert_eq!(
+            decode(&ids, &DetokenizeOptions::default()).unwrap().text,
+            "hi"
+        );
+        assert_eq!(detokenize_bytes(&ids, &policy, false).unwrap(), b"hi");
+
+        let strict = DetokenizeOptions {
+            strict: true,
+            ..DetokenizeOptions::default()
+        };
+        assert!(decode(&ids, &strict).is_err());
+        assert!(detokenize_bytes(&ids, &policy, true).is_err());
+    }
+
+    #[test]
+    fn test_invalid_bytes() {
+        let ids = partial_utf8();
+        let with = |invalid_bytes| DetokenizeOptions {
+            invalid_bytes,
+            ..DetokenizeOptions::default()
+        };
+
+        let replaced = decode(&ids, &with(InvalidBytes::Replace)).unwrap();
+        assert_eq!(replaced.text, "h\u{fffd}llo");
+        assert_eq!(replaced.invalid_sequences, 1);
+
+        let dropped = decode(&ids, &with(InvalidBytes::Drop)).unwrap();
+        assert_eq!(dropped.text, "hllo");
+        assert_eq!(dropped.invalid_sequences, 1);
+
+        let error = decode(&ids, &with(InvalidBytes::Error)).unwrap_err();
+        assert!(error.to_string().contains("byte 1"), "{}", error);
+    }
+
+    #[test]
+    fn test_clean_up_tokenization_spaces() {
+        assert_eq!(
+            clean_up_tokenization("Hello , world ! I 'm here . Isn't it ?"),
+            "Hello, world! I'm here. Isn't it?"
+        );
+        let ids = tokenize("Hi , there", SpecialTokens::Ignore).unwrap();
+        let options = DetokenizeOptions {
+            clean_up_tokenization_spaces: Some(true),
+            ..DetokenizeOptions::default()
+        };
+        let decoded = decode(&ids, &options).unwrap();
+        assert_eq!(decoded.text, "Hi, there");
+        assert!(decoded.clean_up_tokenization_spaces);
+    }
+}
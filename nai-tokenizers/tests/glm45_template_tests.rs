@@ -116,7 +116,9 @@ mod glm45_template_tests {
             ],
         };
 
-        let output = ContextState::new(ReasoningEnabled::No).chat(&chat, PrefillType::None);
+        let output = ContextState::new(ReasoningEnabled::No)
+            .chat(&chat, PrefillType::None)
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
@@ -134,8 +136,9 @@ mod glm45_template_tests {
             ],
         };
 
-        let output =
-            ContextState::new(ReasoningEnabled::No).chat(&chat, PrefillType::Canonical);
+        let output = ContextState::new(ReasoningEnabled::No)
+            .chat(&chat, PrefillType::Canonical)
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
@@ -153,8 +156,9 @@ mod glm45_template_tests {
             ],
         };
 
-        let output =
-            ContextState::new(ReasoningEnabled::Yes).chat(&chat, PrefillType::Canonical);
+        let output = ContextState::new(ReasoningEnabled::Yes)
+            .chat(&chat, PrefillType::Canonical)
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
@@ -169,12 +173,14 @@ mod glm45_template_tests {
             ],
         };
 
-        let output = ContextState::new(ReasoningEnabled::Yes).chat(
-            &chat,
-            PrefillType::PartialReasoning {
-                reasoning_content: "Let me check divisibility...".to_string(),
-            },
-        );
+        let output = ContextState::new(ReasoningEnabled::Yes)
+            .chat(
+                &chat,
+                PrefillType::PartialReasoning {
+                    reasoning_content: "Let me check divisibility...".to_string(),
+                },
+            )
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
@@ -189,13 +195,15 @@ mod glm45_template_tests {
             ],
         };
 
-        let output = ContextState::new(ReasoningEnabled::Yes).chat(
-            &chat,
-            PrefillType::FullReasoning {
-                reasoning_content: "144 / 12 = 12".to_string(),
-                content: "The answer is".to_string(),
-            },
-        );
+        let output = ContextState::new(ReasoningEnabled::Yes)
+            .chat(
+                &chat,
+                PrefillType::FullReasoning {
+                    reasoning_content: "144 / 12 = 12".to_string(),
+                    content: "The answer is".to_string(),
+                },
+            )
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
@@ -220,8 +228,9 @@ mod glm45_template_tests {
             ],
         };
 
-        let output =
-            ContextState::new(ReasoningEnabled::No).chat(&chat, PrefillType::Canonical);
+        let output = ContextState::new(ReasoningEnabled::No)
+            .chat(&chat, PrefillType::Canonical)
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
@@ -243,8 +252,9 @@ mod glm45_template_tests {
             ],
         };
 
-        let output =
-            ContextState::new(ReasoningEnabled::Yes).chat(&chat, PrefillType::Canonical);
+        let output = ContextState::new(ReasoningEnabled::Yes)
+            .chat(&chat, PrefillType::Canonical)
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
@@ -263,7 +273,9 @@ mod glm45_template_tests {
             ],
         };
 
-        let output = ContextState::new(ReasoningEnabled::No).chat(&chat, PrefillType::None);
+        let output = ContextState::new(ReasoningEnabled::No)
+            .chat(&chat, PrefillType::None)
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
@@ -281,8 +293,9 @@ mod glm45_template_tests {
             ],
         };
 
-        let output =
-            ContextState::new(ReasoningEnabled::No).chat(&chat, PrefillType::Canonical);
+        let output = ContextState::new(ReasoningEnabled::No)
+            .chat(&chat, PrefillType::Canonical)
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
@@ -314,9 +327,290 @@ mod glm45_template_tests {
             ],
         };
 
-        let output =
-            ContextState::new(ReasoningEnabled::No).chat(&chat, PrefillType::Canonical);
+        let output = ContextState::new(ReasoningEnabled::No)
+            .chat(&chat, PrefillType::Canonical)
+            .unwrap();
+
+        insta::assert_snapshot!(output);
+    }
+
+    #[test]
+    fn test_19_chat_multimodal_image() {
+        let chat = Chat {
+            messages: vec![Message::MultimodalUser {
+                content: vec![
+                    ContentPart::Image {
+                        width: 56,
+                        height: 28,
+                    },
+                    ContentPart::Text {
+                        text: "What is in this image?".to_string(),
+                    },
+                ],
+            }],
+        };
+
+        let output = ContextState::new(ReasoningEnabled::Yes)
+            .chat(&chat, PrefillType::Canonical)
+            .unwrap();
+
+        insta::assert_snapshot!(output);
+    }
+
+    #[test]
+    fn test_20_chat_multimodal_video() {
+        let chat = Chat {
+            messages: vec![Message::MultimodalUser {
+                content: vec![
+                    ContentPart::Text {
+                        text: "Describe this clip.".to_string(),
+                    },
+                    ContentPart::Video {
+                        frames: 4,
+                        width: 28,
+                        height: 28,
+                    },
+                ],
+            }],
+        };
+
+        let output = ContextState::new(ReasoningEnabled::Yes)
+            .chat(&chat, PrefillType::Canonical)
+            .unwrap();
 
         insta::assert_snapshot!(output);
     }
+
+    #[test]
+    fn test_vision_config_placeholder_counts() {
+        let vision = VisionConfig::default();
+        assert_eq!(vision.image_placeholders(28, 28), 1);
+        assert_eq!(vision.image_placeholders(448, 224), 16 * 8);
+        // Partial patches round up.
+        assert_eq!(vision.image_placeholders(30, 28), 2);
+        assert_eq!(vision.video_groups(5), 3);
+
+        // Degenerate configs saturate instead of overflowing or dividing by zero.
+        let vision = VisionConfig {
+            patch_size: 1 << 16,
+            spatial_merge_size: 1 << 16,
+            temporal_patch_size: 0,
+        };
+        assert_eq!(vision.image_placeholders(u32::MAX, 1), 1);
+        assert_eq!(vision.video_groups(u32::MAX), u32::MAX as usize);
+    }
+
+    #[test]
+    fn test_placeholder_limits() {
+        let image = |width, height| Message::MultimodalUser {
+            content: vec![ContentPart::Image { width, height }],
+        };
+        let limits = PlaceholderLimits {
+            per_part: 16,
+            per_chat: 24,
+        };
+        let render = |messages| {
+            ContextState::new(ReasoningEnabled::No)
+                .with_placeholder_limits(limits)
+                .chat(&Chat { messages }, PrefillType::None)
+        };
+
+        let output = render(vec![image(112, 112), image(28, 28)]).unwrap();
+        assert_eq!(output.matches("<|image|>").count(), 17);
+        assert_eq!(
+            render(vec![image(140, 112)]),
+            Err(PlaceholderLimitError::Part {
+                placeholders: 20,
+                limit: 16
+            })
+        );
+        assert_eq!(
+            render(vec![image(112, 112), image(112, 112)]),
+            Err(PlaceholderLimitError::Chat {
+                placeholders: 32,
+                limit: 24
+            })
+        );
+        // A video's groups count as one part.
+        let video = Message::MultimodalUser {
+            content: vec![ContentPart::Video {
+                frames: 10,
+                width: 56,
+                height: 56,
+            }],
+        };
+        assert_eq!(
+            render(vec![video]),
+            Err(PlaceholderLimitError::Part {
+                placeholders: 20,
+                limit: 16
+            })
+        );
+    }
+
+    fn agent_loop_chat() -> Chat {
+        Chat {
+            messages: vec![
+                Message::System {
+                    content: "You are a research agent.".to_string(),
+                },
+                Message::User {
+                    content: "What's the capital of France?".to_string(),
+                },
+                Message::Assistant {
+                    reasoning_content: Some("Easy, no lookup needed.".to_string()),
+                    content: "Paris.".to_string(),
+                },
+                Message::User {
+                    content: "How far is it from Lyon?".to_string(),
+                },
+                Message::Assistant {
+                    reasoning_content: Some("Lyon is about 400 km southeast.".to_string()),
+                    content: "About 400 km.".to_string(),
+                },
+                Message::User {
+                    content: "And its population?".to_string(),
+                },
+                Message::Assistant {
+                    reasoning_content: Some("I should look this up.".to_string()),
+                    content: "Searching for the population of Paris.".to_string(),
+                },
+                Message::Assistant {
+                    reasoning_content: Some("The search says about 2.1 million.".to_string()),
+                    content: "About 2.1 million people live in Paris.".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_21_chat_reasoning_retention() {
+        let chat = agent_loop_chat();
+        for (name, retention) in [
+            ("strip_past", ReasoningRetention::StripPast),
+            ("since_last_user", ReasoningRetention::SinceLastUser),
+            ("last_2_turns", ReasoningRetention::LastTurns(2)),
+            ("all", ReasoningRetention::All),
+        ] {
+            for (version_name, version) in [("glm45", Version::GLM456), ("glm47", Version::GLM47)] {
+                let output = ContextState::new_with_version(ReasoningEnabled::Yes, version)
+                    .with_reasoning_retention(retention)
+                    .chat(&chat, PrefillType::Canonical)
+                    .unwrap();
+                insta::assert_snapshot!(
+                    format!("21_chat_reasoning_retention_{}_{}", name, version_name),
+                    output
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reasoning_retention_equivalences() {
+        let chat = agent_loop_chat();
+        let render = |retention| {
+            ContextState::new(ReasoningEnabled::Yes)
+                .with_reasoning_retention(retention)
+                .chat(&chat, PrefillType::Canonical)
+                .unwrap()
+        };
+        assert_eq!(
+            render(ReasoningRetention::LastTurns(0)),
+            render(ReasoningRetention::StripPast)
+        );
+        assert_eq!(
+            render(ReasoningRetention::LastTurns(1)),
+            render(ReasoningRetention::SinceLastUser)
+        );
+        // The fixture has three user turns, so two turns sit strictly between one and all.
+        let last_2 = render(ReasoningRetention::LastTurns(2));
+        assert_ne!(last_2, render(ReasoningRetention::LastTurns(1)));
+        assert_ne!(last_2, render(ReasoningRetention::All));
+        assert_eq!(
+            render(ReasoningRetention::LastTurns(3)),
+            render(ReasoningRetention::All)
+        );
+        assert_eq!(
+            render(ReasoningRetention::All),
+            ContextState::new(ReasoningEnabled::Yes)
+                .chat_with_options(&chat, PrefillType::Canonical, true)
+                .unwrap()
+        );
+        // Retention doesn't bring reasoning back when it's disabled.
+        assert_eq!(
+            ContextState::new(ReasoningEnabled::No)
+                .with_reasoning_retention(ReasoningRetention::All)
+                .chat(&chat, PrefillType::Canonical)
+                .unwrap(),
+            ContextState::new(ReasoningEnabled::No)
+                .chat(&chat, PrefillType::Canonical)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_empty_chat() {
+        let chat = Chat { messages: vec![] };
+        for ignore_message_position in [false, true] {
+            let output = ContextState::new(ReasoningEnabled::Yes)
+                .chat_with_options(&chat, PrefillType::None, ignore_message_position)
+                .unwrap();
+            assert_eq!(output, "[gMASK]<sop>");
+        }
+        let output = ContextState::new(ReasoningEnabled::Yes)
+            .chat(&chat, PrefillType::Canonical)
+            .unwrap();
+        assert_eq!(output, "[gMASK]<sop><|assistant|>\n");
+    }
+
+    // Regression tests for inputs that used to crash the renderer.
+
+    #[test]
+    fn test_huge_images_and_videos() {
+        // The image used to repeat `<|image|>` about 2.3e16 times, a capacity overflow
+        // panic, and the video to render 2^31 image blocks.
+        let chat = |part| Chat {
+            messages: vec![Message::MultimodalUser {
+                content: vec![part],
+            }],
+        };
+        let render = |part, vision| {
+            ContextState::new(ReasoningEnabled::No)
+                .with_vision_config(vision)
+                .chat(&chat(part), PrefillType::Canonical)
+        };
+        let huge_image = ContentPart::Image {
+            width: u32::MAX,
+            height: u32::MAX,
+        };
+        assert!(matches!(
+            render(huge_image, VisionConfig::default()),
+            Err(PlaceholderLimitError::Part { .. })
+        ));
+        let long_video = ContentPart::Video {
+            frames: u32::MAX,
+            width: 28,
+            height: 28,
+        };
+        assert!(matches!(
+            render(long_video, VisionConfig::default()),
+            Err(PlaceholderLimitError::Part { .. })
+        ));
+        let small_image = ContentPart::Image {
+            width: 1 << 10,
+            height: 1 << 10,
+        };
+        let tiny_patches = VisionConfig {
+            patch_size: 1,
+            spatial_merge_size: 1,
+            temporal_patch_size: 0,
+        };
+        assert_eq!(
+            render(small_image, tiny_patches),
+            Err(PlaceholderLimitError::Part {
+                placeholders: 1 << 20,
+                limit: MAX_PART_PLACEHOLDERS
+            })
+        );
+    }
 }
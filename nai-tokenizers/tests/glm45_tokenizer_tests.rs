@@ -6,8 +6,8 @@ mod glm45_tokenizer_tests {
     fn test_tokenize() {
         let input = "[gMASK]this is a test where da goose is cooked<|system|>no<|user|>yes<|assistant|>maybe";
         let expected_output = vec![
-            151331, 574, 374, 264, 1273, 1380, 2994, 61701, 374, 28998, 151335, 2152, 151336,
-            9689, 151337, 36569,
+            151331, 574, 374, 264, 1273, 1380, 2994, 61701, 374, 28998, 151335, 2152, 151336, 9689,
+            151337, 36569,
         ];
         let output = tokenize(input, SpecialTokens::Keep).unwrap();
         assert_eq!(output, expected_output);
@@ -16,11 +16,77 @@ mod glm45_tokenizer_tests {
     #[test]
     fn test_detokenize() {
         let input = vec![
-            151331, 574, 374, 264, 1273, 1380, 2994, 61701, 374, 28998, 151335, 2152, 151336,
-            9689, 151337, 36569,
+            151331, 574, 374, 264, 1273, 1380, 2994, 61701, 374, 28998, 151335, 2152, 151336, 9689,
+            151337, 36569,
         ];
         let expected_output = "[gMASK]this is a test where da goose is cooked<|system|>no<|user|>yes<|assistant|>maybe";
         let output = detokenize(&input, SpecialTokens::Keep).unwrap();
         assert_eq!(output, expected_output);
     }
+
+    #[test]
+    fn test_placeholder_spans() {
+        let input = "<|begin_of_image|><|image|><|image|><|end_of_image|>hi<|begin_of_video|><|begin_of_image|><|image|><|end_of_image|><|begin_of_image|><|image|><|end_of_image|><|end_of_video|>";
+        let ids = tokenize(input, SpecialTokens::Keep).unwrap();
+        let spans = placeholder_spans(&ids).unwrap();
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[0].kind, PlaceholderKind::Image);
+        assert_eq!((spans[0].start, spans[0].len), (1, 2));
+        assert_eq!(spans[1].kind, PlaceholderKind::Video);
+        assert_eq!(spans[1].len, 1);
+        assert_eq!(spans[2].kind, PlaceholderKind::Video);
+    }
+
+    #[test]
+    fn test_detokenize_out_of_range() {
+        // Ids outside the vocabulary are dropped, as transformers' `decode` does.
+        let ids = [0, u32::MAX, vocab_size() as u32, 151329];
+        for mode in [SpecialTokens::Keep, SpecialTokens::Ignore] {
+            assert_eq!(
+                detokenize(&ids, mode).unwrap(),
+                detokenize(&[0, 151329], mode).unwrap()
+            );
+        }
+        assert_eq!(token_bytes(u32::MAX), None);
+        assert!(!is_special_token(u32::MAX));
+    }
+
+    #[test]
+    fn test_tokenize_bytes() {
+        let text = "hello wörld <|user|> 日本語";
+        for mode in [SpecialTokens::Keep, SpecialTokens::Ignore] {
+            assert_eq!(
+                tokenize_bytes(text.as_bytes(), mode).unwrap(),
+                tokenize(text, mode).unwrap()
+            );
+        }
+
+        let mut input = b"log: \xff\xfe\x00 caf".to_vec();
+        input.extend_from_slice(&"é".as_bytes()[..1]);
+        input.extend_from_slice(b" \x80end");
+        let ids = tokenize_bytes(&input, SpecialTokens::Ignore).unwrap();
+        assert_eq!(
+            detokenize_bytes(&ids, SpecialTokens::Ignore).unwrap(),
+            input
+        );
+        assert!(
+            tokenize_bytes(b"", SpecialTokens::Ignore)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_detokenize_bytes() {
+        let ids = tokenize("<|user|>hi", SpecialTokens::Keep).unwrap();
+        assert_eq!(
+            detokenize_bytes(&ids, SpecialTokens::Keep).unwrap(),
+            b"<|user|>hi"
+        );
+        assert_eq!(
+            detokenize_bytes(&ids, SpecialTokens::Ignore).unwrap(),
+            b"hi"
+        );
+        assert!(detokenize_bytes(&[u32::MAX], SpecialTokens::Keep).is_err());
+    }
 }
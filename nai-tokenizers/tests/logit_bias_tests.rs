@@ -0,0 +1,101 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod logit_bias_tests {
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, detokenize, tokenize};
+    use nai_tokenizers::logit_bias::*;
+
+    #[test]
+    fn test_variants() {
+        assert_eq!(
+            variants("cat", BiasOptions::default()),
+            vec!["cat", " cat", "Cat", " Cat", "CAT", " CAT"]
+        );
+        assert_eq!(
+            variants(
+                " goose",
+                BiasOptions {
+                    leading_space: false,
+                    capitalization: false,
+                    ..BiasOptions::default()
+                }
+            ),
+            vec!["goose"]
+        );
+        assert!(variants("   ", BiasOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_expand_covers_every_variant() {
+        let bias = expand(
+            &["test", "supercalifragilisticexpialidocious"],
+            BiasOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(bias.entries.len(), 2);
+        for variant in bias.entries.iter().flat_map(|e| &e.variants) {
+            let tokens = tokenize(&variant.text, SpecialTokens::Keep).unwrap();
+            assert_eq!(variant.tokens, tokens);
+            match tokens.as_slice() {
+                [id] => assert!(bias.single_tokens.contains(id)),
+                _ => assert!(bias.sequences.contains(&tokens)),
+            }
+        }
+        for split in bias
+            .entries
+            .iter()
+            .flat_map(|e| &e.variants)
+            .flat_map(|v| &v.split_forms)
+        {
+            assert!(split.len() == 1 || bias.sequences.contains(split));
+        }
+        assert!(bias.single_tokens.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(
+            bias.multi_token_only,
+            vec!["supercalifragilisticexpialidocious"]
+        );
+    }
+
+    #[test]
+    fn test_bias_map_later_wins() {
+        let options = BiasOptions {
+            leading_space: true,
+            capitalization: false,
+            ..BiasOptions::default()
+        };
+        let map = bias_map(&[("test", -100.0), ("test", 5.0)], options).unwrap();
+        let spaced = tokenize(" test", SpecialTokens::Keep).unwrap();
+        assert_eq!(spaced.len(), 1);
+        assert_eq!(map.get(&spaced[0]), Some(&5.0));
+        assert!(map.values().all(|&b| b == 5.0));
+    }
+
+    #[test]
+    fn test_split_forms() {
+        let options = BiasOptions {
+            max_split_forms: 100,
+            max_extra_tokens: 4,
+            ..BiasOptions::default()
+        };
+        let canonical = tokenize(" test", SpecialTokens::Keep).unwrap();
+        let splits = split_forms(" test", &canonical, options);
+        assert!(!splits.is_empty());
+        assert!(!splits.contains(&canonical));
+        assert!(splits.windows(2).all(|w| w[0].len() <= w[1].len()));
+        for split in &splits {
+            assert_eq!(detokenize(split, SpecialTokens::Keep).unwrap(), " test");
+            assert!(split.len() <= canonical.len() + 4);
+        }
+
+        // One token per character is always a valid spelling.
+        let per_char: Vec<u32> = " test"
+            .chars()
+            .flat_map(|c| tokenize(&c.to_string(), SpecialTokens::Keep).unwrap())
+            .collect();
+        assert!(per_char == canonical || splits.contains(&per_char));
+
+        let none = BiasOptions {
+            max_split_forms: 0,
+            ..options
+        };
+        assert!(split_forms(" test", &canonical, none).is_empty());
+    }
+}
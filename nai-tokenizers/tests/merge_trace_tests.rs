@@ -0,0 +1,85 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod merge_trace_tests {
+    use nai_tokenizers::merge_trace::*;
+
+    fn pieces(tokens: &[nai_tokenizers::pretokenization::SplitToken]) -> Vec<&str> {
+        tokens.iter().map(|token| token.token.as_str()).collect()
+    }
+
+    #[test]
+    fn test_replay_matches_model() {
+        let text = "Hello world, tokenization is 12345 fun!! 日本語 🎉";
+        let trace = trace(text, &TraceOptions::default()).unwrap();
+        assert!(!trace.splits.is_empty());
+        for split in &trace.splits {
+            assert!(!split.added);
+            assert_eq!(split.initial.len(), split.text.len(), "one symbol per byte");
+            let last = split
+                .steps
+                .last()
+                .map_or(&split.initial, |step| &step.symbols);
+            assert_eq!(
+                last.iter().map(String::as_str).collect::<Vec<_>>(),
+                pieces(&split.tokens),
+                "{:?}",
+                split
+            );
+        }
+    }
+
+    #[test]
+    fn test_steps() {
+        let trace = trace("hello", &TraceOptions::default()).unwrap();
+        let split = &trace.splits[0];
+        assert_eq!(split.initial, ["h", "e", "l", "l", "o"]);
+        let mut symbols = split.initial.clone();
+        for step in &split.steps {
+            // Each step merges the pair at `position` into one symbol.
+            assert_eq!(symbols[step.position], step.left);
+            assert_eq!(symbols[step.position + 1], step.right);
+            symbols.remove(step.position + 1);
+            symbols[step.position] = format!("{}{}", step.left, step.right);
+            assert_eq!(symbols, step.symbols);
+        }
+        assert_eq!(split.steps.len(), split.initial.len() - split.tokens.len());
+    }
+
+    #[test]
+    fn test_alternatives() {
+        let options = TraceOptions {
+            max_alternatives: 3,
+            max_extra_tokens: 2,
+        };
+        let traced = trace("tokenization", &options).unwrap();
+        let split = &traced.splits[0];
+        assert!(split.alternatives.len() <= 3);
+        for alternative in &split.alternatives {
+            assert_ne!(pieces(alternative), pieces(&split.tokens));
+            assert_eq!(pieces(alternative).concat(), split.text);
+            assert!(alternative.len() <= split.tokens.len() + 2);
+        }
+        assert!(
+            split
+                .alternatives
+                .windows(2)
+                .all(|pair| pair[0].len() <= pair[1].len())
+        );
+
+        let none = TraceOptions {
+            max_alternatives: 0,
+            ..TraceOptions::default()
+        };
+        let none = trace("tokenization", &none).unwrap();
+        assert!(none.splits[0].alternatives.is_empty());
+    }
+
+    #[test]
+    fn test_added_tokens_skip_bpe() {
+        let trace = trace("<|user|>hi", &TraceOptions::default()).unwrap();
+        let user = &trace.splits[0];
+        assert!(user.added);
+        assert!(user.steps.is_empty());
+        assert_eq!(pieces(&user.tokens), ["<|user|>"]);
+        assert!(!trace.splits[1].added);
+    }
+}
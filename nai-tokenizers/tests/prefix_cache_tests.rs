@@ -0,0 +1,92 @@
+#[cfg(all(feature = "glm45_tokenizer", feature = "glm45_template"))]
+mod prefix_cache_tests {
+    use nai_tokenizers::glm45_template::*;
+    use nai_tokenizers::prefix_cache::*;
+
+    fn first_turn() -> Chat {
+        Chat {
+            messages: vec![Message::User {
+                content: "Is 17 prime?".to_string(),
+            }],
+        }
+    }
+
+    fn second_turn() -> Chat {
+        Chat {
+            messages: vec![
+                Message::User {
+                    content: "Is 17 prime?".to_string(),
+                },
+                Message::Assistant {
+                    content: "Yes, 17 is prime.".to_string(),
+                    reasoning_content: Some("Not divisible by 2 or 3.".to_string()),
+                },
+                Message::User {
+                    content: "What about 18?".to_string(),
+                },
+            ],
+        }
+    }
+
+    fn answered_first_turn() -> Chat {
+        let mut chat = second_turn();
+        chat.messages.truncate(2);
+        chat
+    }
+
+    #[test]
+    fn test_prefix_delta() {
+        let delta = prefix_delta(&[1, 2, 3, 4], &[1, 2, 5]);
+        assert_eq!(delta.shared_len, 2);
+        assert_eq!(delta.delta, vec![5]);
+        assert!(!delta.is_append_only());
+
+        let delta = prefix_delta(&[1, 2], &[1, 2, 3]);
+        assert!(delta.is_append_only());
+        assert_eq!(delta.delta, vec![3]);
+    }
+
+    #[test]
+    fn test_stable_history_is_append_only() {
+        let options = RenderOptions::new(ReasoningEnabled::Yes);
+        let delta = chat_delta(
+            &options,
+            &answered_first_turn(),
+            PrefillType::None,
+            &second_turn(),
+            PrefillType::Canonical,
+        )
+        .unwrap();
+        assert!(delta.is_append_only());
+    }
+
+    #[test]
+    fn test_canonical_history_rewrites_reasoning() {
+        let options = RenderOptions {
+            history: HistoryRendering::Canonical,
+            ..RenderOptions::new(ReasoningEnabled::Yes)
+        };
+        // The answered turn keeps its reasoning while it's last, then loses it.
+        let delta = chat_delta(
+            &options,
+            &answered_first_turn(),
+            PrefillType::Canonical,
+            &second_turn(),
+            PrefillType::Canonical,
+        )
+        .unwrap();
+        assert!(!delta.is_append_only());
+    }
+
+    #[test]
+    fn test_stable_prompt_extends_previous_prompt() {
+        let options = RenderOptions::new(ReasoningEnabled::Yes);
+        let previous = options
+            .render(&first_turn(), PrefillType::Canonical)
+            .unwrap();
+        let next = options
+            .render(&second_turn(), PrefillType::Canonical)
+            .unwrap();
+        assert!(next.starts_with(&previous));
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod pretokenization_tests {
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, tokenize};
+    use nai_tokenizers::pretokenization::*;
+
+    fn check(text: &str) -> Explanation {
+        let explanation = explain_splits(text).unwrap();
+        // The splits tile the input and their tokens are exactly what encode produces.
+        let mut end = 0;
+        for split in &explanation.splits {
+            assert_eq!(split.start, end, "{:?}", explanation);
+            assert_eq!(split.text, text[split.start..split.end]);
+            assert!(!split.tokens.is_empty());
+            end = split.end;
+        }
+        assert_eq!(end, text.len());
+        let ids: Vec<u32> = explanation
+            .splits
+            .iter()
+            .flat_map(|split| split.tokens.iter().map(|token| token.id))
+            .collect();
+        assert_eq!(ids, tokenize(text, SpecialTokens::Keep).unwrap());
+        assert_eq!(explanation.token_count, ids.len());
+        explanation
+    }
+
+    #[test]
+    fn test_splits_match_encode() {
+        for text in [
+            "Hello, world! It's 2025.",
+            "  leading spaces\n\nand newlines\t tabs",
+            "日本語のテキスト 🎉🎉",
+            "",
+        ] {
+            check(text);
+        }
+    }
+
+    #[test]
+    fn test_words_and_numbers_split() {
+        let explanation = check("Hello world 12345");
+        let texts: Vec<&str> = explanation
+            .splits
+            .iter()
+            .map(|split| split.text.as_str())
+            .collect();
+        assert!(texts.len() >= 3, "{:?}", texts);
+        assert_eq!(texts[0], "Hello");
+        assert!(texts.contains(&" world"));
+        // ByteLevel maps the leading space.
+        let world = &explanation.splits[texts.iter().position(|&t| t == " world").unwrap()];
+        assert_eq!(world.pretoken, "Ġworld");
+        assert_eq!(explanation.normalized, "Hello world 12345");
+    }
+
+    #[test]
+    fn test_added_tokens_are_their_own_split() {
+        let explanation = check("<|user|>hi<|assistant|>");
+        let added: Vec<&Split> = explanation.splits.iter().filter(|s| s.added).collect();
+        assert_eq!(added.len(), 2);
+        assert_eq!(added[0].text, "<|user|>");
+        assert_eq!(added[0].tokens.len(), 1);
+        assert_eq!(added[0].tokens[0].token, "<|user|>");
+        assert!(
+            explanation
+                .splits
+                .iter()
+                .any(|s| !s.added && s.text == "hi")
+        );
+    }
+}
@@ -0,0 +1,95 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod special_tokens_tests {
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, detokenize, tokenize};
+    use nai_tokenizers::special_tokens::*;
+
+    const TEXT: &str = "<|user|>hi there<|assistant|>hello";
+
+    fn user_id() -> u32 {
+        tokenize("<|user|>", SpecialTokens::Keep).unwrap()[0]
+    }
+
+    #[test]
+    fn test_allow_all_matches_tokenize() {
+        assert_eq!(
+            tokenize_with_policy(TEXT, &EncodePolicy::allow_all()).unwrap(),
+            tokenize(TEXT, SpecialTokens::Keep).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_default_disallows_everything() {
+        let error = tokenize_with_policy(TEXT, &EncodePolicy::default()).unwrap_err();
+        assert!(error.to_string().contains("<|user|>"), "{}", error);
+        assert!(tokenize_with_policy("plain text", &EncodePolicy::default()).is_ok());
+    }
+
+    #[test]
+    fn test_allowed_and_literal() {
+        let policy = EncodePolicy {
+            allowed: TokenSet::only(["<|user|>"]),
+            disallowed: TokenSet::default(),
+        };
+        let ids = tokenize_with_policy(TEXT, &policy).unwrap();
+        assert_eq!(ids[0], user_id());
+        assert_eq!(ids.iter().filter(|&&id| id == user_id()).count(), 1);
+        // `<|assistant|>` is spelled out in regular tokens.
+        let assistant = tokenize("<|assistant|>", SpecialTokens::Keep).unwrap()[0];
+        assert!(!ids.contains(&assistant));
+        assert_eq!(detokenize(&ids, SpecialTokens::Keep).unwrap(), TEXT);
+
+        let literal = tokenize_with_policy(TEXT, &EncodePolicy::literal()).unwrap();
+        assert!(!literal.contains(&user_id()));
+        assert_eq!(detokenize(&literal, SpecialTokens::Keep).unwrap(), TEXT);
+    }
+
+    #[test]
+    fn test_disallowed_list() {
+        let policy = EncodePolicy {
+            allowed: TokenSet::only(["<|user|>"]),
+            disallowed: TokenSet::only(["<|assistant|>"]),
+        };
+        assert!(tokenize_with_policy(TEXT, &policy).is_err());
+        assert!(tokenize_with_policy("<|user|>hi", &policy).is_ok());
+
+        let overlapping = EncodePolicy {
+            allowed: TokenSet::only(["<|user|>"]),
+            disallowed: TokenSet::only(["<|user|>"]),
+        };
+        assert!(tokenize_with_policy("hi", &overlapping).is_err());
+        let unknown = EncodePolicy {
+            allowed: TokenSet::only(["<|usr|>"]),
+            ..EncodePolicy::literal()
+        };
+        assert!(tokenize_with_policy("hi", &unknown).is_err());
+    }
+
+    #[test]
+    fn test_decode_skip() {
+        let ids = tokenize(TEXT, SpecialTokens::Keep).unwrap();
+        let skip_user = DecodePolicy {
+            skip: TokenSet::only(["<|user|>"]),
+        };
+        assert_eq!(
+            detokenize_with_policy(&ids, &skip_user).unwrap(),
+            "hi there<|assistant|>hello"
+        );
+        for mode in [SpecialTokens::Keep, SpecialTokens::Ignore] {
+            assert_eq!(
+                detokenize_with_policy(&ids, &mode.into()).unwrap(),
+                detokenize(&ids, mode).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_deserialize_token_set() {
+        let policy: EncodePolicy =
+            serde_json::from_str(r#"{"allowed": "all", "disallowed": ["<|user|>"]}"#).unwrap();
+        assert_eq!(policy.allowed, TokenSet::All);
+        assert_eq!(policy.disallowed, TokenSet::only(["<|user|>"]));
+        assert!(serde_json::from_str::<TokenSet>(r#""some""#).is_err());
+        let policy: EncodePolicy = serde_json::from_str("{}").unwrap();
+        assert_eq!(policy, EncodePolicy::default());
+    }
+}
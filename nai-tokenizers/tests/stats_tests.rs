@@ -0,0 +1,105 @@
+#[cfg(feature = "stats")]
+mod stats_tests {
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, tokenize};
+    use nai_tokenizers::stats::*;
+
+    #[test]
+    fn test_accumulator_totals() {
+        let documents = ["hello world", "this is a test<|user|>", "a"];
+        let mut accumulator = StatsAccumulator::default();
+        for document in documents {
+            accumulator.add_document(document).unwrap();
+        }
+        let stats = accumulator.finish(3);
+
+        let lengths: Vec<u64> = documents
+            .iter()
+            .map(|d| tokenize(d, SpecialTokens::Keep).unwrap().len() as u64)
+            .collect();
+        assert_eq!(stats.documents, 3);
+        assert_eq!(stats.tokens, lengths.iter().sum::<u64>());
+        assert_eq!(
+            stats.bytes,
+            documents.iter().map(|d| d.len() as u64).sum::<u64>()
+        );
+        let summary = stats.tokens_per_document.unwrap();
+        assert_eq!(summary.min, *lengths.iter().min().unwrap());
+        assert_eq!(summary.max, *lengths.iter().max().unwrap());
+        assert_eq!(
+            stats
+                .length_histogram
+                .iter()
+                .map(|b| b.documents)
+                .sum::<u64>(),
+            3
+        );
+        assert!(stats.top_tokens.len() <= 3);
+        assert_eq!(stats.special_tokens.len(), 1);
+        assert_eq!(stats.special_tokens[0].text, "<|user|>");
+    }
+
+    #[test]
+    fn test_merge_matches_single_pass() {
+        let mut whole = StatsAccumulator::default();
+        whole.add_document("hello world").unwrap();
+        whole.add_document("goose").unwrap();
+
+        let mut left = StatsAccumulator::default();
+        left.add_document("hello world").unwrap();
+        let mut right = StatsAccumulator::default();
+        right.add_document("goose").unwrap();
+        left.merge(right);
+
+        let (whole, merged) = (whole.finish(10), left.finish(10));
+        assert_eq!(whole.tokens, merged.tokens);
+        assert_eq!(whole.chars, merged.chars);
+        assert_eq!(
+            serde_json::to_string(&whole.top_tokens).unwrap(),
+            serde_json::to_string(&merged.top_tokens).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_empty_corpus() {
+        let stats = StatsAccumulator::default().finish(10);
+        assert_eq!(stats.tokens, 0);
+        assert_eq!(stats.chars_per_token, 0.0);
+        assert!(stats.tokens_per_document.is_none());
+    }
+
+    #[test]
+    fn test_corpus_stats_jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.jsonl");
+        std::fs::write(
+            &path,
+            "{\"text\": \"hello world\"}\n{\"body\": \"no text\"}\n\n{\"text\": \"goose\"}\n",
+        )
+        .unwrap();
+        let format = InputFormat::Jsonl {
+            field: "text".to_string(),
+        };
+        let stats = corpus_stats(&[path], &format, 5).unwrap();
+        assert_eq!(stats.documents, 2);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.chars, 16);
+    }
+
+    #[test]
+    fn test_corpus_stats_skips_invalid_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.jsonl");
+        std::fs::write(
+            &path,
+            b"{\"text\": \"hello world\"}\n{\"text\": \"bad \xff\"}\n{\"text\": \"goose\"}\n",
+        )
+        .unwrap();
+        let format = InputFormat::Jsonl {
+            field: "text".to_string(),
+        };
+        let stats = corpus_stats(&[path], &format, 5).unwrap();
+        assert_eq!(stats.documents, 2);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.chars, 16);
+    }
+}
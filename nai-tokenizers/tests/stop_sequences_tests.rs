@@ -0,0 +1,67 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod stop_sequences_tests {
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, tokenize};
+    use nai_tokenizers::stop_sequences::*;
+
+    fn run(matcher: &mut StopMatcher, text: &str) -> (String, Option<StopMatch>) {
+        let mut out = String::new();
+        for id in tokenize(text, SpecialTokens::Keep).unwrap() {
+            let update = matcher.push(id).unwrap();
+            out.push_str(&update.text);
+            if update.stop.is_some() {
+                return (out, update.stop);
+            }
+        }
+        out.push_str(&matcher.finish());
+        (out, None)
+    }
+
+    #[test]
+    fn test_default_stops_on_role_sentinel() {
+        let mut matcher = StopMatcher::default();
+        let (text, stop) = run(&mut matcher, "The answer is 4.<|user|>And 5?");
+        assert_eq!(text, "The answer is 4.");
+        assert_eq!(stop.unwrap().sequence, "<|user|>");
+        assert!(matcher.stopped().is_some());
+        assert_eq!(matcher.push_bytes(b"more"), StopUpdate::default());
+    }
+
+    #[test]
+    fn test_stop_across_token_boundaries() {
+        let mut matcher = StopMatcher::new(["\n\nUser:"]);
+        let (text, stop) = run(&mut matcher, "Sure thing.\n\nUser: hi");
+        assert_eq!(text, "Sure thing.");
+        assert_eq!(stop.unwrap().index, 0);
+
+        // Byte by byte, nothing that could still become the stop is emitted.
+        let mut matcher = StopMatcher::new(["END"]);
+        assert_eq!(matcher.push_bytes(b"abcE").text, "abc");
+        assert_eq!(matcher.push_bytes(b"N").text, "");
+        assert_eq!(matcher.push_bytes(b"d").text, "ENd");
+        let update = matcher.push_bytes(b"xEND");
+        assert_eq!(update.text, "x");
+        assert!(update.stop.is_some());
+    }
+
+    #[test]
+    fn test_holds_back_partial_utf8() {
+        let mut matcher = StopMatcher::new(["STOP"]);
+        let bytes = "\u{1f600}".as_bytes();
+        assert_eq!(matcher.push_bytes(&bytes[..2]).text, "");
+        assert_eq!(matcher.push_bytes(&bytes[2..]).text, "\u{1f600}");
+    }
+
+    #[test]
+    fn test_no_match_flushes_everything() {
+        let mut matcher = StopMatcher::new(["<|observation|>", "obs"]);
+        let (text, stop) = run(&mut matcher, "this is a test where da goose is cooked <|o");
+        assert_eq!(text, "this is a test where da goose is cooked <|o");
+        assert!(stop.is_none());
+
+        // Earliest match wins regardless of list order.
+        matcher.reset();
+        let update = matcher.push_bytes(b"x obs <|observation|>");
+        assert_eq!(update.text, "x ");
+        assert_eq!(update.stop.unwrap().sequence, "obs");
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod token_healing_tests {
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, token_bytes, tokenize};
+    use nai_tokenizers::token_healing::*;
+
+    #[test]
+    fn test_token_bytes_round_trip() {
+        let text = "this is a test where da goose is cooked<|user|>\u{e9}\u{1f600}";
+        let bytes: Vec<u8> = tokenize(text, SpecialTokens::Keep)
+            .unwrap()
+            .into_iter()
+            .flat_map(|id| token_bytes(id).unwrap())
+            .collect();
+        assert_eq!(bytes, text.as_bytes());
+    }
+
+    #[test]
+    fn test_heal_backs_off_last_token() {
+        let ids = tokenize("this is a test", SpecialTokens::Keep).unwrap();
+        let healing = heal(&ids, 1).unwrap();
+        assert_eq!(healing.ids, ids[..ids.len() - 1]);
+        assert_eq!(healing.prefix_text(), " test");
+
+        let index = VocabPrefixIndex::new();
+        let allowed = healing.allowed_tokens(&index);
+        assert!(allowed.contains(ids.last().unwrap()));
+        let space = tokenize(" ", SpecialTokens::Keep).unwrap();
+        assert!(allowed.contains(&space[0]));
+    }
+
+    #[test]
+    fn test_heal_stops_at_special_tokens() {
+        let healing = heal_prompt("<|assistant|>\n<think>ok</think>", 5).unwrap();
+        assert!(healing.is_satisfied());
+
+        let healing = heal_prompt("<|assistant|>hello", 5).unwrap();
+        assert_eq!(healing.prefix_text(), "hello");
+        assert_eq!(healing.ids.len(), 1);
+    }
+
+    #[test]
+    fn test_advance() {
+        let ids = tokenize(" test", SpecialTokens::Keep).unwrap();
+        let mut healing = TokenHealing {
+            ids: vec![],
+            prefix: b" te".to_vec(),
+        };
+        let space = tokenize(" ", SpecialTokens::Keep).unwrap()[0];
+        assert!(!healing.advance(space).unwrap());
+        assert_eq!(healing.prefix, b"te");
+        assert!(healing.advance(space).is_err());
+
+        let mut healing = TokenHealing {
+            ids: vec![],
+            prefix: b" te".to_vec(),
+        };
+        assert!(healing.advance(ids[0]).unwrap());
+    }
+
+    #[test]
+    fn test_index_queries() {
+        let index = VocabPrefixIndex::new();
+        let exact: Vec<u32> = index.exact(b" test").collect();
+        assert_eq!(exact, tokenize(" test", SpecialTokens::Keep).unwrap());
+        assert!(
+            index
+                .with_prefix(b" te")
+                .all(|id| token_bytes(id).unwrap().starts_with(b" te"))
+        );
+    }
+}
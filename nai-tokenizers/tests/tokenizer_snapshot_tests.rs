@@ -0,0 +1,51 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod tokenizer_snapshot_tests {
+    use nai_tokenizers::glm45_tokenizer;
+    use nai_tokenizers::tokenizer_snapshot::*;
+
+    const TOKENIZER_JSON: &[u8] = include_bytes!("../tokenizers/glm-4.5-tokenizer.json.br");
+
+    #[test]
+    fn test_snapshot_matches_json() {
+        let from_json = glm45_tokenizer::load_from_json(TOKENIZER_JSON).unwrap();
+        let from_snapshot = load(GLM45_SNAPSHOT).unwrap();
+
+        assert_eq!(
+            from_snapshot.get_vocab_size(true),
+            from_json.get_vocab_size(true)
+        );
+        let mut json_vocab: Vec<_> = from_json.get_vocab(true).into_iter().collect();
+        let mut snapshot_vocab: Vec<_> = from_snapshot.get_vocab(true).into_iter().collect();
+        json_vocab.sort();
+        snapshot_vocab.sort();
+        assert_eq!(snapshot_vocab, json_vocab);
+        assert_eq!(
+            from_snapshot.get_added_tokens_decoder(),
+            from_json.get_added_tokens_decoder()
+        );
+
+        let text =
+            "[gMASK]<sop>Hello, world!\n\n  indented\tcode() <|user|>naïve café \u{1f600}1234567";
+        for special in [true, false] {
+            let expected = from_json.encode(text, special).unwrap();
+            let encoding = from_snapshot.encode(text, special).unwrap();
+            assert_eq!(encoding.get_ids(), expected.get_ids());
+            assert_eq!(encoding.get_offsets(), expected.get_offsets());
+            assert_eq!(
+                from_snapshot.decode(encoding.get_ids(), !special).unwrap(),
+                from_json.decode(expected.get_ids(), !special).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_snapshot() {
+        assert!(read_from(&mut &b"NAITRIE\0\x01\0\0\0"[..]).is_err());
+        assert!(read_from(&mut &b"NAITOK\0\0\x02\0\0\0"[..]).is_err());
+
+        let mut data = Vec::new();
+        brotli::BrotliDecompress(&mut &GLM45_SNAPSHOT[..], &mut data).unwrap();
+        data.truncate(data.len() - 3);
+        assert!(read_from(&mut &data[..]).is_err());
+    }
+}
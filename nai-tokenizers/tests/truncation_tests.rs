@@ -0,0 +1,108 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod truncation_tests {
+    use nai_tokenizers::glm45_tokenizer::{SpecialTokens, tokenize};
+    use nai_tokenizers::truncation::*;
+
+    const TEXT: &str = "The quick brown fox jumps over the lazy dog. \
+        Pack my box with five dozen liquor jugs! 日本語のテキストも少し。 🎉🎉 done.";
+
+    fn count(text: &str) -> usize {
+        tokenize(text, SpecialTokens::Ignore).unwrap().len()
+    }
+
+    fn truncate_to(max_tokens: usize, strategy: TruncationStrategy) -> Truncation {
+        let options = TruncateOptions::new(max_tokens, strategy);
+        let truncation = truncate(TEXT, &options, SpecialTokens::Ignore).unwrap();
+        assert_eq!(truncation.tokens, count(&truncation.text));
+        assert!(truncation.tokens <= max_tokens, "{:?}", truncation);
+        truncation
+    }
+
+    fn dropped_range(truncation: &Truncation) -> std::ops::Range<usize> {
+        match truncation.dropped.as_slice() {
+            [dropped] => dropped.clone(),
+            _ => panic!("expected one dropped range: {:?}", truncation),
+        }
+    }
+
+    #[test]
+    fn test_fits_unchanged() {
+        for strategy in [
+            TruncationStrategy::KeepHead,
+            TruncationStrategy::KeepTail,
+            TruncationStrategy::KeepEnds,
+        ] {
+            let truncation = truncate_to(count(TEXT), strategy);
+            assert_eq!(truncation.text, TEXT);
+            assert!(!truncation.truncated());
+        }
+    }
+
+    #[test]
+    fn test_keep_head() {
+        for max_tokens in [0, 1, 5, 10, count(TEXT) - 1] {
+            let truncation = truncate_to(max_tokens, TruncationStrategy::KeepHead);
+            assert!(TEXT.starts_with(&truncation.text));
+            assert_eq!(
+                dropped_range(&truncation),
+                truncation.text.len()..TEXT.len()
+            );
+            // One more character would go over.
+            let next = TEXT[truncation.text.len()..].chars().next().unwrap();
+            let longer = &TEXT[..truncation.text.len() + next.len_utf8()];
+            assert!(count(longer) > max_tokens);
+        }
+    }
+
+    #[test]
+    fn test_keep_tail() {
+        for max_tokens in [0, 1, 5, 10, count(TEXT) - 1] {
+            let truncation = truncate_to(max_tokens, TruncationStrategy::KeepTail);
+            assert!(TEXT.ends_with(&truncation.text));
+            let start = TEXT.len() - truncation.text.len();
+            assert_eq!(dropped_range(&truncation), 0..start);
+            let previous = TEXT[..start].chars().next_back().unwrap();
+            assert!(count(&TEXT[start - previous.len_utf8()..]) > max_tokens);
+        }
+    }
+
+    #[test]
+    fn test_keep_ends() {
+        for max_tokens in [count("…"), 5, 10, 20, count(TEXT) - 1] {
+            let truncation = truncate_to(max_tokens, TruncationStrategy::KeepEnds);
+            let dropped = dropped_range(&truncation);
+            assert!(!dropped.is_empty());
+            let (head, tail) = (&TEXT[..dropped.start], &TEXT[dropped.end..]);
+            assert_eq!(truncation.text, format!("{}…{}", head, tail));
+        }
+
+        let options = TruncateOptions {
+            ellipsis: " [...] ".to_string(),
+            ..TruncateOptions::new(12, TruncationStrategy::KeepEnds)
+        };
+        let truncation = truncate(TEXT, &options, SpecialTokens::Ignore).unwrap();
+        assert!(truncation.text.contains(" [...] "));
+        assert!(truncation.tokens <= 12);
+
+        let too_small = TruncateOptions::new(0, TruncationStrategy::KeepEnds);
+        assert!(truncate(TEXT, &too_small, SpecialTokens::Ignore).is_err());
+    }
+
+    #[test]
+    fn test_special_tokens() {
+        let text = "<|user|>hello there<|assistant|>";
+        let options = TruncateOptions::new(2, TruncationStrategy::KeepHead);
+        let kept = truncate(text, &options, SpecialTokens::Keep).unwrap();
+        assert!(kept.tokens <= 2);
+        assert!(kept.text.starts_with("<|user|>"));
+        assert_eq!(
+            tokenize(&kept.text, SpecialTokens::Keep).unwrap().len(),
+            kept.tokens
+        );
+
+        let options = TruncateOptions::new(2, TruncationStrategy::KeepTail);
+        let kept = truncate(text, &options, SpecialTokens::Keep).unwrap();
+        assert!(kept.tokens <= 2);
+        assert!(kept.text.ends_with("<|assistant|>"));
+    }
+}
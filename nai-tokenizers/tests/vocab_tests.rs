@@ -0,0 +1,113 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod vocab_tests {
+    use nai_tokenizers::glm45_tokenizer::{self, SpecialTokens};
+    use nai_tokenizers::vocab::*;
+
+    #[test]
+    fn test_token_info() {
+        let ids = glm45_tokenizer::tokenize(" hello", SpecialTokens::Ignore).unwrap();
+        let info = token_info(ids[ids.len() - 1]).unwrap();
+        assert!(!info.added);
+        assert!(!info.special);
+        assert_eq!(info.text.as_bytes(), info.bytes.as_slice());
+        assert_eq!(token_to_id(&info.token), Some(info.id));
+        assert_eq!(bytes_to_id(&info.bytes), Some(info.id));
+
+        let user = glm45_tokenizer::tokenize("<|user|>", SpecialTokens::Keep).unwrap();
+        let info = token_info(user[0]).unwrap();
+        assert!(info.added);
+        assert!(info.special);
+        assert_eq!(info.token, "<|user|>");
+        assert_eq!(info.bytes, b"<|user|>");
+        assert_eq!(bytes_to_id(b"<|user|>"), Some(user[0]));
+
+        assert_eq!(token_info(glm45_tokenizer::vocab_size() as u32), None);
+        assert_eq!(token_to_id("definitely not a token"), None);
+    }
+
+    #[test]
+    fn test_every_byte_has_a_token() {
+        for byte in 0..=255u8 {
+            let id = bytes_to_id(&[byte]).unwrap();
+            assert_eq!(token_info(id).unwrap().bytes, [byte]);
+        }
+    }
+
+    #[test]
+    fn test_search_substring() {
+        let results = search(&SearchQuery {
+            pattern: "user".to_string(),
+            limit: usize::MAX,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(results.total, results.tokens.len());
+        assert!(results.tokens.iter().all(|t| t.text.contains("user")));
+        assert!(results.tokens.iter().any(|t| t.token == "<|user|>"));
+        assert!(results.tokens.windows(2).all(|w| w[0].id < w[1].id));
+
+        let without_added = search(&SearchQuery {
+            pattern: "user".to_string(),
+            exclude_added: true,
+            limit: usize::MAX,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert!(without_added.tokens.iter().all(|t| !t.added));
+        assert!(without_added.total < results.total);
+
+        let ignore_case = search(&SearchQuery {
+            pattern: "USER".to_string(),
+            ignore_case: true,
+            limit: 0,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert!(ignore_case.total >= results.total);
+        assert!(ignore_case.tokens.is_empty());
+    }
+
+    #[test]
+    fn test_search_regex_and_paging() {
+        let query = SearchQuery {
+            pattern: r"^\d+$".to_string(),
+            regex: true,
+            limit: 5,
+            ..SearchQuery::default()
+        };
+        let first = search(&query).unwrap();
+        assert_eq!(first.tokens.len(), 5.min(first.total));
+        assert!(
+            first
+                .tokens
+                .iter()
+                .all(|t| t.text.chars().all(|c| c.is_ascii_digit()))
+        );
+
+        let second = search(&SearchQuery {
+            offset: 5,
+            ..query.clone()
+        })
+        .unwrap();
+        assert_eq!(second.total, first.total);
+        if let (Some(last), Some(next)) = (first.tokens.last(), second.tokens.first()) {
+            assert!(last.id < next.id);
+        }
+
+        let everything = search(&SearchQuery {
+            limit: 1,
+            ..SearchQuery::default()
+        })
+        .unwrap();
+        assert_eq!(everything.total, glm45_tokenizer::vocab_size());
+
+        assert!(
+            search(&SearchQuery {
+                pattern: "(".to_string(),
+                regex: true,
+                ..SearchQuery::default()
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(feature = "glm45_tokenizer")]
+mod vocab_trie_tests {
+    use nai_tokenizers::glm45_tokenizer::{
+        SpecialTokens, is_special_token, token_bytes, tokenize, vocab_size,
+    };
+    use nai_tokenizers::vocab_trie::*;
+
+    #[test]
+    fn test_with_prefix_matches_vocab() {
+        let trie = VocabTrie::new(false);
+        let prefix = b" te";
+        let mut expected: Vec<u32> = (0..vocab_size() as u32)
+            .filter(|&id| !is_special_token(id))
+            .filter(|&id| token_bytes(id).is_some_and(|b| b.starts_with(prefix)))
+            .collect();
+        let mut found = trie.with_prefix(prefix).to_vec();
+        expected.sort_unstable();
+        found.sort_unstable();
+        assert_eq!(found, expected);
+        assert!(trie.with_prefix(b"\xff\xff\xff\xff").is_empty());
+        assert_eq!(trie.with_prefix(b"").len(), trie.len());
+    }
+
+    #[test]
+    fn test_exact_and_prefixes() {
+        let trie = VocabTrie::new(false);
+        let test = tokenize(" test", SpecialTokens::Keep).unwrap();
+        assert_eq!(trie.exact(b" test"), test.as_slice());
+
+        let prefixes = trie.prefixes_of(b" test");
+        assert_eq!(prefixes.last(), Some(&test[0]));
+        for id in &prefixes {
+            assert!(b" test".starts_with(&token_bytes(*id).unwrap()));
+        }
+
+        let compatible = trie.compatible(b" te");
+        assert!(compatible.contains(&test[0]));
+        assert!(compatible.contains(&tokenize(" ", SpecialTokens::Keep).unwrap()[0]));
+    }
+
+    #[test]
+    fn test_special_tokens() {
+        let user = tokenize("<|user|>", SpecialTokens::Keep).unwrap();
+        assert!(VocabTrie::new(false).exact(b"<|user|>").is_empty());
+        assert_eq!(VocabTrie::new(true).exact(b"<|user|>"), user.as_slice());
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vocab.trie");
+        let trie = VocabTrie::load_or_build(&path, false).unwrap();
+        assert!(path.exists());
+
+        let loaded = VocabTrie::load(&path).unwrap();
+        assert_eq!(loaded.len(), trie.len());
+        assert_eq!(loaded.with_prefix(b" te"), trie.with_prefix(b" te"));
+
+        std::fs::write(&path, b"garbage").unwrap();
+        assert!(VocabTrie::load(&path).is_err());
+        let rebuilt = VocabTrie::load_or_build(&path, false).unwrap();
+        assert_eq!(rebuilt.len(), trie.len());
+        assert!(VocabTrie::load(&path).is_ok());
+    }
+
+    #[test]
+    fn test_corrupt_cache() {
+        let mut bytes = Vec::new();
+        VocabTrie::new(false).write_to(&mut bytes).unwrap();
+        assert!(VocabTrie::from_bytes(&bytes).is_ok());
+        let set_u32 = |bytes: &mut Vec<u8>, offset: usize, value: u32| {
+            bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+
+        // Truncated input and header counts that don't fit the input.
+        assert!(VocabTrie::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        for count_offset in [20, 24, 28] {
+            let mut corrupt = bytes.clone();
+            set_u32(&mut corrupt, count_offset, u32::MAX);
+            assert!(VocabTrie::from_bytes(&corrupt).is_err());
+        }
+
+        // The root's children range points past the edges.
+        let mut corrupt = bytes.clone();
+        set_u32(&mut corrupt, 36, u32::MAX);
+        assert!(VocabTrie::from_bytes(&corrupt).is_err());
+
+        // An out-of-range token id at the end.
+        let mut corrupt = bytes.clone();
+        let last = corrupt.len() - 4;
+        set_u32(&mut corrupt, last, vocab_size() as u32);
+        assert!(VocabTrie::from_bytes(&corrupt).is_err());
+    }
+}